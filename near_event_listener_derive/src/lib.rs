@@ -0,0 +1,172 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, LitStr};
+
+#[derive(Default)]
+struct EventAttributes {
+    standard: Option<LitStr>,
+    version: Option<LitStr>,
+    event: Option<LitStr>,
+}
+
+impl EventAttributes {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut attributes = Self::default();
+
+        for attr in attrs {
+            if !attr.path().is_ident("near_event") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                let value: LitStr = meta.value()?.parse()?;
+                if meta.path.is_ident("standard") {
+                    attributes.standard = Some(value);
+                } else if meta.path.is_ident("version") {
+                    attributes.version = Some(value);
+                } else if meta.path.is_ident("event") {
+                    attributes.event = Some(value);
+                } else {
+                    return Err(meta.error("expected `standard`, `version` or `event`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(attributes)
+    }
+}
+
+#[proc_macro_derive(NearEvent, attributes(near_event))]
+pub fn derive_near_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let attributes = EventAttributes::parse(&input.attrs)?;
+    let standard = attributes.standard.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "missing #[near_event(standard = \"...\")] attribute",
+        )
+    })?;
+
+    let version_check = match &attributes.version {
+        Some(version) => quote! { event_log.version == #version },
+        None => quote! { true },
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (event_names, decode) = match &input.data {
+        Data::Struct(_) => {
+            let event = attributes
+                .event
+                .unwrap_or_else(|| LitStr::new(&to_snake_case(&ident.to_string()), ident.span()));
+
+            let decode = quote! { event_log.parse_data::<Self>() };
+            (vec![event], decode)
+        }
+        Data::Enum(data) => {
+            if attributes.event.is_some() {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`event` is set per variant on enums",
+                ));
+            }
+
+            let mut event_names = Vec::new();
+            let mut arms = Vec::new();
+
+            for variant in &data.variants {
+                let variant_ident = &variant.ident;
+                let event = EventAttributes::parse(&variant.attrs)?
+                    .event
+                    .unwrap_or_else(|| {
+                        LitStr::new(
+                            &to_snake_case(&variant_ident.to_string()),
+                            variant_ident.span(),
+                        )
+                    });
+
+                let arm =
+                    match &variant.fields {
+                        Fields::Unit => quote! { #event => Ok(Self::#variant_ident), },
+                        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                            quote! { #event => Ok(Self::#variant_ident(event_log.parse_data()?)), }
+                        }
+                        _ => return Err(syn::Error::new_spanned(
+                            variant,
+                            "NearEvent variants must be unit variants or hold exactly one field",
+                        )),
+                    };
+
+                event_names.push(event);
+                arms.push(arm);
+            }
+
+            let decode = quote! {
+                match event_log.event.as_str() {
+                    #(#arms)*
+                    other => Err(::near_event_listener::ListenerError::InvalidEventFormat(
+                        format!("Unexpected event: {}", other),
+                    )),
+                }
+            };
+            (event_names, decode)
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "NearEvent cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::near_event_listener::NearEvent for #ident #ty_generics #where_clause {
+            fn matches(event_log: &::near_event_listener::EventLog) -> bool {
+                event_log.standard == #standard
+                    && #version_check
+                    && [#(#event_names),*].contains(&event_log.event.as_str())
+            }
+
+            fn from_event_log(
+                event_log: &::near_event_listener::EventLog,
+            ) -> Result<Self, ::near_event_listener::ListenerError> {
+                if !<Self as ::near_event_listener::NearEvent>::matches(event_log) {
+                    return Err(::near_event_listener::ListenerError::InvalidEventFormat(format!(
+                        "Event {} {} {} does not match {}",
+                        event_log.standard,
+                        event_log.version,
+                        event_log.event,
+                        stringify!(#ident),
+                    )));
+                }
+
+                #decode
+            }
+        }
+    })
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
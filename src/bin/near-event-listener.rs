@@ -0,0 +1,120 @@
+use clap::{Parser, ValueEnum};
+use futures::StreamExt;
+use near_event_listener::{EventEnvelope, EventTemplate, NearEventListener, StartPosition};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    #[value(help = "One JSON envelope per line")]
+    Json,
+    #[value(help = "Aligned columns for reading in a terminal")]
+    Table,
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "near-event-listener",
+    version,
+    about = "Prints the NEP-297 events emitted by NEAR contracts"
+)]
+struct Args {
+    #[arg(long, help = "JSON-RPC endpoint to poll")]
+    rpc_url: String,
+    #[arg(
+        long = "account",
+        required = true,
+        help = "Account to watch, may contain * and ? wildcards; repeatable"
+    )]
+    accounts: Vec<String>,
+    #[arg(
+        long = "method",
+        help = "Method to watch; repeatable, every method when omitted"
+    )]
+    methods: Vec<String>,
+    #[arg(long, help = "First block to read [default: the latest block]")]
+    from_block: Option<u64>,
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    #[arg(
+        long,
+        conflicts_with = "format",
+        help = "Print each event with this Jinja template, e.g. \"{{ event }} by {{ signer_id }}\""
+    )]
+    template: Option<String>,
+    #[arg(long, help = "Append events to this file instead of printing them")]
+    output: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let (patterns, account_ids): (Vec<&str>, Vec<&str>) = args
+        .accounts
+        .iter()
+        .map(String::as_str)
+        .partition(|account| account.contains(['*', '?']));
+    let mut builder = NearEventListener::builder(&args.rpc_url).account_ids(&account_ids);
+    for pattern in patterns {
+        builder = builder.account_pattern(pattern);
+    }
+    for method in &args.methods {
+        builder = builder.method_name(method);
+    }
+    builder = builder.start_from(match args.from_block {
+        Some(height) => StartPosition::Height(height),
+        None => StartPosition::Latest,
+    });
+    let mut listener = builder.build()?;
+    let template = args
+        .template
+        .as_deref()
+        .map(EventTemplate::new)
+        .transpose()?;
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::options().create(true).append(true).open(path)?,
+        )),
+        None => Box::new(io::stdout()),
+    };
+
+    if let (None, Format::Table) = (&template, args.format) {
+        writeln!(
+            out,
+            "{:<12} {:<44} {:<32} {:<12} {:<16} DATA",
+            "BLOCK", "TX / RECEIPT", "ACCOUNT", "STANDARD", "EVENT"
+        )?;
+    }
+
+    let mut events = listener.stream();
+    while let Some(envelope) = events.next().await {
+        let envelope = envelope?;
+        match (&template, args.format) {
+            (Some(template), _) => writeln!(out, "{}", template.render(&envelope)?)?,
+            (None, Format::Json) => writeln!(out, "{}", serde_json::to_string(&envelope)?)?,
+            (None, Format::Table) => write_row(&mut out, &envelope)?,
+        }
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+fn write_row(out: &mut dyn Write, envelope: &EventEnvelope) -> io::Result<()> {
+    let context = &envelope.context;
+    let event = &envelope.event;
+    let origin = context
+        .tx_hash
+        .as_deref()
+        .or(context.receipt_id.as_deref())
+        .unwrap_or_default();
+
+    writeln!(
+        out,
+        "{:<12} {:<44} {:<32} {:<12} {:<16} {}",
+        context.block_height, origin, context.account_id, event.standard, event.event, event.data
+    )
+}
@@ -0,0 +1,96 @@
+use near_primitives::hash::CryptoHash;
+use near_primitives::views::{BlockView, ChunkView};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::Arc;
+
+// Keeps the most recently used entries; the least recently used one is
+// evicted once full.
+#[derive(Debug)]
+struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, V>,
+    order: VecDeque<K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        let value = self.entries.get(key)?.clone();
+        self.touch(key);
+        Some(value)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.entries.insert(key.clone(), value).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    fn touch(&mut self, key: &K) {
+        if let Some(position) = self.order.iter().position(|cached| cached == key) {
+            if let Some(key) = self.order.remove(position) {
+                self.order.push_back(key);
+            }
+        }
+    }
+}
+
+// Blocks and chunks already fetched from the node. Chunks are keyed by hash.
+// Blocks are keyed by hash, and by height once final, since the block at a
+// non-final height can still change.
+#[derive(Debug)]
+pub(crate) struct ResponseCache {
+    blocks: LruCache<CryptoHash, BlockView>,
+    final_heights: LruCache<u64, CryptoHash>,
+    chunks: LruCache<CryptoHash, Arc<ChunkView>>,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            blocks: LruCache::new(capacity),
+            final_heights: LruCache::new(capacity),
+            chunks: LruCache::new(capacity),
+        }
+    }
+
+    pub(crate) fn block_by_hash(&mut self, hash: &CryptoHash) -> Option<BlockView> {
+        self.blocks.get(hash)
+    }
+
+    pub(crate) fn final_block(&mut self, height: u64) -> Option<BlockView> {
+        let hash = self.final_heights.get(&height)?;
+        self.blocks.get(&hash)
+    }
+
+    pub(crate) fn insert_block(&mut self, block: &BlockView, is_final: bool) {
+        if is_final {
+            self.final_heights
+                .insert(block.header.height, block.header.hash);
+        }
+        self.blocks.insert(block.header.hash, block.clone());
+    }
+
+    pub(crate) fn chunk(&mut self, hash: &CryptoHash) -> Option<Arc<ChunkView>> {
+        self.chunks.get(hash)
+    }
+
+    pub(crate) fn insert_chunk(&mut self, chunk: Arc<ChunkView>) {
+        self.chunks.insert(chunk.header.chunk_hash, chunk);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{DeliveredEvent, EventEnvelope, ListenerError};
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+pub trait CheckpointStore: Send + Sync {
+    fn load(&self) -> Result<Option<u64>, ListenerError>;
+
+    fn save(&self, height: u64) -> Result<(), ListenerError>;
+
+    // Called before the events of `height` are delivered. Stores that record
+    // it should make `load` return the block before it until `save_block`
+    // marks `height` as done, so that an interrupted block is delivered again.
+    // Events saved by `save_delivered` are kept if `height` is the block that
+    // was already in progress, and dropped otherwise.
+    fn begin_block(&self, height: u64) -> Result<(), ListenerError> {
+        let _ = height;
+        Ok(())
+    }
+
+    // Called once the events of `height` have been delivered.
+    fn save_block(&self, height: u64, envelopes: &[EventEnvelope]) -> Result<(), ListenerError> {
+        let _ = envelopes;
+        self.save(height)
+    }
+
+    fn revert_to(&self, height: u64) -> Result<(), ListenerError> {
+        self.save(height)
+    }
+
+    // The sequence number of the last delivered event, as saved by
+    // `save_progress`. Stores that do not keep it start every run from zero.
+    fn load_sequence(&self) -> Result<Option<u64>, ListenerError> {
+        Ok(None)
+    }
+
+    // Called instead of `save_block` by the listener, with the sequence number
+    // of the last event delivered so far. Stores that keep it should write it
+    // together with the block.
+    fn save_progress(
+        &self,
+        height: u64,
+        sequence: u64,
+        envelopes: &[EventEnvelope],
+    ) -> Result<(), ListenerError> {
+        let _ = sequence;
+        self.save_block(height, envelopes)
+    }
+
+    // Called in exactly-once mode once an event of the block in progress,
+    // `height`, has been delivered. Stores that keep these should return them
+    // from `load_delivered` until the block is saved, so that the listener
+    // skips them when the block is delivered again after a restart.
+    fn save_delivered(&self, height: u64, event: &DeliveredEvent) -> Result<(), ListenerError> {
+        let _ = (height, event);
+        Ok(())
+    }
+
+    // The events of the interrupted block that were already delivered.
+    fn load_delivered(&self) -> Result<Vec<DeliveredEvent>, ListenerError> {
+        Ok(Vec::new())
+    }
+}
+
+// Keeps a single record, `processing <height>` or `done <height>`, followed by
+// the last sequence number once there is one. A `processing` record is
+// followed by one line of JSON per event of the block delivered so far. The
+// file is replaced atomically by writing a temporary file and renaming it.
+// Files holding only a height, as written by earlier versions, count as done.
+#[derive(Debug, Clone)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn read(&self) -> Result<Option<CheckpointRecord>, ListenerError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(ListenerError::CheckpointError(e.to_string())),
+        };
+        let invalid = |reason: String| {
+            ListenerError::CheckpointError(format!(
+                "Invalid checkpoint in {}: {}",
+                self.path.display(),
+                reason
+            ))
+        };
+
+        let mut lines = contents.lines();
+        let mut fields = lines.next().unwrap_or_default().split_whitespace();
+        let (in_progress, height) = match fields.next() {
+            Some("processing") => (true, fields.next()),
+            Some("done") => (false, fields.next()),
+            height => (false, height),
+        };
+        let height = height
+            .unwrap_or_default()
+            .parse::<u64>()
+            .map_err(|e| invalid(e.to_string()))?;
+        let sequence = fields
+            .next()
+            .map(str::parse::<u64>)
+            .transpose()
+            .map_err(|e| invalid(e.to_string()))?;
+        let delivered = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| invalid(e.to_string()))?;
+
+        Ok(Some(CheckpointRecord {
+            height,
+            in_progress,
+            sequence,
+            delivered,
+        }))
+    }
+
+    // Writes a new record, keeping the sequence number of the current one
+    // unless a new one is given.
+    fn write(
+        &self,
+        state: &str,
+        height: u64,
+        sequence: Option<u64>,
+        delivered: &[DeliveredEvent],
+    ) -> Result<(), ListenerError> {
+        let sequence = match sequence {
+            Some(sequence) => Some(sequence),
+            None => self.read()?.and_then(|record| record.sequence),
+        };
+        let mut record = match sequence {
+            Some(sequence) => format!("{} {} {}", state, height, sequence),
+            None => format!("{} {}", state, height),
+        };
+        for event in delivered {
+            record.push('\n');
+            record.push_str(&serde_json::to_string(event)?);
+        }
+
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+
+        let result = File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(record.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temp_path, &self.path));
+        result.map_err(|e| ListenerError::CheckpointError(e.to_string()))
+    }
+
+    // The events saved for `height` if it is the block in progress.
+    fn in_progress_delivered(&self, height: u64) -> Result<Vec<DeliveredEvent>, ListenerError> {
+        Ok(self
+            .read()?
+            .filter(|record| record.in_progress && record.height == height)
+            .map(|record| record.delivered)
+            .unwrap_or_default())
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Result<Option<u64>, ListenerError> {
+        Ok(self.read()?.map(|record| {
+            if record.in_progress {
+                record.height.saturating_sub(1)
+            } else {
+                record.height
+            }
+        }))
+    }
+
+    fn save(&self, height: u64) -> Result<(), ListenerError> {
+        self.write("done", height, None, &[])
+    }
+
+    fn begin_block(&self, height: u64) -> Result<(), ListenerError> {
+        let delivered = self.in_progress_delivered(height)?;
+        self.write("processing", height, None, &delivered)
+    }
+
+    fn load_sequence(&self) -> Result<Option<u64>, ListenerError> {
+        Ok(self.read()?.and_then(|record| record.sequence))
+    }
+
+    fn save_progress(
+        &self,
+        height: u64,
+        sequence: u64,
+        _envelopes: &[EventEnvelope],
+    ) -> Result<(), ListenerError> {
+        self.write("done", height, Some(sequence), &[])
+    }
+
+    fn save_delivered(&self, height: u64, event: &DeliveredEvent) -> Result<(), ListenerError> {
+        let mut delivered = self.in_progress_delivered(height)?;
+        delivered.push(event.clone());
+        self.write("processing", height, None, &delivered)
+    }
+
+    fn load_delivered(&self) -> Result<Vec<DeliveredEvent>, ListenerError> {
+        Ok(self
+            .read()?
+            .filter(|record| record.in_progress)
+            .map(|record| record.delivered)
+            .unwrap_or_default())
+    }
+}
+
+struct CheckpointRecord {
+    height: u64,
+    in_progress: bool,
+    sequence: Option<u64>,
+    delivered: Vec<DeliveredEvent>,
+}
@@ -0,0 +1,337 @@
+use crate::{
+    DeliveryMode, EventFilter, ExecutionStatusFilter, FileCheckpointStore, FileDeadLetterSink,
+    HealthCheck, ListenerError, NearEventListenerBuilder, OverflowPolicy, RetryPolicy,
+    StartPosition,
+};
+use near_primitives::types::Finality;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+const ENV_PREFIX: &str = "NEAR_EVENT_LISTENER_";
+
+// Builder options that can be set without recompiling. Keys are named after the
+// builder methods; intervals are in milliseconds.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ListenerConfig {
+    rpc_url: Option<String>,
+    archival_rpc_url: Option<String>,
+    fallback_rpc_urls: Vec<String>,
+    rpc_api_key: Option<String>,
+    headers: BTreeMap<String, String>,
+    proxy: Option<String>,
+    connect_timeout_ms: Option<u64>,
+    account_ids: Vec<String>,
+    account_patterns: Vec<String>,
+    factories: Vec<String>,
+    method_names: Vec<String>,
+    standards: Vec<String>,
+    versions: Vec<String>,
+    events: Vec<String>,
+    log_prefixes: Vec<String>,
+    last_processed_block: Option<u64>,
+    start_from: Option<ConfigStart>,
+    expect_chain: Option<String>,
+    replay_last: Option<u64>,
+    until_height: Option<u64>,
+    max_events: Option<usize>,
+    finality: Option<String>,
+    delivery_on: Option<String>,
+    checkpoint_path: Option<PathBuf>,
+    dead_letter_path: Option<PathBuf>,
+    include_raw_logs: Option<bool>,
+    strict_validation: Option<bool>,
+    max_attempts: Option<u32>,
+    max_rps: Option<u32>,
+    request_timeout_ms: Option<u64>,
+    call_deadline_ms: Option<u64>,
+    health_check_interval_ms: Option<u64>,
+    max_head_age_ms: Option<u64>,
+    delivery: Option<String>,
+    dedupe_capacity: Option<usize>,
+    poll_interval_ms: Option<u64>,
+    catch_up_interval_ms: Option<u64>,
+    adaptive_polling: Option<bool>,
+    chunk_concurrency: Option<usize>,
+    transaction_concurrency: Option<usize>,
+    backfill_concurrency: Option<usize>,
+    delivery_queue: Option<usize>,
+    overflow: Option<String>,
+    shard_filter: Option<bool>,
+    cache_capacity: Option<usize>,
+}
+
+// `start_from` is either a block height or one of "checkpoint" and "latest".
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ConfigStart {
+    Height(u64),
+    Named(String),
+}
+
+impl ListenerConfig {
+    #[cfg(feature = "config")]
+    pub(crate) fn from_toml(contents: &str) -> Result<Self, ListenerError> {
+        toml::from_str(contents).map_err(|e| ListenerError::InvalidConfig(e.to_string()))
+    }
+
+    pub(crate) fn from_env() -> Result<Self, ListenerError> {
+        Ok(Self {
+            rpc_url: env_var("RPC_URL")?,
+            archival_rpc_url: env_var("ARCHIVAL_RPC_URL")?,
+            fallback_rpc_urls: env_list("FALLBACK_RPC_URLS"),
+            rpc_api_key: env_var("RPC_API_KEY")?,
+            headers: BTreeMap::new(),
+            proxy: env_var("PROXY")?,
+            connect_timeout_ms: env_var("CONNECT_TIMEOUT_MS")?,
+            account_ids: env_list("ACCOUNT_IDS"),
+            account_patterns: env_list("ACCOUNT_PATTERNS"),
+            factories: env_list("FACTORIES"),
+            method_names: env_list("METHOD_NAMES"),
+            standards: env_list("STANDARDS"),
+            versions: env_list("VERSIONS"),
+            events: env_list("EVENTS"),
+            log_prefixes: env_list("LOG_PREFIXES"),
+            last_processed_block: env_var("LAST_PROCESSED_BLOCK")?,
+            start_from: env_var::<String>("START_FROM")?.map(|start| match start.parse() {
+                Ok(height) => ConfigStart::Height(height),
+                Err(_) => ConfigStart::Named(start),
+            }),
+            expect_chain: env_var("EXPECT_CHAIN")?,
+            replay_last: env_var("REPLAY_LAST")?,
+            until_height: env_var("UNTIL_HEIGHT")?,
+            max_events: env_var("MAX_EVENTS")?,
+            finality: env_var("FINALITY")?,
+            delivery_on: env_var("DELIVERY_ON")?,
+            checkpoint_path: env_var("CHECKPOINT_PATH")?,
+            dead_letter_path: env_var("DEAD_LETTER_PATH")?,
+            include_raw_logs: env_var("INCLUDE_RAW_LOGS")?,
+            strict_validation: env_var("STRICT_VALIDATION")?,
+            max_attempts: env_var("MAX_ATTEMPTS")?,
+            max_rps: env_var("MAX_RPS")?,
+            request_timeout_ms: env_var("REQUEST_TIMEOUT_MS")?,
+            call_deadline_ms: env_var("CALL_DEADLINE_MS")?,
+            health_check_interval_ms: env_var("HEALTH_CHECK_INTERVAL_MS")?,
+            max_head_age_ms: env_var("MAX_HEAD_AGE_MS")?,
+            delivery: env_var("DELIVERY")?,
+            dedupe_capacity: env_var("DEDUPE_CAPACITY")?,
+            poll_interval_ms: env_var("POLL_INTERVAL_MS")?,
+            catch_up_interval_ms: env_var("CATCH_UP_INTERVAL_MS")?,
+            adaptive_polling: env_var("ADAPTIVE_POLLING")?,
+            chunk_concurrency: env_var("CHUNK_CONCURRENCY")?,
+            transaction_concurrency: env_var("TRANSACTION_CONCURRENCY")?,
+            backfill_concurrency: env_var("BACKFILL_CONCURRENCY")?,
+            delivery_queue: env_var("DELIVERY_QUEUE")?,
+            overflow: env_var("OVERFLOW")?,
+            shard_filter: env_var("SHARD_FILTER")?,
+            cache_capacity: env_var("CACHE_CAPACITY")?,
+        })
+    }
+
+    pub(crate) fn into_builder(self) -> Result<NearEventListenerBuilder, ListenerError> {
+        let rpc_url = self
+            .rpc_url
+            .ok_or_else(|| ListenerError::MissingField("rpc_url".to_string()))?;
+        let mut builder = NearEventListenerBuilder::new(&rpc_url).into_watched();
+
+        if let Some(url) = &self.archival_rpc_url {
+            builder = builder.archival_rpc_url(url);
+        }
+        for url in &self.fallback_rpc_urls {
+            builder = builder.fallback_rpc_url(url);
+        }
+        if let Some(api_key) = &self.rpc_api_key {
+            builder = builder.rpc_api_key(api_key);
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(timeout) = self.connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(timeout));
+        }
+        for account_id in &self.account_ids {
+            builder = builder.account_id(account_id);
+        }
+        for pattern in &self.account_patterns {
+            builder = builder.account_pattern(pattern);
+        }
+        for factory_id in &self.factories {
+            builder = builder.factory(factory_id);
+        }
+        for method_name in &self.method_names {
+            builder = builder.method_name(method_name);
+        }
+
+        let mut filter = EventFilter::new();
+        for standard in &self.standards {
+            filter = filter.standard(standard);
+        }
+        for version in &self.versions {
+            filter = filter.version(version);
+        }
+        for event in &self.events {
+            filter = filter.event(event);
+        }
+        builder = builder.event_filter(filter);
+        if !self.log_prefixes.is_empty() {
+            let prefixes: Vec<&str> = self.log_prefixes.iter().map(String::as_str).collect();
+            builder = builder.log_prefixes(&prefixes);
+        }
+
+        if let Some(block) = self.last_processed_block {
+            builder = builder.last_processed_block(block);
+        }
+        if let Some(start) = self.start_from {
+            builder = builder.start_from(match start {
+                ConfigStart::Height(height) => StartPosition::Height(height),
+                ConfigStart::Named(name) => match name.as_str() {
+                    "checkpoint" => StartPosition::Checkpoint,
+                    "latest" => StartPosition::Latest,
+                    _ => return Err(invalid("start_from", name)),
+                },
+            });
+        }
+        if let Some(chain_id) = &self.expect_chain {
+            builder = builder.expect_chain(chain_id);
+        }
+        if let Some(blocks) = self.replay_last {
+            builder = builder.replay_last(blocks);
+        }
+        if let Some(height) = self.until_height {
+            builder = builder.until_height(height);
+        }
+        if let Some(max_events) = self.max_events {
+            builder = builder.max_events(max_events);
+        }
+        if let Some(finality) = self.finality {
+            builder = builder.finality(match finality.as_str() {
+                "final" => Finality::Final,
+                "near-final" => Finality::DoomSlug,
+                "optimistic" => Finality::None,
+                _ => return Err(invalid("finality", finality)),
+            });
+        }
+        if let Some(delivery_on) = self.delivery_on {
+            builder = builder.delivery_on(match delivery_on.as_str() {
+                "success_only" => ExecutionStatusFilter::SuccessOnly,
+                "all" => ExecutionStatusFilter::All,
+                _ => return Err(invalid("delivery_on", delivery_on)),
+            });
+        }
+        if let Some(path) = self.checkpoint_path {
+            builder = builder.checkpoint_store(Box::new(FileCheckpointStore::new(path)));
+        }
+        if let Some(path) = self.dead_letter_path {
+            builder = builder.dead_letter_sink(Box::new(FileDeadLetterSink::new(path)));
+        }
+        if let Some(include) = self.include_raw_logs {
+            builder = builder.include_raw_logs(include);
+        }
+        if let Some(strict) = self.strict_validation {
+            builder = builder.strict_validation(strict);
+        }
+        if let Some(max_attempts) = self.max_attempts {
+            builder = builder.retry_policy(RetryPolicy::new(max_attempts));
+        }
+        if let Some(max_rps) = self.max_rps {
+            builder = builder.max_rps(max_rps);
+        }
+        if let Some(timeout) = self.request_timeout_ms {
+            builder = builder.request_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(deadline) = self.call_deadline_ms {
+            builder = builder.call_deadline(Duration::from_millis(deadline));
+        }
+        if let Some(interval) = self.health_check_interval_ms {
+            let mut health_check = HealthCheck::new(Duration::from_millis(interval));
+            if let Some(age) = self.max_head_age_ms {
+                health_check = health_check.max_head_age(Duration::from_millis(age));
+            }
+            builder = builder.health_check(health_check);
+        }
+        if let Some(delivery) = self.delivery {
+            builder = builder.delivery(match delivery.as_str() {
+                "at_least_once" => DeliveryMode::AtLeastOnce,
+                "exactly_once" => DeliveryMode::ExactlyOnce,
+                _ => return Err(invalid("delivery", delivery)),
+            });
+        }
+        if let Some(capacity) = self.dedupe_capacity {
+            builder = builder.dedupe_capacity(capacity);
+        }
+        if let Some(interval) = self.poll_interval_ms {
+            builder = builder.poll_interval(Duration::from_millis(interval));
+        }
+        if let Some(interval) = self.catch_up_interval_ms {
+            builder = builder.catch_up_interval(Duration::from_millis(interval));
+        }
+        if let Some(adaptive) = self.adaptive_polling {
+            builder = builder.adaptive_polling(adaptive);
+        }
+        if let Some(concurrency) = self.chunk_concurrency {
+            builder = builder.chunk_concurrency(concurrency);
+        }
+        if let Some(concurrency) = self.transaction_concurrency {
+            builder = builder.transaction_concurrency(concurrency);
+        }
+        if let Some(concurrency) = self.backfill_concurrency {
+            builder = builder.backfill_concurrency(concurrency);
+        }
+        if let Some(capacity) = self.delivery_queue {
+            let overflow = match self.overflow.as_deref() {
+                None | Some("block") => OverflowPolicy::Block,
+                Some("drop_oldest") => OverflowPolicy::DropOldest,
+                Some("drop_newest") => OverflowPolicy::DropNewest,
+                Some("error") => OverflowPolicy::Error,
+                Some(overflow) => return Err(invalid("overflow", overflow.to_string())),
+            };
+            builder = builder.delivery_queue(capacity, overflow);
+        }
+        if let Some(enabled) = self.shard_filter {
+            builder = builder.shard_filter(enabled);
+        }
+        if let Some(capacity) = self.cache_capacity {
+            builder = builder.cache_capacity(capacity);
+        }
+
+        Ok(builder)
+    }
+}
+
+fn env_var<T>(key: &str) -> Result<Option<T>, ListenerError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let name = format!("{}{}", ENV_PREFIX, key);
+    match env::var(&name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|e| ListenerError::InvalidConfig(format!("{}: {}", name, e))),
+        Err(_) => Ok(None),
+    }
+}
+
+// Comma-separated values, e.g. `NEAR_EVENT_LISTENER_ACCOUNT_IDS=a.near,b.near`.
+fn env_list(key: &str) -> Vec<String> {
+    env::var(format!("{}{}", ENV_PREFIX, key))
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn invalid(field: &str, value: String) -> ListenerError {
+    ListenerError::InvalidConfig(format!("invalid {} `{}`", field, value))
+}
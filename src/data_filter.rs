@@ -0,0 +1,133 @@
+use crate::ListenerError;
+use serde_json::Value;
+
+// What the values selected by a data filter path must satisfy. A filter
+// matches when any selected value does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataPredicate {
+    Exists,
+    Equals(Value),
+    // As in jq: a string contains a substring, an array an element, and any
+    // other value only itself.
+    Contains(Value),
+}
+
+impl DataPredicate {
+    fn matches(&self, selected: &[&Value]) -> bool {
+        match self {
+            DataPredicate::Exists => !selected.is_empty(),
+            DataPredicate::Equals(expected) => selected.contains(&expected),
+            DataPredicate::Contains(needle) => selected.iter().any(|value| match (value, needle) {
+                (Value::String(value), Value::String(needle)) => value.contains(needle),
+                (Value::Array(items), needle) => items.contains(needle),
+                (value, needle) => *value == needle,
+            }),
+        }
+    }
+}
+
+pub fn exists() -> DataPredicate {
+    DataPredicate::Exists
+}
+
+pub fn equals(value: impl Into<Value>) -> DataPredicate {
+    DataPredicate::Equals(value.into())
+}
+
+pub fn contains(value: impl Into<Value>) -> DataPredicate {
+    DataPredicate::Contains(value.into())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+    Wildcard,
+}
+
+// A path into event data, in the subset of JSONPath and jq shared by both:
+// `$` or `.` for the data itself, `.name` and `['name']` for fields, `[0]`
+// for array items and `[*]` or `.*` for every item or field.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DataPath(Vec<Segment>);
+
+impl DataPath {
+    pub(crate) fn parse(path: &str) -> Result<Self, ListenerError> {
+        let invalid = |reason: &str| {
+            ListenerError::InvalidConfig(format!("invalid data filter path `{}`: {}", path, reason))
+        };
+
+        let mut rest = path.trim();
+        rest = rest.strip_prefix('$').unwrap_or(rest);
+        if rest == "." {
+            rest = "";
+        }
+
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("unclosed `[`"))?;
+                let inner = after[..end].trim();
+                let quoted = inner.len() >= 2
+                    && ((inner.starts_with('\'') && inner.ends_with('\''))
+                        || (inner.starts_with('"') && inner.ends_with('"')));
+                segments.push(if inner == "*" {
+                    Segment::Wildcard
+                } else if quoted {
+                    Segment::Field(inner[1..inner.len() - 1].to_string())
+                } else {
+                    Segment::Index(inner.parse().map_err(|_| {
+                        invalid("brackets must hold an index, `*` or a quoted name")
+                    })?)
+                });
+                rest = &after[end + 1..];
+            } else if let Some(after) = rest.strip_prefix('.') {
+                // jq writes `.[0]` for the first item.
+                if after.starts_with('[') {
+                    rest = after;
+                    continue;
+                }
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                let name = &after[..end];
+                if name.is_empty() {
+                    return Err(invalid("empty field name"));
+                }
+                segments.push(if name == "*" {
+                    Segment::Wildcard
+                } else {
+                    Segment::Field(name.to_string())
+                });
+                rest = &after[end..];
+            } else {
+                return Err(invalid("expected `.` or `[`"));
+            }
+        }
+
+        Ok(Self(segments))
+    }
+
+    fn select<'a>(&self, data: &'a Value) -> Vec<&'a Value> {
+        let mut selected = vec![data];
+        for segment in &self.0 {
+            selected = selected
+                .into_iter()
+                .flat_map(|value| match (segment, value) {
+                    (Segment::Field(name), Value::Object(fields)) => {
+                        fields.get(name).into_iter().collect()
+                    }
+                    (Segment::Index(index), Value::Array(items)) => {
+                        items.get(*index).into_iter().collect()
+                    }
+                    (Segment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (Segment::Wildcard, Value::Object(fields)) => fields.values().collect(),
+                    _ => Vec::new(),
+                })
+                .collect();
+        }
+        selected
+    }
+
+    pub(crate) fn matches(&self, predicate: &DataPredicate, data: &Value) -> bool {
+        predicate.matches(&self.select(data))
+    }
+}
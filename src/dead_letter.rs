@@ -0,0 +1,61 @@
+use crate::{EventContext, ListenerError};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::sync::mpsc::UnboundedSender;
+
+// An `EVENT_JSON:` log that could not be parsed, kept for later inspection.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DeadLetter {
+    pub context: EventContext,
+    pub log: String,
+    pub error: String,
+}
+
+pub trait DeadLetterSink: Send + Sync {
+    fn send(&self, letter: DeadLetter) -> Result<(), ListenerError>;
+}
+
+impl<F> DeadLetterSink for F
+where
+    F: Fn(DeadLetter) + Send + Sync,
+{
+    fn send(&self, letter: DeadLetter) -> Result<(), ListenerError> {
+        self(letter);
+        Ok(())
+    }
+}
+
+impl DeadLetterSink for UnboundedSender<DeadLetter> {
+    fn send(&self, letter: DeadLetter) -> Result<(), ListenerError> {
+        UnboundedSender::send(self, letter)
+            .map_err(|_| ListenerError::DeadLetterError("receiver dropped".to_string()))
+    }
+}
+
+// Appends every dead letter to a file as one JSON object per line.
+#[derive(Debug, Clone)]
+pub struct FileDeadLetterSink {
+    path: PathBuf,
+}
+
+impl FileDeadLetterSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl DeadLetterSink for FileDeadLetterSink {
+    fn send(&self, letter: DeadLetter) -> Result<(), ListenerError> {
+        let mut line = serde_json::to_string(&letter)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| ListenerError::DeadLetterError(e.to_string()))
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{EventLog, ListenerError};
+use near_sdk::base64::engine::general_purpose::STANDARD;
+use near_sdk::base64::Engine;
+use near_sdk::borsh::BorshDeserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+const EVENT_JSON_PREFIX: &str = "EVENT_JSON:";
+
+type BorshDecode = Box<dyn Fn(&[u8]) -> Result<Value, String> + Send + Sync>;
+
+// Turns a log line into an event. A log the decoder does not recognize as one
+// of its own is rejected with `ListenerError::InvalidEventFormat`, which keeps
+// it out of the dead letter sink; any other error means a malformed event.
+pub trait LogDecoder: Send + Sync {
+    fn decode(&self, log: &str) -> Result<EventLog, ListenerError>;
+}
+
+// NEP-297 JSON events, the default. Contracts following a variant convention
+// log the same JSON after another prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventJsonDecoder {
+    prefixes: Vec<String>,
+}
+
+impl Default for EventJsonDecoder {
+    fn default() -> Self {
+        Self::with_prefixes(&[EVENT_JSON_PREFIX])
+    }
+}
+
+impl EventJsonDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces `EVENT_JSON:`; list it too to keep reading standard events.
+    pub fn with_prefixes(prefixes: &[&str]) -> Self {
+        Self {
+            prefixes: prefixes.iter().map(|prefix| prefix.to_string()).collect(),
+        }
+    }
+}
+
+impl LogDecoder for EventJsonDecoder {
+    fn decode(&self, log: &str) -> Result<EventLog, ListenerError> {
+        let json = self
+            .prefixes
+            .iter()
+            .find_map(|prefix| log.strip_prefix(prefix.as_str()))
+            .ok_or_else(|| {
+                ListenerError::InvalidEventFormat(format!(
+                    "Log does not start with {}",
+                    self.prefixes.join(" or ")
+                ))
+            })?;
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+// Logs holding a base64-encoded Borsh value of type `T`, optionally after a
+// prefix. The contract's log carries no name, so the decoder labels every
+// event with the given standard, version and event; `data` is the value
+// serialized to JSON, so `parse_data::<T>()` gives it back.
+pub struct BorshLogDecoder {
+    standard: String,
+    version: String,
+    event: String,
+    prefix: Option<String>,
+    decode: BorshDecode,
+}
+
+impl fmt::Debug for BorshLogDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BorshLogDecoder")
+            .field("standard", &self.standard)
+            .field("version", &self.version)
+            .field("event", &self.event)
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BorshLogDecoder {
+    pub fn new<T>(standard: &str, version: &str, event: &str) -> Self
+    where
+        T: BorshDeserialize + Serialize + 'static,
+    {
+        Self {
+            standard: standard.to_string(),
+            version: version.to_string(),
+            event: event.to_string(),
+            prefix: None,
+            decode: Box::new(|bytes| {
+                let value = T::try_from_slice(bytes).map_err(|e| e.to_string())?;
+                serde_json::to_value(value).map_err(|e| e.to_string())
+            }),
+        }
+    }
+
+    // Only logs starting with `prefix`, e.g. "EVENT_BORSH:", are decoded. With a
+    // prefix, a log that fails to decode is a malformed event rather than an
+    // unrelated log.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+}
+
+impl LogDecoder for BorshLogDecoder {
+    fn decode(&self, log: &str) -> Result<EventLog, ListenerError> {
+        let (payload, malformed): (&str, fn(String) -> ListenerError) = match &self.prefix {
+            Some(prefix) => match log.strip_prefix(prefix.as_str()) {
+                Some(payload) => (payload, ListenerError::DecodeError),
+                None => {
+                    return Err(ListenerError::InvalidEventFormat(format!(
+                        "Log does not start with {}",
+                        prefix
+                    )))
+                }
+            },
+            None => (log, ListenerError::InvalidEventFormat),
+        };
+
+        let bytes = STANDARD
+            .decode(payload.trim())
+            .map_err(|e| malformed(format!("invalid base64: {}", e)))?;
+        let data = (self.decode)(&bytes).map_err(|e| malformed(format!("invalid Borsh: {}", e)))?;
+
+        Ok(EventLog {
+            standard: self.standard.clone(),
+            version: self.version.clone(),
+            event: self.event.clone(),
+            data,
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use crate::EventContext;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMode {
+    #[default]
+    AtLeastOnce,
+    ExactlyOnce,
+}
+
+pub(crate) type EventKey = (Option<String>, Option<String>, usize);
+
+// Identifies a delivered event by its transaction hash, receipt ID and log
+// index. Checkpoint stores keep these for the block in progress, so that
+// `DeliveryMode::ExactlyOnce` holds across a restart in the middle of it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DeliveredEvent {
+    pub tx_hash: Option<String>,
+    pub receipt_id: Option<String>,
+    pub log_index: usize,
+}
+
+impl DeliveredEvent {
+    pub(crate) fn key(&self) -> EventKey {
+        (
+            self.tx_hash.clone(),
+            self.receipt_id.clone(),
+            self.log_index,
+        )
+    }
+}
+
+impl From<&EventContext> for DeliveredEvent {
+    fn from(context: &EventContext) -> Self {
+        Self {
+            tx_hash: context.tx_hash.clone(),
+            receipt_id: context.receipt_id.clone(),
+            log_index: context.log_index,
+        }
+    }
+}
+
+// Remembers the most recently delivered events so that refetched blocks do
+// not reach callbacks twice. The oldest keys are forgotten once full.
+#[derive(Debug)]
+pub(crate) struct DedupeCache {
+    capacity: usize,
+    delivered: HashSet<EventKey>,
+    order: VecDeque<EventKey>,
+}
+
+impl DedupeCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            delivered: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    // Returns false if the event was already delivered.
+    pub(crate) fn insert(&mut self, context: &EventContext) -> bool {
+        self.insert_key(event_key(context))
+    }
+
+    pub(crate) fn insert_key(&mut self, key: EventKey) -> bool {
+        if !self.delivered.insert(key.clone()) {
+            return false;
+        }
+
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.delivered.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    // Returns false if the event was never delivered.
+    pub(crate) fn remove(&mut self, context: &EventContext) -> bool {
+        let key = event_key(context);
+        self.order.retain(|delivered| *delivered != key);
+        self.delivered.remove(&key)
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // The remembered keys, oldest first.
+    pub(crate) fn keys(&self) -> Vec<EventKey> {
+        self.order.iter().cloned().collect()
+    }
+}
+
+fn event_key(context: &EventContext) -> EventKey {
+    (
+        context.tx_hash.clone(),
+        context.receipt_id.clone(),
+        context.log_index,
+    )
+}
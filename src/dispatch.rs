@@ -0,0 +1,54 @@
+use crate::EventEnvelope;
+use std::collections::HashMap;
+use std::fmt;
+
+type EventHandler = Box<dyn Fn(EventEnvelope) + Send + Sync>;
+
+// Callbacks keyed by the `event` field of the NEP-297 log, with a fallback for
+// events that have none.
+#[derive(Default)]
+pub(crate) struct EventHandlers {
+    by_event: HashMap<String, Vec<EventHandler>>,
+    fallback: Option<EventHandler>,
+}
+
+impl fmt::Debug for EventHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventHandlers")
+            .field("events", &self.by_event.keys().collect::<Vec<_>>())
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+impl EventHandlers {
+    pub(crate) fn insert(&mut self, event: &str, handler: EventHandler) {
+        self.by_event
+            .entry(event.to_string())
+            .or_default()
+            .push(handler);
+    }
+
+    pub(crate) fn set_fallback(&mut self, handler: EventHandler) {
+        self.fallback = Some(handler);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.by_event.is_empty() && self.fallback.is_none()
+    }
+
+    pub(crate) fn dispatch(&self, envelope: &EventEnvelope) {
+        match self.by_event.get(&envelope.event.event) {
+            Some(handlers) => {
+                for handler in handlers {
+                    handler(envelope.clone());
+                }
+            }
+            None => {
+                if let Some(fallback) = &self.fallback {
+                    fallback(envelope.clone());
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,9 @@
+use near_jsonrpc_client::errors::{
+    JsonRpcError, JsonRpcServerError, JsonRpcServerResponseStatusError, JsonRpcTransportRecvError,
+    JsonRpcTransportSendError, RpcTransportError,
+};
+use near_jsonrpc_client::methods;
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,12 +11,203 @@ pub enum ListenerError {
     #[error("RPC error: {0}")]
     RpcError(String),
 
+    #[error("Block not found: {0}")]
+    BlockNotFound(String),
+
+    #[error("Chunk not found: {0}")]
+    ChunkNotFound(String),
+
+    #[error("Transaction not found: {0}")]
+    TxNotFound(String),
+
+    #[error("RPC request timed out")]
+    Timeout,
+
+    #[error("Rate limited by the RPC node")]
+    RateLimited,
+
+    #[error("Unhealthy RPC node: {0}")]
+    UnhealthyNode(String),
+
+    #[error("Transport error: {0}")]
+    Transport(#[source] Box<RpcTransportError>),
+
     #[error("Invalid event format: {0}")]
     InvalidEventFormat(String),
 
+    #[error("Event does not comply with NEP-297: {0}")]
+    NonCompliantEvent(String),
+
+    #[error("Could not decode event: {0}")]
+    DecodeError(String),
+
+    #[error("Event data does not match its schema: {0}")]
+    SchemaViolation(String),
+
+    #[error("Delivery queue is full ({0} events)")]
+    QueueFull(usize),
+
     #[error("JSON parsing error: {0}")]
     JsonError(#[from] serde_json::Error),
 
     #[error("Missing field: {0}")]
     MissingField(String),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("Checkpoint error: {0}")]
+    CheckpointError(String),
+
+    #[error("Data source error: {0}")]
+    SourceError(String),
+
+    #[error("Dead letter error: {0}")]
+    DeadLetterError(String),
+
+    #[error("Webhook error: {0}")]
+    WebhookError(String),
+
+    #[error("Sink error: {0}")]
+    SinkError(String),
+
+    #[error("Template error: {0}")]
+    TemplateError(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    Continue,
+    Stop,
+}
+
+// Handler errors of the RPC methods the listener calls, mapped onto the
+// dedicated variants where one exists.
+pub(crate) trait HandlerError: fmt::Debug + fmt::Display {
+    fn classify(&self) -> Option<ListenerError>;
+}
+
+impl HandlerError for methods::block::RpcBlockError {
+    fn classify(&self) -> Option<ListenerError> {
+        match self {
+            Self::UnknownBlock { error_message } => {
+                Some(ListenerError::BlockNotFound(error_message.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl HandlerError for methods::chunk::RpcChunkError {
+    fn classify(&self) -> Option<ListenerError> {
+        match self {
+            Self::UnknownBlock { error_message } => {
+                Some(ListenerError::BlockNotFound(error_message.clone()))
+            }
+            Self::UnknownChunk { chunk_hash } => {
+                Some(ListenerError::ChunkNotFound(chunk_hash.0.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl HandlerError for methods::tx::RpcTransactionError {
+    fn classify(&self) -> Option<ListenerError> {
+        match self {
+            Self::UnknownTransaction {
+                requested_transaction_hash,
+            } => Some(ListenerError::TxNotFound(
+                requested_transaction_hash.to_string(),
+            )),
+            Self::TimeoutError => Some(ListenerError::Timeout),
+            _ => None,
+        }
+    }
+}
+
+impl HandlerError for methods::status::RpcStatusError {
+    fn classify(&self) -> Option<ListenerError> {
+        None
+    }
+}
+
+impl HandlerError for methods::EXPERIMENTAL_receipt::RpcReceiptError {
+    fn classify(&self) -> Option<ListenerError> {
+        None
+    }
+}
+
+impl HandlerError for methods::EXPERIMENTAL_protocol_config::RpcProtocolConfigError {
+    fn classify(&self) -> Option<ListenerError> {
+        match self {
+            Self::UnknownBlock { error_message } => {
+                Some(ListenerError::BlockNotFound(error_message.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl HandlerError for methods::EXPERIMENTAL_changes::RpcStateChangesError {
+    fn classify(&self) -> Option<ListenerError> {
+        match self {
+            Self::UnknownBlock { error_message } => {
+                Some(ListenerError::BlockNotFound(error_message.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl HandlerError for methods::query::RpcQueryError {
+    fn classify(&self) -> Option<ListenerError> {
+        match self {
+            Self::UnknownBlock { block_reference } => Some(ListenerError::BlockNotFound(format!(
+                "{:?}",
+                block_reference
+            ))),
+            _ => None,
+        }
+    }
+}
+
+impl HandlerError for methods::light_client_proof::RpcLightClientProofError {
+    fn classify(&self) -> Option<ListenerError> {
+        match self {
+            Self::UnknownBlock { error_message } => {
+                Some(ListenerError::BlockNotFound(error_message.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<E: HandlerError> From<JsonRpcError<E>> for ListenerError {
+    fn from(err: JsonRpcError<E>) -> Self {
+        match err {
+            JsonRpcError::TransportError(err) if is_timeout(&err) => ListenerError::Timeout,
+            JsonRpcError::TransportError(err) => ListenerError::Transport(Box::new(err)),
+            JsonRpcError::ServerError(JsonRpcServerError::HandlerError(err)) => err
+                .classify()
+                .unwrap_or_else(|| ListenerError::RpcError(format!("Handler error: {:?}", err))),
+            JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(
+                JsonRpcServerResponseStatusError::TooManyRequests,
+            )) => ListenerError::RateLimited,
+            JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(
+                JsonRpcServerResponseStatusError::Unexpected { status },
+            )) if status.as_u16() == 408 || status.as_u16() == 504 => ListenerError::Timeout,
+            JsonRpcError::ServerError(err) => ListenerError::RpcError(err.to_string()),
+        }
+    }
+}
+
+pub(crate) fn is_timeout(err: &RpcTransportError) -> bool {
+    match err {
+        RpcTransportError::SendError(JsonRpcTransportSendError::PayloadSendError(err))
+        | RpcTransportError::RecvError(JsonRpcTransportRecvError::PayloadRecvError(err)) => {
+            err.is_timeout()
+        }
+        _ => false,
+    }
 }
@@ -0,0 +1,7 @@
+use crate::{EventLog, ListenerError};
+
+pub trait NearEvent: Sized {
+    fn matches(event_log: &EventLog) -> bool;
+
+    fn from_event_log(event_log: &EventLog) -> Result<Self, ListenerError>;
+}
@@ -13,9 +13,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .last_processed_block(0)
         .build()?;
 
-    listener.start(|event_log| {
+    listener.start(|envelope| {
+        let event_log = envelope.event;
+
         // User can process the data as they prefer
-        println!("Received event:");
+        println!("Received event from {}:", envelope.context.account_id);
         println!("Standard: {}", event_log.standard);
         println!("Version: {}", event_log.version);
         println!("Event: {}", event_log.event);
@@ -8,7 +8,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .last_processed_block(0)
         .build()?;
 
-    listener.start(|event_log| {
+    listener.start(|envelope| {
+        let event_log = envelope.event;
+        println!("Account: {}", envelope.context.account_id);
         println!("Standard: {}", event_log.standard);
         println!("Version: {}", event_log.version);
         println!("Event: {}", event_log.event);
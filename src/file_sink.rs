@@ -0,0 +1,156 @@
+use crate::{EventEnvelope, EventSink, ListenerError};
+use futures::future::BoxFuture;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Writes every event as one JSON envelope per line (NDJSON), to stdout or to a
+// file that log shippers such as Vector or Logstash can tail.
+#[derive(Debug)]
+pub struct FileSink {
+    output: Output,
+}
+
+#[derive(Debug)]
+enum Output {
+    Stdout,
+    File(Mutex<RotatingFile>),
+}
+
+// The current file is renamed to `<name>-<unix millis>.<extension>` next to it
+// once it would grow past `max_bytes` or has been written to for `max_age`,
+// and a new one is started under the original name.
+#[derive(Debug)]
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+    file: Option<File>,
+    size: u64,
+    opened_at: Instant,
+}
+
+impl FileSink {
+    // Appends to `path`, creating it if needed.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            output: Output::File(Mutex::new(RotatingFile {
+                path: path.into(),
+                max_bytes: None,
+                max_age: None,
+                file: None,
+                size: 0,
+                opened_at: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn stdout() -> Self {
+        Self {
+            output: Output::Stdout,
+        }
+    }
+
+    // Has no effect on `stdout()`.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        if let Output::File(file) = &mut self.output {
+            file.get_mut().unwrap().max_bytes = Some(max_bytes);
+        }
+        self
+    }
+
+    // Counted from when the sink opened the file. Has no effect on `stdout()`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        if let Output::File(file) = &mut self.output {
+            file.get_mut().unwrap().max_age = Some(max_age);
+        }
+        self
+    }
+
+    fn write(&self, line: &[u8]) -> io::Result<()> {
+        match &self.output {
+            Output::Stdout => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(line)?;
+                stdout.flush()
+            }
+            Output::File(file) => file.lock().unwrap().write(line),
+        }
+    }
+}
+
+impl RotatingFile {
+    fn write(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.file.is_some() && self.is_due(line.len() as u64) {
+            self.rotate()?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.open()?,
+        };
+        file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    // An empty file is never rotated, so a line larger than `max_bytes` still
+    // gets written.
+    fn is_due(&self, len: u64) -> bool {
+        self.size > 0
+            && (self
+                .max_bytes
+                .is_some_and(|max_bytes| self.size + len > max_bytes)
+                || self
+                    .max_age
+                    .is_some_and(|max_age| self.opened_at.elapsed() >= max_age))
+    }
+
+    fn open(&mut self) -> io::Result<&mut File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.opened_at = Instant::now();
+        Ok(self.file.insert(file))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        fs::rename(&self.path, rotated_path(&self.path))
+    }
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    // Several rotations within the same millisecond get a counter.
+    let mut rotated = path.with_file_name(format!("{}-{}{}", stem, millis, extension));
+    let mut counter = 1;
+    while rotated.exists() {
+        rotated = path.with_file_name(format!("{}-{}-{}{}", stem, millis, counter, extension));
+        counter += 1;
+    }
+    rotated
+}
+
+impl EventSink for FileSink {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_vec(envelope)?;
+            line.push(b'\n');
+            self.write(&line)
+                .map_err(|e| ListenerError::SinkError(e.to_string()))
+        })
+    }
+}
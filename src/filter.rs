@@ -0,0 +1,97 @@
+use crate::{EventLog, ExecutionStatus};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventFilter {
+    standards: Vec<String>,
+    versions: Vec<String>,
+    events: Vec<String>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn standard(mut self, standard: &str) -> Self {
+        self.standards.push(standard.to_string());
+        self
+    }
+
+    pub fn version(mut self, version: &str) -> Self {
+        self.versions.push(version.to_string());
+        self
+    }
+
+    pub fn event(mut self, event: &str) -> Self {
+        self.events.push(event.to_string());
+        self
+    }
+
+    pub fn matches(&self, event_log: &EventLog) -> bool {
+        matches_any(&self.standards, &event_log.standard)
+            && matches_any(&self.versions, &event_log.version)
+            && matches_any(&self.events, &event_log.event)
+    }
+}
+
+// Whose logs are turned into events. A transaction to a watched contract can
+// call other contracts, whose logs show up in the same outcome; by default
+// only logs emitted by watched accounts are kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ExecutorFilter {
+    #[default]
+    WatchedAccounts,
+    Accounts(Vec<String>),
+    Any,
+}
+
+// Which events are delivered depending on how their transaction ended. A
+// transaction can fail after some of its receipts already emitted logs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecutionStatusFilter {
+    #[default]
+    SuccessOnly,
+    All,
+}
+
+impl ExecutionStatusFilter {
+    // Events whose status is not known yet are delivered either way.
+    pub(crate) fn accepts(self, status: Option<ExecutionStatus>) -> bool {
+        match self {
+            Self::SuccessOnly => status != Some(ExecutionStatus::Failure),
+            Self::All => true,
+        }
+    }
+}
+
+fn matches_any(allowed: &[String], value: &str) -> bool {
+    allowed.is_empty() || allowed.iter().any(|allowed| allowed == value)
+}
+
+pub(crate) fn glob_matches(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let value = value.as_bytes();
+
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+
+    while v < value.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
@@ -0,0 +1,205 @@
+use crate::server::state_name;
+use crate::{EventEnvelope, EventServer};
+use async_graphql::http::{WebSocket, WsMessage, ALL_WEBSOCKET_PROTOCOLS};
+use async_graphql::{Context, EmptyMutation, Json, Object, Schema, SimpleObject, Subscription};
+use axum::extract::ws::{CloseFrame, Message, WebSocketUpgrade};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, MethodRouter};
+use futures::stream::{self, Stream};
+use futures::{future, SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::sync::broadcast::error::RecvError;
+
+pub(crate) type EventSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+// `/graphql`: queries are POSTed as JSON, and subscriptions run over
+// a WebSocket speaking graphql-transport-ws or the older graphql-ws protocol.
+pub(crate) fn route<S: Clone + Send + Sync + 'static>(server: EventServer) -> MethodRouter<S> {
+    let schema = Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(server)
+        .finish();
+    get(subscribe).post(execute).with_state(schema)
+}
+
+async fn execute(
+    State(schema): State<EventSchema>,
+    request: axum::Json<async_graphql::Request>,
+) -> axum::Json<async_graphql::Response> {
+    axum::Json(schema.execute(request.0).await)
+}
+
+async fn subscribe(
+    State(schema): State<EventSchema>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let protocol = headers
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .split(',')
+                .find_map(|protocol| protocol.trim().parse().ok())
+        });
+    let Some(protocol) = protocol else {
+        return (StatusCode::BAD_REQUEST, "unsupported WebSocket protocol").into_response();
+    };
+
+    upgrade
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| async move {
+            let (mut sender, receiver) = socket.split();
+            let input = receiver
+                .take_while(|message| future::ready(message.is_ok()))
+                .filter_map(|message| {
+                    future::ready(match message {
+                        Ok(Message::Text(text)) => Some(text.as_str().as_bytes().to_vec()),
+                        Ok(Message::Binary(bytes)) => Some(bytes.to_vec()),
+                        _ => None,
+                    })
+                });
+
+            let mut output = WebSocket::new(schema, input, protocol);
+            while let Some(message) = output.next().await {
+                let message = match message {
+                    WsMessage::Text(text) => Message::Text(text.into()),
+                    WsMessage::Close(code, reason) => Message::Close(Some(CloseFrame {
+                        code,
+                        reason: reason.into(),
+                    })),
+                };
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+        })
+}
+
+pub(crate) struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn status(&self, ctx: &Context<'_>) -> Status {
+        let status = ctx.data_unchecked::<EventServer>().handle().status();
+        Status {
+            state: state_name(status.state).to_string(),
+            last_processed_block: status.last_processed_block,
+            head_block: status.head_block,
+            events_emitted: status.events_emitted,
+            rpc_errors: status.rpc_errors,
+            cache_hits: status.cache_hits,
+            cache_misses: status.cache_misses,
+            uptime_ms: status.uptime.as_millis() as u64,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Status {
+    state: String,
+    last_processed_block: u64,
+    head_block: u64,
+    events_emitted: usize,
+    rpc_errors: u64,
+    cache_hits: u64,
+    cache_misses: u64,
+    uptime_ms: u64,
+}
+
+pub(crate) struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    // Events sent to the server after the subscription started, optionally
+    // only those of one account, event name or standard.
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        account_id: Option<String>,
+        event: Option<String>,
+        standard: Option<String>,
+    ) -> impl Stream<Item = Event> {
+        let receiver = ctx.data_unchecked::<EventServer>().subscribe();
+        let matches = move |envelope: &EventEnvelope| {
+            account_id
+                .as_ref()
+                .is_none_or(|account_id| *account_id == envelope.context.account_id)
+                && event
+                    .as_ref()
+                    .is_none_or(|event| *event == envelope.event.event)
+                && standard
+                    .as_ref()
+                    .is_none_or(|standard| *standard == envelope.event.standard)
+        };
+
+        stream::unfold(receiver, move |mut receiver| {
+            let matches = matches.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(envelope) if matches(&envelope) => {
+                            return Some((Event(envelope), receiver))
+                        }
+                        Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        })
+    }
+}
+
+struct Event(EventEnvelope);
+
+#[Object]
+impl Event {
+    async fn account_id(&self) -> &str {
+        &self.0.context.account_id
+    }
+
+    async fn block_height(&self) -> u64 {
+        self.0.context.block_height
+    }
+
+    async fn block_hash(&self) -> &str {
+        &self.0.context.block_hash
+    }
+
+    async fn block_timestamp(&self) -> u64 {
+        self.0.context.block_timestamp
+    }
+
+    async fn tx_hash(&self) -> Option<&str> {
+        self.0.context.tx_hash.as_deref()
+    }
+
+    async fn receipt_id(&self) -> Option<&str> {
+        self.0.context.receipt_id.as_deref()
+    }
+
+    async fn log_index(&self) -> usize {
+        self.0.context.log_index
+    }
+
+    async fn sequence(&self) -> Option<u64> {
+        self.0.context.sequence
+    }
+
+    async fn standard(&self) -> &str {
+        &self.0.event.standard
+    }
+
+    async fn version(&self) -> &str {
+        &self.0.event.version
+    }
+
+    async fn event(&self) -> &str {
+        &self.0.event.event
+    }
+
+    async fn data(&self) -> Json<&Value> {
+        Json(&self.0.event.data)
+    }
+}
@@ -0,0 +1,155 @@
+use crate::status::StatusTracker;
+use crate::subscription::SubscriptionId;
+use crate::{EventFilter, ListenerError, ListenerStatus, Subscription};
+use futures::future;
+use near_sdk::AccountId;
+use std::pin::pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+// A change to what the listener watches, applied before its next poll.
+#[derive(Debug)]
+pub(crate) enum WatchChange {
+    AddAccount(String),
+    RemoveAccount(String),
+    AddMethod(String),
+    RemoveMethod(String),
+    EventFilter(EventFilter),
+    AddSubscription(Subscription),
+    RemoveSubscription(SubscriptionId),
+}
+
+// Controls a running listener from elsewhere, e.g. while it runs in a spawned
+// task. Clones control the same listener.
+#[derive(Debug, Clone)]
+pub struct ListenerHandle {
+    paused: Arc<watch::Sender<bool>>,
+    stopped: Arc<watch::Sender<bool>>,
+    status: Arc<StatusTracker>,
+    changes: Arc<Mutex<Vec<WatchChange>>>,
+    next_subscription_id: Arc<AtomicU64>,
+}
+
+impl ListenerHandle {
+    pub(crate) fn new() -> Self {
+        Self {
+            paused: Arc::new(watch::Sender::new(false)),
+            stopped: Arc::new(watch::Sender::new(false)),
+            status: Arc::default(),
+            changes: Arc::default(),
+            next_subscription_id: Arc::default(),
+        }
+    }
+
+    // The block being processed when this is called is finished; after that
+    // the listener neither polls nor advances `last_processed_block` until
+    // `resume` is called.
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    // Finishes the block in progress and then stops: `start` and the other
+    // entry points return, and streams end. Waits between polls and pauses are
+    // cut short. A stopped listener does not start again.
+    pub fn stop(&self) {
+        self.stopped.send_replace(true);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        *self.stopped.borrow()
+    }
+
+    // Reflects the listener as of its last poll.
+    pub fn status(&self) -> ListenerStatus {
+        self.status.snapshot(self.is_paused())
+    }
+
+    // The changes below take effect from the next block the listener polls,
+    // without restarting it or moving its cursor.
+    pub fn add_account_id(&self, account_id: &str) -> Result<(), ListenerError> {
+        validate_account_id(account_id)?;
+        self.push(WatchChange::AddAccount(account_id.to_string()));
+        Ok(())
+    }
+
+    pub fn remove_account_id(&self, account_id: &str) {
+        self.push(WatchChange::RemoveAccount(account_id.to_string()));
+    }
+
+    pub fn add_method_name(&self, method_name: &str) {
+        self.push(WatchChange::AddMethod(method_name.to_string()));
+    }
+
+    pub fn remove_method_name(&self, method_name: &str) {
+        self.push(WatchChange::RemoveMethod(method_name.to_string()));
+    }
+
+    pub fn set_event_filter(&self, filter: EventFilter) {
+        self.push(WatchChange::EventFilter(filter));
+    }
+
+    // Returns the ID to pass to `remove_subscription`.
+    pub fn add_subscription(
+        &self,
+        mut subscription: Subscription,
+    ) -> Result<SubscriptionId, ListenerError> {
+        if !subscription.has_accounts() {
+            return Err(ListenerError::MissingField(
+                "subscription account_id".to_string(),
+            ));
+        }
+        for account_id in &subscription.account_ids {
+            validate_account_id(account_id)?;
+        }
+
+        let id = SubscriptionId(self.next_subscription_id.fetch_add(1, Ordering::Relaxed));
+        subscription.id = Some(id);
+        self.push(WatchChange::AddSubscription(subscription));
+        Ok(id)
+    }
+
+    pub fn remove_subscription(&self, id: SubscriptionId) {
+        self.push(WatchChange::RemoveSubscription(id));
+    }
+
+    pub(crate) fn tracker(&self) -> &Arc<StatusTracker> {
+        &self.status
+    }
+
+    pub(crate) fn take_changes(&self) -> Vec<WatchChange> {
+        std::mem::take(&mut *self.changes.lock().unwrap())
+    }
+
+    pub(crate) async fn wait_while_paused(&self) {
+        let mut paused = self.paused.subscribe();
+        // The sender is kept alive by `self`, so this cannot fail.
+        let resumed = pin!(paused.wait_for(|paused| !paused));
+        future::select(resumed, pin!(self.wait_for_stop())).await;
+    }
+
+    pub(crate) async fn wait_for_stop(&self) {
+        let mut stopped = self.stopped.subscribe();
+        let _ = stopped.wait_for(|stopped| *stopped).await;
+    }
+
+    pub(crate) fn push(&self, change: WatchChange) {
+        self.changes.lock().unwrap().push(change);
+    }
+}
+
+fn validate_account_id(account_id: &str) -> Result<(), ListenerError> {
+    AccountId::from_str(account_id).map_err(|e| {
+        ListenerError::InvalidConfig(format!("invalid account_id `{}`: {}", account_id, e))
+    })?;
+    Ok(())
+}
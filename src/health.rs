@@ -0,0 +1,52 @@
+use near_primitives::views::StatusSyncInfo;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MAX_HEAD_AGE: Duration = Duration::from_secs(60);
+
+// How often the listener probes its RPC node's `status` while polling. A node
+// is unhealthy while it is syncing or when its head is older than
+// `max_head_age`, one minute by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthCheck {
+    interval: Duration,
+    max_head_age: Duration,
+}
+
+impl HealthCheck {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            max_head_age: DEFAULT_MAX_HEAD_AGE,
+        }
+    }
+
+    pub fn max_head_age(mut self, age: Duration) -> Self {
+        self.max_head_age = age;
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    // Describes what is wrong with the node, if anything.
+    pub(crate) fn problem(&self, sync_info: &StatusSyncInfo) -> Option<String> {
+        if sync_info.syncing {
+            return Some("is syncing".to_string());
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as i128;
+        let head_time = sync_info.latest_block_time.unix_timestamp_nanos();
+        let age = Duration::from_nanos(u64::try_from(now - head_time).unwrap_or(0));
+        (age > self.max_head_age).then(|| {
+            format!(
+                "has a stale head: block {} is {}s old",
+                sync_info.latest_block_height,
+                age.as_secs()
+            )
+        })
+    }
+}
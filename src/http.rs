@@ -0,0 +1,97 @@
+use crate::ListenerError;
+use near_jsonrpc_client::JsonRpcClient;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Certificate, Proxy};
+use std::time::Duration;
+
+// The header paid RPC providers read the API key from.
+const API_KEY_HEADER: &str = "x-api-key";
+
+// How the listener's JSON-RPC clients reach their nodes. Headers are sent with
+// every call; the rest needs a dedicated HTTP client instead of the shared one.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HttpOptions {
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) proxy: Option<String>,
+    pub(crate) connect_timeout: Option<Duration>,
+    // PEM-encoded certificates trusted in addition to the system's.
+    pub(crate) root_certificates: Vec<Vec<u8>>,
+    pub(crate) accept_invalid_certs: bool,
+}
+
+impl HttpOptions {
+    pub(crate) fn add_api_key(&mut self, api_key: &str) {
+        self.headers
+            .push((API_KEY_HEADER.to_string(), api_key.to_string()));
+    }
+
+    // Returns a function connecting a client to an RPC URL with these options.
+    pub(crate) fn connector(&self) -> Result<impl Fn(&String) -> JsonRpcClient, ListenerError> {
+        let headers = self.header_map()?;
+        let connector = if self.needs_client() {
+            Some(JsonRpcClient::with(self.client()?))
+        } else {
+            None
+        };
+
+        Ok(move |url: &String| {
+            let mut client = match &connector {
+                Some(connector) => connector.connect(url),
+                None => JsonRpcClient::connect(url),
+            };
+            client.headers_mut().extend(headers.clone());
+            client
+        })
+    }
+
+    fn needs_client(&self) -> bool {
+        self.proxy.is_some()
+            || self.connect_timeout.is_some()
+            || !self.root_certificates.is_empty()
+            || self.accept_invalid_certs
+    }
+
+    fn header_map(&self) -> Result<HeaderMap, ListenerError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| invalid("header name", name, e))?;
+            let mut header_value =
+                HeaderValue::from_str(value).map_err(|e| invalid("header value for", name, e))?;
+            // Keeps API keys out of debug output.
+            header_value.set_sensitive(true);
+            headers.append(header_name, header_value);
+        }
+        Ok(headers)
+    }
+
+    fn client(&self) -> Result<reqwest::Client, ListenerError> {
+        // The shared client sends this too; a dedicated one has to be told.
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let mut builder = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy).map_err(|e| invalid("proxy", proxy, e))?);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        for pem in &self.root_certificates {
+            let certificate = Certificate::from_pem(pem).map_err(|e| {
+                ListenerError::InvalidConfig(format!("invalid root certificate: {}", e))
+            })?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        builder
+            .build()
+            .map_err(|e| ListenerError::InvalidConfig(format!("invalid HTTP client: {}", e)))
+    }
+}
+
+fn invalid(what: &str, value: &str, err: impl std::fmt::Display) -> ListenerError {
+    ListenerError::InvalidConfig(format!("invalid {} `{}`: {}", what, value, err))
+}
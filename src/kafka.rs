@@ -0,0 +1,78 @@
+use crate::sink::render_topic;
+use crate::{EventEnvelope, EventSink, ListenerError, SinkFormat, SinkKey};
+use futures::future::BoxFuture;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use std::time::Duration;
+
+const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Produces every event to a Kafka topic. The topic may contain `{account_id}`,
+// `{standard}` and `{event}` placeholders.
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    key: SinkKey,
+    format: SinkFormat,
+    queue_timeout: Duration,
+}
+
+impl KafkaSink {
+    pub fn new(brokers: &str, topic: &str) -> Result<Self, ListenerError> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", brokers);
+        Self::from_config(&config, topic)
+    }
+
+    pub fn from_config(config: &ClientConfig, topic: &str) -> Result<Self, ListenerError> {
+        let producer = config
+            .create()
+            .map_err(|e| ListenerError::SinkError(e.to_string()))?;
+
+        Ok(Self {
+            producer,
+            topic: topic.to_string(),
+            key: SinkKey::default(),
+            format: SinkFormat::default(),
+            queue_timeout: DEFAULT_QUEUE_TIMEOUT,
+        })
+    }
+
+    pub fn key(mut self, key: SinkKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn format(mut self, format: SinkFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    // How long to wait for room in the producer queue when it is full.
+    pub fn queue_timeout(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = timeout;
+        self
+    }
+}
+
+impl EventSink for KafkaSink {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        Box::pin(async move {
+            let payload = self.format.encode(envelope)?;
+            let topic = render_topic(&self.topic, envelope);
+            let key = self.key.key(envelope);
+
+            let mut record = FutureRecord::<str, [u8]>::to(&topic).payload(&payload);
+            if let Some(key) = &key {
+                record = record.key(key.as_str());
+            }
+
+            self.producer
+                .send(record, Timeout::After(self.queue_timeout))
+                .await
+                .map(|_| ())
+                .map_err(|(e, _)| ListenerError::SinkError(e.to_string()))
+        })
+    }
+}
@@ -1,7 +1,122 @@
+mod cache;
+mod checkpoint;
+mod config;
+mod data_filter;
+mod dead_letter;
+mod decoder;
+mod dedupe;
+mod dispatch;
+#[cfg(feature = "email")]
+mod email;
 mod error;
+mod event;
+mod file_sink;
+mod filter;
+#[cfg(feature = "graphql")]
+mod graphql;
+mod handle;
+mod health;
+mod http;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "lake")]
+mod lake;
 mod listener;
+mod middleware;
 mod models;
+mod multi;
+#[cfg(feature = "nats")]
+mod nats;
+mod network;
+#[cfg(feature = "notifier")]
+mod notifier;
+mod polling;
+#[cfg(any(feature = "webhook", feature = "notifier"))]
+mod post;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod queue;
+mod rate_limit;
+mod registry;
+mod retry;
+mod runtime;
+#[cfg(feature = "schema")]
+mod schema;
+#[cfg(feature = "http-server")]
+mod server;
+mod sink;
+mod snapshot;
+mod source;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod start;
+mod status;
+mod subscription;
+#[cfg(feature = "templates")]
+mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "webhook")]
+mod webhook;
+#[cfg(feature = "websocket")]
+mod websocket;
+mod window;
 
-pub use error::ListenerError;
-pub use listener::{NearEventListener, NearEventListenerBuilder};
-pub use models::EventLog;
+pub use checkpoint::{CheckpointStore, FileCheckpointStore};
+pub use data_filter::{contains, equals, exists, DataPredicate};
+pub use dead_letter::{DeadLetter, DeadLetterSink, FileDeadLetterSink};
+pub use decoder::{BorshLogDecoder, EventJsonDecoder, LogDecoder};
+pub use dedupe::{DeliveredEvent, DeliveryMode};
+#[cfg(feature = "email")]
+pub use email::EmailSink;
+pub use error::{ErrorAction, ListenerError};
+pub use event::NearEvent;
+pub use file_sink::FileSink;
+pub use filter::{EventFilter, ExecutionStatusFilter, ExecutorFilter};
+pub use handle::ListenerHandle;
+pub use health::HealthCheck;
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaSink;
+pub use listener::{NearEventListener, NearEventListenerBuilder, Unwatched, Watched};
+pub use middleware::{EventMiddleware, Next};
+pub use models::{
+    AccountChange, AccountChangeKind, AttributedLog, BlockEvents, BlockHeader, BlockProcessed,
+    EventContext, EventEnvelope, EventLog, EventNotification, ExecutionMetrics, ExecutionStatus,
+    FailedCall, FtBurnLog, FtMintLog, FtTransferLog, ListenerEvent, LogSource, MatchedReceipt,
+    MatchedTransaction, Nep141Event, Nep171Event, NftBurnLog, NftContractMetadataUpdateLog,
+    NftMintLog, NftTransferLog, Payment, RawLog, ReceiptDetails, StateChange,
+};
+pub use multi::{MultiNetworkListener, NetworkEvent};
+#[cfg(feature = "nats")]
+pub use nats::NatsSink;
+pub use near_event_listener_derive::NearEvent;
+pub use near_primitives::types::Finality;
+pub use near_primitives::views::TxExecutionStatus;
+pub use network::Network;
+#[cfg(feature = "notifier")]
+pub use notifier::{ChatPlatform, NotifierSink};
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresSink;
+pub use queue::OverflowPolicy;
+pub use registry::EventRegistry;
+pub use retry::RetryPolicy;
+#[cfg(feature = "tokio-runtime")]
+pub use runtime::TokioRuntime;
+pub use runtime::{Runtime, TaskHandle};
+#[cfg(feature = "schema")]
+pub use schema::SchemaRegistry;
+#[cfg(feature = "http-server")]
+pub use server::EventServer;
+pub use sink::{render_topic, EventSink, SinkFormat, SinkKey, ENVELOPE_AVRO_SCHEMA};
+pub use snapshot::ListenerSnapshot;
+pub use source::DataSource;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+pub use start::StartPosition;
+pub use status::{ListenerState, ListenerStatus};
+pub use subscription::{Subscription, SubscriptionId};
+#[cfg(feature = "templates")]
+pub use template::EventTemplate;
+#[cfg(feature = "webhook")]
+pub use webhook::{WebhookMetrics, WebhookSink, SIGNATURE_HEADER};
+pub use window::{EventWindow, WindowSize};
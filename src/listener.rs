@@ -1,257 +1,4340 @@
-use crate::{EventLog, ListenerError};
+use crate::cache::ResponseCache;
+use crate::config::ListenerConfig;
+use crate::data_filter::{DataPath, DataPredicate};
+use crate::dedupe::{DedupeCache, DeliveredEvent, EventKey};
+use crate::dispatch::EventHandlers;
+use crate::filter::glob_matches;
+use crate::handle::WatchChange;
+use crate::http::HttpOptions;
+#[cfg(feature = "lake")]
+use crate::lake::LakeClient;
+use crate::middleware::Next;
+use crate::polling::{
+    is_behind_head, PollTimer, DEFAULT_CATCH_UP_INTERVAL, DEFAULT_POLL_INTERVAL,
+    LAG_CHECK_INTERVAL, OUTCOME_DELAY,
+};
+use crate::queue::delivery_queue;
+use crate::rate_limit::RateLimiter;
+use crate::runtime;
+#[cfg(feature = "schema")]
+use crate::schema::SchemaRegistry;
+use crate::status::RunGuard;
+#[cfg(feature = "websocket")]
+use crate::websocket::BlockSubscription;
+use crate::window::TumblingWindows;
+use crate::{
+    AccountChange, AccountChangeKind, AttributedLog, BlockEvents, BlockHeader, BlockProcessed,
+    CheckpointStore, DataSource, DeadLetter, DeadLetterSink, DeliveryMode, ErrorAction,
+    EventContext, EventEnvelope, EventFilter, EventJsonDecoder, EventLog, EventMiddleware,
+    EventNotification, EventRegistry, EventSink, EventWindow, ExecutionMetrics, ExecutionStatus,
+    ExecutionStatusFilter, ExecutorFilter, FailedCall, HealthCheck, ListenerError, ListenerEvent,
+    ListenerHandle, ListenerSnapshot, ListenerStatus, LogDecoder, LogSource, MatchedReceipt,
+    MatchedTransaction, NearEvent, Network, OverflowPolicy, Payment, RawLog, ReceiptDetails,
+    RetryPolicy, Runtime, StartPosition, StateChange, Subscription, TaskHandle, WindowSize,
+};
+use futures::future::{self, Either, Future, FutureExt};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use log::{debug, error, info, warn};
 use near_jsonrpc_client::errors::{JsonRpcError, JsonRpcServerError};
+use near_jsonrpc_client::methods::RpcMethod;
 use near_jsonrpc_client::methods::{block::RpcBlockError, chunk::ChunkReference};
-use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_jsonrpc_client::{methods, JsonRpcClient, MethodCallResult};
+use near_jsonrpc_primitives::types::query::{QueryResponseKind, RpcQueryError};
+use near_jsonrpc_primitives::types::receipts::{ReceiptReference, RpcReceiptError};
 use near_jsonrpc_primitives::types::transactions::RpcTransactionResponse;
+use near_primitives::action::delegate::DelegateAction;
 use near_primitives::hash::CryptoHash;
-use near_primitives::types::{BlockId, BlockReference, Finality};
-use near_primitives::views::{ActionView, BlockView, ChunkView, FinalExecutionOutcomeViewEnum};
+#[cfg(feature = "lake")]
+use near_primitives::shard_layout::account_id_to_shard_id;
+#[cfg(feature = "lake")]
+use near_primitives::types::ShardId;
+use near_primitives::types::{BlockId, BlockReference, Finality, StoreKey, TransactionOrReceiptId};
+use near_primitives::views::{
+    AccessKeyPermissionView, AccessKeyView, ActionView, BlockView, ChunkHeaderView, ChunkView,
+    FinalExecutionOutcomeViewEnum, QueryRequest, ReceiptEnumView, ReceiptView,
+    StateChangeCauseView, StateChangeValueView, StateChangeWithCauseView, StateChangesRequestView,
+    TxExecutionStatus,
+};
 use near_sdk::AccountId;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+#[cfg(feature = "lake")]
+use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::pin::pin;
 use std::str::FromStr;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc};
+use url::Url;
+
+const DEFAULT_CHUNK_CONCURRENCY: usize = 8;
+const DEFAULT_TRANSACTION_CONCURRENCY: usize = 8;
+const DEFAULT_BACKFILL_CONCURRENCY: usize = 16;
+const DEFAULT_DEDUPE_CAPACITY: usize = 10_000;
+// How long the primary node's earliest available height is trusted before it
+// is looked up again.
+const EARLIEST_BLOCK_TTL: Duration = Duration::from_secs(60);
+// Number of recently processed non-final blocks kept to detect forks.
+const REORG_WINDOW: usize = 64;
+// Number of receipts of relayed transactions remembered so that the receipt
+// scan does not deliver them a second time.
+const RELAYED_RECEIPTS_CAPACITY: usize = 1024;
+// Number of skipped heights remembered for `repair_gaps`; the lowest are
+// forgotten first.
+const SKIPPED_HEIGHTS_CAPACITY: usize = 10_000;
+// Number of lines read ahead of the replay.
+const REPLAY_BUFFER: usize = 256;
+
+type NameMatcher = Box<dyn Fn(&str) -> bool + Send + Sync>;
+type DataMatcher = Box<dyn Fn(&Value) -> bool + Send + Sync>;
+type ErrorHandler = Arc<dyn Fn(&ListenerError) -> ErrorAction + Send + Sync>;
+type LagHandler = Box<dyn Fn(u64) + Send + Sync>;
+type RawLogHandler = Box<dyn Fn(RawLog) + Send + Sync>;
+type StateChangeHandler = Box<dyn Fn(StateChange) + Send + Sync>;
+type AccountChangeHandler = Box<dyn Fn(AccountChange) + Send + Sync>;
+type PaymentHandler = Box<dyn Fn(Payment) + Send + Sync>;
+type FailureHandler = Box<dyn Fn(FailedCall) + Send + Sync>;
+type BlockProcessedHandler = Box<dyn Fn(BlockProcessed) + Send + Sync>;
+type StartHandler = Box<dyn Fn(u64) + Send + Sync>;
+type BlockStartHandler = Box<dyn Fn(BlockHeader) + Send + Sync>;
+type CatchUpHandler = Box<dyn Fn(u64) + Send + Sync>;
+type StopHandler = Box<dyn Fn(Option<&ListenerError>) + Send + Sync>;
+
+struct ProcessedBlock {
+    height: u64,
+    hash: CryptoHash,
+    timestamp: u64,
+    envelopes: Vec<EventEnvelope>,
+}
+
+struct LagAlert {
+    threshold: u64,
+    handler: LagHandler,
+    last_check: Option<Instant>,
+}
+
+// The first watched function call of a transaction or receipt.
+struct WatchedCall {
+    method_name: String,
+    gas: u64,
+    deposit: u128,
+}
+
+struct MatchedPayment {
+    sender_id: AccountId,
+    signer_id: AccountId,
+    receiver_id: AccountId,
+    amount: u128,
+    method_name: Option<String>,
+    tx_hash: Option<CryptoHash>,
+    receipt_id: Option<CryptoHash>,
+}
+
+struct OutcomeLogs {
+    receipt_id: Option<CryptoHash>,
+    executor_id: AccountId,
+    logs: Vec<String>,
+    status: Option<ExecutionStatus>,
+    receipt: Option<ReceiptDetails>,
+    metrics: Option<ExecutionMetrics>,
+    // The execution error, when this outcome failed.
+    failure: Option<String>,
+}
 
-#[derive(Debug)]
 pub struct NearEventListener {
     pub client: JsonRpcClient,
-    pub account_id: String,
-    pub method_name: String,
+    pub archival_client: Option<JsonRpcClient>,
+    // Endpoints to switch `client` to when it fails a health check, in turn.
+    fallback_clients: VecDeque<JsonRpcClient>,
+    pub account_ids: Vec<String>,
+    pub account_patterns: Vec<String>,
+    pub method_names: Vec<String>,
     pub last_processed_block: u64,
+    pub event_filter: EventFilter,
+    pub finality: Finality,
+    // Every one of them must accept an event's data for it to be delivered.
+    data_filters: Vec<DataMatcher>,
+    factories: Vec<String>,
+    // Watched storage prefixes, none of them covered by another of the same account.
+    storage_watches: Vec<(AccountId, Vec<u8>)>,
+    account_watches: Vec<AccountId>,
+    executor_filter: ExecutorFilter,
+    delivery_on: ExecutionStatusFilter,
+    wait_until: TxExecutionStatus,
+    subscriptions: Vec<Subscription>,
+    middleware: Vec<Box<dyn EventMiddleware>>,
+    event_handlers: EventHandlers,
+    handle: ListenerHandle,
+    head_height: u64,
+    pending_start: Option<StartPosition>,
+    // Checked against the nodes' chain ID before the first block is read.
+    expected_chain: Option<String>,
+    replay_last: u64,
+    until_height: Option<u64>,
+    max_events: Option<usize>,
+    events_emitted: usize,
+    // Sequence number of the last event delivered to the listener's own consumers.
+    sequence: u64,
+    recent_blocks: VecDeque<ProcessedBlock>,
+    // Heights passed over because no block was found there.
+    skipped_heights: BTreeSet<u64>,
+    // The last polled block, until its events have been delivered.
+    uncommitted_block: Option<(u64, Vec<EventEnvelope>)>,
+    // The block the last poll processed or reverted, for batched delivery.
+    polled_block: Option<(BlockHeader, bool)>,
+    account_matcher: Option<NameMatcher>,
+    method_matcher: Option<NameMatcher>,
+    checkpoint_store: Option<Box<dyn CheckpointStore>>,
+    dead_letter_sink: Option<Box<dyn DeadLetterSink>>,
+    include_raw_logs: bool,
+    include_receipt_views: bool,
+    include_execution_metrics: bool,
+    strict_validation: bool,
+    log_decoder: Box<dyn LogDecoder>,
+    #[cfg(feature = "schema")]
+    schema_registry: Option<SchemaRegistry>,
+    detect_payments: bool,
+    detect_failures: bool,
+    raw_log_handler: Option<RawLogHandler>,
+    state_change_handler: Option<StateChangeHandler>,
+    account_change_handler: Option<AccountChangeHandler>,
+    payment_handler: Option<PaymentHandler>,
+    failure_handler: Option<FailureHandler>,
+    block_processed_handler: Option<BlockProcessedHandler>,
+    start_handler: Option<StartHandler>,
+    block_start_handler: Option<BlockStartHandler>,
+    catch_up_handler: Option<CatchUpHandler>,
+    stop_handler: Option<StopHandler>,
+    // Whether `on_start` was called for the current run.
+    run_started: bool,
+    // Whether the head was reached since the listener last fell behind.
+    caught_up: bool,
+    error_handler: Option<ErrorHandler>,
+    lag_alert: Option<LagAlert>,
+    retry_policy: RetryPolicy,
+    request_timeout: Option<Duration>,
+    call_deadline: Option<Duration>,
+    health_check: Option<HealthCheck>,
+    runtime: Arc<dyn Runtime>,
+    last_health_check: Option<Instant>,
+    rate_limiter: Option<RateLimiter>,
+    dedupe: Option<Mutex<DedupeCache>>,
+    response_cache: Option<Mutex<ResponseCache>>,
+    earliest_block: Mutex<Option<(u64, Instant)>>,
+    relayed_receipts: Mutex<VecDeque<String>>,
+    poll_timer: PollTimer,
+    chunk_concurrency: usize,
+    transaction_concurrency: usize,
+    backfill_concurrency: usize,
+    delivery_queue: Option<(usize, OverflowPolicy)>,
+    #[cfg(feature = "lake")]
+    shard_filter: bool,
+    #[cfg(feature = "lake")]
+    watched_shards: Mutex<Option<(CryptoHash, HashSet<ShardId>)>>,
+    #[cfg(feature = "lake")]
+    lake: Option<LakeClient>,
+    #[cfg(feature = "websocket")]
+    subscription: Option<BlockSubscription>,
+}
+
+impl fmt::Debug for NearEventListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NearEventListener")
+            .field("client", &self.client)
+            .field("archival_client", &self.archival_client)
+            .field("account_ids", &self.account_ids)
+            .field("account_patterns", &self.account_patterns)
+            .field("method_names", &self.method_names)
+            .field("last_processed_block", &self.last_processed_block)
+            .field("event_filter", &self.event_filter)
+            .field("finality", &self.finality)
+            .field("subscriptions", &self.subscriptions)
+            .field("event_handlers", &self.event_handlers)
+            .finish_non_exhaustive()
+    }
 }
 
-pub struct NearEventListenerBuilder {
+// The options collected by `NearEventListenerBuilder`, whatever its state.
+struct BuilderOptions {
     rpc_url: String,
-    account_id: String,
-    method_name: String,
+    archival_rpc_url: Option<String>,
+    fallback_rpc_urls: Vec<String>,
+    http: HttpOptions,
+    account_ids: Vec<String>,
+    account_patterns: Vec<String>,
+    account_matcher: Option<NameMatcher>,
+    method_names: Vec<String>,
+    method_matcher: Option<NameMatcher>,
+    factories: Vec<String>,
+    storage_watches: Vec<(String, Vec<u8>)>,
+    account_watches: Vec<String>,
     last_processed_block: u64,
+    start_position: StartPosition,
+    expected_chain: Option<String>,
+    replay_last: u64,
+    until_height: Option<u64>,
+    max_events: Option<usize>,
+    event_filter: EventFilter,
+    data_paths: Vec<(String, DataPredicate)>,
+    data_matchers: Vec<DataMatcher>,
+    finality: Finality,
+    executor_filter: ExecutorFilter,
+    delivery_on: ExecutionStatusFilter,
+    wait_until: TxExecutionStatus,
+    subscriptions: Vec<Subscription>,
+    middleware: Vec<Box<dyn EventMiddleware>>,
+    source: DataSource,
+    checkpoint_store: Option<Box<dyn CheckpointStore>>,
+    dead_letter_sink: Option<Box<dyn DeadLetterSink>>,
+    include_raw_logs: bool,
+    include_receipt_views: bool,
+    include_execution_metrics: bool,
+    strict_validation: bool,
+    log_decoder: Box<dyn LogDecoder>,
+    #[cfg(feature = "schema")]
+    schema_registry: Option<SchemaRegistry>,
+    detect_payments: bool,
+    detect_failures: bool,
+    raw_log_handler: Option<RawLogHandler>,
+    state_change_handler: Option<StateChangeHandler>,
+    account_change_handler: Option<AccountChangeHandler>,
+    payment_handler: Option<PaymentHandler>,
+    failure_handler: Option<FailureHandler>,
+    block_processed_handler: Option<BlockProcessedHandler>,
+    start_handler: Option<StartHandler>,
+    block_start_handler: Option<BlockStartHandler>,
+    catch_up_handler: Option<CatchUpHandler>,
+    stop_handler: Option<StopHandler>,
+    error_handler: Option<ErrorHandler>,
+    lag_alert: Option<LagAlert>,
+    retry_policy: RetryPolicy,
+    request_timeout: Option<Duration>,
+    call_deadline: Option<Duration>,
+    health_check: Option<HealthCheck>,
+    runtime: Option<Arc<dyn Runtime>>,
+    max_rps: Option<u32>,
+    delivery: DeliveryMode,
+    dedupe_capacity: usize,
+    poll_interval: Duration,
+    catch_up_interval: Duration,
+    adaptive_polling: bool,
+    chunk_concurrency: usize,
+    transaction_concurrency: usize,
+    backfill_concurrency: usize,
+    delivery_queue: Option<(usize, OverflowPolicy)>,
+    shard_filter: bool,
+    cache_capacity: Option<usize>,
+    // Carried over by `NearEventListener::from_state`.
+    restored_sequence: Option<u64>,
+    restored_deliveries: Vec<EventKey>,
 }
 
-impl NearEventListenerBuilder {
+// The type states of `NearEventListenerBuilder`: `build` is only available
+// once something to watch was given, e.g. with `account_id` or `subscription`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unwatched {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watched {}
+
+pub struct NearEventListenerBuilder<S = Watched> {
+    options: BuilderOptions,
+    state: PhantomData<S>,
+}
+
+impl NearEventListenerBuilder<Unwatched> {
     pub fn new(rpc_url: &str) -> Self {
-        Self {
+        let options = BuilderOptions {
             rpc_url: rpc_url.to_string(),
-            account_id: String::new(),
-            method_name: String::new(),
+            archival_rpc_url: None,
+            fallback_rpc_urls: Vec::new(),
+            http: HttpOptions::default(),
+            account_ids: Vec::new(),
+            account_patterns: Vec::new(),
+            account_matcher: None,
+            method_names: Vec::new(),
+            method_matcher: None,
+            factories: Vec::new(),
+            storage_watches: Vec::new(),
+            account_watches: Vec::new(),
             last_processed_block: 0,
+            start_position: StartPosition::Checkpoint,
+            expected_chain: None,
+            replay_last: 0,
+            until_height: None,
+            max_events: None,
+            event_filter: EventFilter::default(),
+            data_paths: Vec::new(),
+            data_matchers: Vec::new(),
+            finality: Finality::Final,
+            executor_filter: ExecutorFilter::WatchedAccounts,
+            delivery_on: ExecutionStatusFilter::SuccessOnly,
+            wait_until: TxExecutionStatus::ExecutedOptimistic,
+            subscriptions: Vec::new(),
+            middleware: Vec::new(),
+            source: DataSource::Rpc,
+            checkpoint_store: None,
+            dead_letter_sink: None,
+            include_raw_logs: false,
+            include_receipt_views: false,
+            include_execution_metrics: false,
+            strict_validation: false,
+            log_decoder: Box::new(EventJsonDecoder::new()),
+            #[cfg(feature = "schema")]
+            schema_registry: None,
+            detect_payments: false,
+            detect_failures: false,
+            raw_log_handler: None,
+            state_change_handler: None,
+            account_change_handler: None,
+            payment_handler: None,
+            failure_handler: None,
+            block_processed_handler: None,
+            start_handler: None,
+            block_start_handler: None,
+            catch_up_handler: None,
+            stop_handler: None,
+            error_handler: None,
+            lag_alert: None,
+            retry_policy: RetryPolicy::none(),
+            request_timeout: None,
+            call_deadline: None,
+            health_check: None,
+            runtime: runtime::default_runtime(),
+            max_rps: None,
+            delivery: DeliveryMode::AtLeastOnce,
+            dedupe_capacity: DEFAULT_DEDUPE_CAPACITY,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            catch_up_interval: DEFAULT_CATCH_UP_INTERVAL,
+            adaptive_polling: false,
+            chunk_concurrency: DEFAULT_CHUNK_CONCURRENCY,
+            transaction_concurrency: DEFAULT_TRANSACTION_CONCURRENCY,
+            backfill_concurrency: DEFAULT_BACKFILL_CONCURRENCY,
+            delivery_queue: None,
+            shard_filter: false,
+            cache_capacity: None,
+            restored_sequence: None,
+            restored_deliveries: Vec::new(),
+        };
+        NearEventListenerBuilder {
+            options,
+            state: PhantomData,
+        }
+    }
+}
+
+impl NearEventListenerBuilder {
+    // Reads the options from a TOML file whose keys are named after the builder
+    // methods. Callbacks and sinks can still be added to the returned builder.
+    #[cfg(feature = "config")]
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, ListenerError> {
+        let contents = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            ListenerError::InvalidConfig(format!("{}: {}", path.as_ref().display(), e))
+        })?;
+        ListenerConfig::from_toml(&contents)?.into_builder()
+    }
+
+    // Same options as `from_config_file`, read from `NEAR_EVENT_LISTENER_*`
+    // environment variables.
+    pub fn from_env() -> Result<Self, ListenerError> {
+        ListenerConfig::from_env()?.into_builder()
+    }
+}
+
+impl<S> NearEventListenerBuilder<S> {
+    // Moves to the state where `build` is available. Builders whose watch list
+    // is only known at run time, such as those read from configuration, rely
+    // on the check `build` still makes.
+    pub(crate) fn into_watched(self) -> NearEventListenerBuilder<Watched> {
+        NearEventListenerBuilder {
+            options: self.options,
+            state: PhantomData,
+        }
+    }
+
+    pub fn archival_rpc_url(mut self, rpc_url: &str) -> Self {
+        self.options.archival_rpc_url = Some(rpc_url.to_string());
+        self
+    }
+
+    // An endpoint of the same network to fail over to when the current one
+    // fails a health check. Fallbacks are tried in the order they were added.
+    pub fn fallback_rpc_url(mut self, rpc_url: &str) -> Self {
+        self.options.fallback_rpc_urls.push(rpc_url.to_string());
+        self
+    }
+
+    // Sends the header with every RPC call, to the main, archival and
+    // fallback nodes alike.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.options
+            .http
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // The `x-api-key` header paid RPC providers authenticate with.
+    pub fn rpc_api_key(mut self, api_key: &str) -> Self {
+        self.options.http.add_api_key(api_key);
+        self
+    }
+
+    // Routes RPC calls through an HTTP, HTTPS or SOCKS proxy.
+    pub fn proxy(mut self, proxy_url: &str) -> Self {
+        self.options.http.proxy = Some(proxy_url.to_string());
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.options.http.connect_timeout = Some(timeout);
+        self
+    }
+
+    // Trusts a PEM-encoded certificate in addition to the system's, e.g. for a
+    // node behind a private CA.
+    pub fn root_certificate(mut self, pem: &[u8]) -> Self {
+        self.options.http.root_certificates.push(pem.to_vec());
+        self
+    }
+
+    // Skips TLS certificate verification. Only meant for local nodes with
+    // self-signed certificates.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.options.http.accept_invalid_certs = accept;
+        self
+    }
+
+    pub fn account_id(mut self, account_id: &str) -> NearEventListenerBuilder<Watched> {
+        self.options.account_ids.push(account_id.to_string());
+        self.into_watched()
+    }
+
+    pub fn account_ids(mut self, account_ids: &[&str]) -> NearEventListenerBuilder<Watched> {
+        self.options
+            .account_ids
+            .extend(account_ids.iter().map(|account_id| account_id.to_string()));
+        self.into_watched()
+    }
+
+    pub fn account_pattern(mut self, pattern: &str) -> NearEventListenerBuilder<Watched> {
+        self.options.account_patterns.push(pattern.to_string());
+        self.into_watched()
+    }
+
+    pub fn account_matcher<F>(mut self, matcher: F) -> NearEventListenerBuilder<Watched>
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.options.account_matcher = Some(Box::new(matcher));
+        self.into_watched()
+    }
+
+    pub fn method_name(mut self, method_name: &str) -> Self {
+        self.options.method_names.push(method_name.to_string());
+        self
+    }
+
+    pub fn method_names(mut self, method_names: &[&str]) -> Self {
+        self.options.method_names.extend(
+            method_names
+                .iter()
+                .map(|method_name| method_name.to_string()),
+        );
+        self
+    }
+
+    pub fn method_matcher<F>(mut self, matcher: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.options.method_matcher = Some(Box::new(matcher));
+        self
+    }
+
+    // Watches the direct subaccounts `factory_id` creates from now on, e.g. the
+    // stores of a marketplace factory, by adding them to the watched accounts.
+    pub fn factory(mut self, factory_id: &str) -> NearEventListenerBuilder<Watched> {
+        self.options.factories.push(factory_id.to_string());
+        self.into_watched()
+    }
+
+    // Reports writes to the storage of `account_id` under keys starting with
+    // `key_prefix`, for contracts whose state changes without events being
+    // logged. An empty prefix covers all of the contract's storage.
+    pub fn watch_storage(
+        mut self,
+        account_id: &str,
+        key_prefix: &[u8],
+    ) -> NearEventListenerBuilder<Watched> {
+        self.options
+            .storage_watches
+            .push((account_id.to_string(), key_prefix.to_vec()));
+        self.into_watched()
+    }
+
+    // Reports changes to the account itself: its balance, its access keys, the
+    // code deployed to it and its deletion. Meant for guarding treasuries and
+    // other accounts whose keys and funds matter more than their events.
+    pub fn watch_account_changes(mut self, account_id: &str) -> NearEventListenerBuilder<Watched> {
+        self.options.account_watches.push(account_id.to_string());
+        self.into_watched()
+    }
+
+    pub fn last_processed_block(mut self, block: u64) -> Self {
+        self.options.last_processed_block = block;
+        self
+    }
+
+    pub fn start_from(mut self, position: StartPosition) -> Self {
+        self.options.start_position = position;
+        self
+    }
+
+    // Starts at the block that included the transaction, e.g. a contract's
+    // deployment, which is looked up when the listener starts.
+    pub fn start_after_tx(mut self, tx_hash: &str, signer_id: &str) -> Self {
+        self.options.start_position = StartPosition::Transaction {
+            tx_hash: tx_hash.to_string(),
+            signer_id: signer_id.to_string(),
+        };
+        self
+    }
+
+    // Fails the listener before it reads any block when the RPC node, or the
+    // archival one, reports another chain ID, e.g. a listener meant for
+    // mainnet pointed at a testnet endpoint.
+    pub fn expect_chain(mut self, chain_id: &str) -> Self {
+        self.options.expected_chain = Some(chain_id.to_string());
+        self
+    }
+
+    // Steps back `blocks` blocks from the start position, e.g. to deliver again
+    // the events a sink lost before the last checkpoint.
+    pub fn replay_last(mut self, blocks: u64) -> Self {
+        self.options.replay_last = blocks;
+        self
+    }
+
+    pub fn until_height(mut self, height: u64) -> Self {
+        self.options.until_height = Some(height);
+        self
+    }
+
+    pub fn max_events(mut self, max_events: usize) -> Self {
+        self.options.max_events = Some(max_events);
+        self
+    }
+
+    pub fn event_filter(mut self, filter: EventFilter) -> Self {
+        self.options.event_filter = filter;
+        self
+    }
+
+    // Delivers only events whose data has a value at `path` that satisfies
+    // `predicate`, e.g. `.data_filter("$[0].token_ids[*]", equals("123"))`.
+    // Paths are checked by `build`.
+    pub fn data_filter(mut self, path: &str, predicate: DataPredicate) -> Self {
+        self.options.data_paths.push((path.to_string(), predicate));
+        self
+    }
+
+    pub fn data_filter_fn<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Value) -> bool + Send + Sync + 'static,
+    {
+        self.options.data_matchers.push(Box::new(filter));
+        self
+    }
+
+    pub fn finality(mut self, finality: Finality) -> Self {
+        self.options.finality = finality;
+        self
+    }
+
+    pub fn executor_filter(mut self, filter: ExecutorFilter) -> Self {
+        self.options.executor_filter = filter;
+        self
+    }
+
+    pub fn delivery_on(mut self, filter: ExecutionStatusFilter) -> Self {
+        self.options.delivery_on = filter;
+        self
+    }
+
+    // How far a transaction must have executed before its outcome is read.
+    // Levels below `ExecutedOptimistic` can return outcomes that are missing
+    // the logs of receipts that have not run yet.
+    pub fn wait_until(mut self, wait_until: TxExecutionStatus) -> Self {
+        self.options.wait_until = wait_until;
+        self
+    }
+
+    // Appends a step to the middleware chain; events go through the chain in
+    // the order the steps were added.
+    pub fn middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: EventMiddleware + 'static,
+    {
+        self.options.middleware.push(Box::new(middleware));
+        self
+    }
+
+    pub fn subscription(mut self, subscription: Subscription) -> NearEventListenerBuilder<Watched> {
+        self.options.subscriptions.push(subscription);
+        self.into_watched()
+    }
+
+    pub fn source(mut self, source: DataSource) -> Self {
+        self.options.source = source;
+        self
+    }
+
+    pub fn checkpoint_store(mut self, store: Box<dyn CheckpointStore>) -> Self {
+        self.options.checkpoint_store = Some(store);
+        self
+    }
+
+    pub fn dead_letter_sink(mut self, sink: Box<dyn DeadLetterSink>) -> Self {
+        self.options.dead_letter_sink = Some(sink);
+        self
+    }
+
+    pub fn include_raw_logs(mut self, include: bool) -> Self {
+        self.options.include_raw_logs = include;
+        self
+    }
+
+    // Attaches the receipt and execution outcome behind each event to its
+    // context. Receipts the node did not return with the transaction are
+    // fetched one by one, so this costs extra requests.
+    pub fn include_receipt_views(mut self, include: bool) -> Self {
+        self.options.include_receipt_views = include;
+        self
+    }
+
+    // Adds the gas and tokens burnt by each event's transaction to its
+    // context, e.g. to watch a method for gas regressions.
+    pub fn include_execution_metrics(mut self, include: bool) -> Self {
+        self.options.include_execution_metrics = include;
+        self
+    }
+
+    // Rejects events that parse but do not follow NEP-297, e.g. a version that
+    // is not semver. They are handled like malformed events.
+    pub fn strict_validation(mut self, strict: bool) -> Self {
+        self.options.strict_validation = strict;
+        self
+    }
+
+    // Replaces the `EVENT_JSON:` decoder for logs of the watched accounts, e.g.
+    // with a `BorshLogDecoder` for a contract logging Borsh. Subscriptions can
+    // bring their own.
+    pub fn log_decoder<D>(mut self, decoder: D) -> Self
+    where
+        D: LogDecoder + 'static,
+    {
+        self.options.log_decoder = Box::new(decoder);
+        self
+    }
+
+    // Reads events logged after any of `prefixes` instead of `EVENT_JSON:`.
+    pub fn log_prefixes(self, prefixes: &[&str]) -> Self {
+        self.log_decoder(EventJsonDecoder::with_prefixes(prefixes))
+    }
+
+    // Checks the data of events with a registered schema. Mismatches are
+    // handled like malformed events and go to the dead letter sink.
+    #[cfg(feature = "schema")]
+    pub fn schema_registry(mut self, registry: SchemaRegistry) -> Self {
+        self.options.schema_registry = Some(registry);
+        self
+    }
+
+    // Reports NEAR sent to the watched accounts, in transfers or attached to
+    // function calls, whether or not the receiving contract logs anything.
+    pub fn detect_payments(mut self, detect: bool) -> Self {
+        self.options.detect_payments = detect;
+        self
+    }
+
+    pub fn on_raw_log<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(RawLog) + Send + Sync + 'static,
+    {
+        self.options.include_raw_logs = true;
+        self.options.raw_log_handler = Some(Box::new(handler));
+        self
+    }
+
+    pub fn on_state_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(StateChange) + Send + Sync + 'static,
+    {
+        self.options.state_change_handler = Some(Box::new(handler));
+        self
+    }
+
+    pub fn on_account_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(AccountChange) + Send + Sync + 'static,
+    {
+        self.options.account_change_handler = Some(Box::new(handler));
+        self
+    }
+
+    pub fn on_payment<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Payment) + Send + Sync + 'static,
+    {
+        self.options.detect_payments = true;
+        self.options.payment_handler = Some(Box::new(handler));
+        self
+    }
+
+    // Reports watched function calls whose execution failed, whether or not
+    // they logged anything, as `EventNotification::Failure`.
+    pub fn detect_failures(mut self, detect: bool) -> Self {
+        self.options.detect_failures = detect;
+        self
+    }
+
+    pub fn on_failure<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(FailedCall) + Send + Sync + 'static,
+    {
+        self.options.detect_failures = true;
+        self.options.failure_handler = Some(Box::new(handler));
+        self
+    }
+
+    // Called once each polled block is done, whether or not it had matching
+    // events, so consumers can move their own watermarks along and tell that
+    // the listener is alive. Reverted blocks are not reported.
+    pub fn on_block_processed<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(BlockProcessed) + Send + Sync + 'static,
+    {
+        self.options.block_processed_handler = Some(Box::new(handler));
+        self
+    }
+
+    // Called once per run, with the first height the listener reads, after
+    // the start position is resolved and before any block is processed.
+    pub fn on_start<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        self.options.start_handler = Some(Box::new(handler));
+        self
+    }
+
+    // Called when a block has been read, before its events are collected.
+    pub fn on_block_start<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(BlockHeader) + Send + Sync + 'static,
+    {
+        self.options.block_start_handler = Some(Box::new(handler));
+        self
+    }
+
+    // The counterpart of `on_block_start`; another name for
+    // `on_block_processed`.
+    pub fn on_block_end<F>(self, handler: F) -> Self
+    where
+        F: Fn(BlockProcessed) + Send + Sync + 'static,
+    {
+        self.on_block_processed(handler)
+    }
+
+    // Called with the last processed height when the listener reaches the
+    // chain head: the first time in a run, and again whenever it has fallen
+    // behind and caught up.
+    pub fn on_catch_up_complete<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        self.options.catch_up_handler = Some(Box::new(handler));
+        self
+    }
+
+    // Called when a run that reported `on_start` ends, with the error that
+    // stopped it, if any. A stream dropped before it ends does not call it.
+    pub fn on_stop<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Option<&ListenerError>) + Send + Sync + 'static,
+    {
+        self.options.stop_handler = Some(Box::new(handler));
+        self
+    }
+
+    pub fn on_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ListenerError) -> ErrorAction + Send + Sync + 'static,
+    {
+        self.options.error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    pub fn on_lag<F>(mut self, threshold: u64, handler: F) -> Self
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        self.options.lag_alert = Some(LagAlert {
+            threshold,
+            handler: Box::new(handler),
+            last_check: None,
+        });
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.options.retry_policy = policy;
+        self
+    }
+
+    // Gives up on a single RPC request after `timeout`. A timed out request is
+    // retried like a dropped connection, under the retry policy.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.options.request_timeout = Some(timeout);
+        self
+    }
+
+    // Bounds a whole RPC call, retries and backoff included. Once it passes,
+    // the call fails with `ListenerError::Timeout` without further retries.
+    pub fn call_deadline(mut self, deadline: Duration) -> Self {
+        self.options.call_deadline = Some(deadline);
+        self
+    }
+
+    // Probes the node's sync status and head age when the listener starts and
+    // then every `HealthCheck::interval`. An unhealthy node is reported to the
+    // error handler as `ListenerError::UnhealthyNode`, and the listener fails
+    // over to the next `fallback_rpc_url` if there is one.
+    pub fn health_check(mut self, health_check: HealthCheck) -> Self {
+        self.options.health_check = Some(health_check);
+        self
+    }
+
+    // Runs the listener's timers and background tasks on another executor
+    // than tokio, the default. Required when the `tokio-runtime` feature is
+    // disabled.
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.options.runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    pub fn max_rps(mut self, max_rps: u32) -> Self {
+        self.options.max_rps = Some(max_rps);
+        self
+    }
+
+    pub fn delivery(mut self, delivery: DeliveryMode) -> Self {
+        self.options.delivery = delivery;
+        self
+    }
+
+    pub fn dedupe_capacity(mut self, capacity: usize) -> Self {
+        self.options.dedupe_capacity = capacity;
+        self
+    }
+
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.options.poll_interval = interval;
+        self
+    }
+
+    pub fn catch_up_interval(mut self, interval: Duration) -> Self {
+        self.options.catch_up_interval = interval;
+        self
+    }
+
+    pub fn adaptive_polling(mut self, adaptive: bool) -> Self {
+        self.options.adaptive_polling = adaptive;
+        self
+    }
+
+    pub fn chunk_concurrency(mut self, concurrency: usize) -> Self {
+        self.options.chunk_concurrency = concurrency;
+        self
+    }
+
+    pub fn transaction_concurrency(mut self, concurrency: usize) -> Self {
+        self.options.transaction_concurrency = concurrency;
+        self
+    }
+
+    pub fn backfill_concurrency(mut self, concurrency: usize) -> Self {
+        self.options.backfill_concurrency = concurrency;
+        self
+    }
+
+    // Runs the `start` callbacks in their own task, fed through a queue of up
+    // to `capacity` events, so that a slow callback does not hold up polling.
+    pub fn delivery_queue(mut self, capacity: usize, overflow: OverflowPolicy) -> Self {
+        self.options.delivery_queue = Some((capacity, overflow));
+        self
+    }
+
+    // Only reads the shards that hold the watched accounts. Requires the Lake
+    // source and accounts given by ID rather than by pattern.
+    pub fn shard_filter(mut self, enabled: bool) -> Self {
+        self.options.shard_filter = enabled;
+        self
+    }
+
+    // Keeps up to `capacity` blocks and as many chunks, so that retries and
+    // overlapping runs do not fetch them again. Hits and misses are counted
+    // in `status()`.
+    pub fn cache_capacity(mut self, capacity: usize) -> Self {
+        self.options.cache_capacity = Some(capacity);
+        self
+    }
+}
+
+impl NearEventListenerBuilder {
+    // Watch lists that turn out empty at run time, e.g. from an empty slice or
+    // configuration, are still rejected with `MissingField`.
+    pub fn build(self) -> Result<NearEventListener, ListenerError> {
+        if self.options.account_ids.is_empty()
+            && self.options.account_patterns.is_empty()
+            && self.options.account_matcher.is_none()
+            && self.options.subscriptions.is_empty()
+            && self.options.factories.is_empty()
+            && self.options.storage_watches.is_empty()
+            && self.options.account_watches.is_empty()
+        {
+            return Err(ListenerError::MissingField("account_id".to_string()));
+        }
+        if self
+            .options
+            .subscriptions
+            .iter()
+            .any(|subscription| !subscription.has_accounts())
+        {
+            return Err(ListenerError::MissingField(
+                "subscription account_id".to_string(),
+            ));
+        }
+
+        validate_http_url("rpc_url", &self.options.rpc_url)?;
+        if let Some(archival_rpc_url) = &self.options.archival_rpc_url {
+            validate_http_url("archival_rpc_url", archival_rpc_url)?;
+        }
+        for fallback_rpc_url in &self.options.fallback_rpc_urls {
+            validate_http_url("fallback_rpc_url", fallback_rpc_url)?;
+        }
+        let Some(runtime) = self.options.runtime else {
+            return Err(ListenerError::InvalidConfig(
+                "runtime is required without the tokio-runtime feature".to_string(),
+            ));
+        };
+        if !self.options.fallback_rpc_urls.is_empty() && self.options.health_check.is_none() {
+            return Err(ListenerError::InvalidConfig(
+                "fallback_rpc_url requires a health_check".to_string(),
+            ));
+        }
+        let subscription_account_ids = self
+            .options
+            .subscriptions
+            .iter()
+            .flat_map(|subscription| &subscription.account_ids);
+        for account_id in self
+            .options
+            .account_ids
+            .iter()
+            .chain(subscription_account_ids)
+            .chain(&self.options.factories)
+            .chain(
+                self.options
+                    .storage_watches
+                    .iter()
+                    .map(|(account_id, _)| account_id),
+            )
+            .chain(&self.options.account_watches)
+        {
+            AccountId::from_str(account_id).map_err(|e| {
+                ListenerError::InvalidConfig(format!("invalid account_id `{}`: {}", account_id, e))
+            })?;
+        }
+
+        if let StartPosition::Transaction { tx_hash, signer_id } = &self.options.start_position {
+            parse_transaction_id(tx_hash, signer_id)?;
+        }
+        let mut data_filters = self.options.data_matchers;
+        for (path, predicate) in self.options.data_paths {
+            let path = DataPath::parse(&path)?;
+            data_filters.push(Box::new(move |data| path.matches(&predicate, data)));
+        }
+
+        #[cfg(feature = "lake")]
+        let reads_lake = matches!(self.options.source, DataSource::Lake { .. });
+        #[cfg(not(feature = "lake"))]
+        let reads_lake = false;
+        if !self.options.storage_watches.is_empty() && reads_lake {
+            // Storage changes are only served by the RPC node.
+            return Err(ListenerError::InvalidConfig(
+                "watch_storage requires the RPC source".to_string(),
+            ));
+        }
+        if !self.options.account_watches.is_empty() && reads_lake {
+            return Err(ListenerError::InvalidConfig(
+                "watch_account_changes requires the RPC source".to_string(),
+            ));
+        }
+        if self.options.shard_filter && !reads_lake {
+            // RPC chunks list transactions and receipts under the shard of their
+            // sender, so the watched account's shard would miss most calls.
+            return Err(ListenerError::InvalidConfig(
+                "shard_filter requires the Lake source".to_string(),
+            ));
+        }
+
+        let resume = self.options.start_position == StartPosition::Checkpoint;
+        let mut last_processed_block = self.options.last_processed_block;
+        let mut pending_start = None;
+        match &self.options.start_position {
+            StartPosition::Checkpoint => {
+                if let Some(store) = &self.options.checkpoint_store {
+                    if let Some(height) = store.load()? {
+                        last_processed_block = height;
+                    }
+                }
+            }
+            StartPosition::Height(height) => last_processed_block = height.saturating_sub(1),
+            position => pending_start = Some(position.clone()),
+        }
+
+        #[cfg(feature = "lake")]
+        let mut lake = None;
+        #[cfg(feature = "websocket")]
+        let mut subscription = None;
+        match &self.options.source {
+            DataSource::Rpc => {}
+            #[cfg(feature = "lake")]
+            DataSource::Lake {
+                s3_bucket,
+                region,
+                start_block,
+            } => {
+                if resume && last_processed_block == 0 {
+                    last_processed_block = start_block.saturating_sub(1);
+                }
+                lake = Some(LakeClient::from_env(s3_bucket, region)?);
+            }
+            #[cfg(feature = "websocket")]
+            DataSource::WebSocket {
+                url,
+                subscribe_message,
+            } => {
+                subscription = Some(BlockSubscription::new(url, subscribe_message.as_deref()));
+            }
+        }
+
+        if resume && last_processed_block == 0 {
+            pending_start = Some(StartPosition::Latest);
+        }
+        // The block interrupted by a restart is delivered again, minus the
+        // events the store saw delivered.
+        let mut restored_deliveries = self.options.restored_deliveries;
+        if resume && self.options.delivery == DeliveryMode::ExactlyOnce {
+            if let Some(store) = &self.options.checkpoint_store {
+                restored_deliveries.extend(store.load_delivered()?.iter().map(DeliveredEvent::key));
+            }
+        }
+        let sequence = match (
+            self.options.restored_sequence,
+            &self.options.checkpoint_store,
+        ) {
+            (Some(sequence), _) => sequence,
+            (None, Some(store)) => store.load_sequence()?.unwrap_or_default(),
+            (None, None) => 0,
+        };
+        if pending_start.is_none() {
+            last_processed_block = last_processed_block.saturating_sub(self.options.replay_last);
+        }
+
+        let connect = self.options.http.connector()?;
+        let client = connect(&self.options.rpc_url);
+        let archival_client = self.options.archival_rpc_url.as_ref().map(&connect);
+        let fallback_clients = self
+            .options
+            .fallback_rpc_urls
+            .iter()
+            .map(&connect)
+            .collect();
+
+        Ok(NearEventListener {
+            client,
+            archival_client,
+            fallback_clients,
+            account_ids: self.options.account_ids,
+            account_patterns: self.options.account_patterns,
+            method_names: self.options.method_names,
+            last_processed_block,
+            event_filter: self.options.event_filter,
+            finality: self.options.finality,
+            data_filters,
+            factories: self.options.factories,
+            storage_watches: storage_watches(&self.options.storage_watches),
+            account_watches: self
+                .options
+                .account_watches
+                .iter()
+                .filter_map(|account_id| AccountId::from_str(account_id).ok())
+                .collect(),
+            executor_filter: self.options.executor_filter,
+            delivery_on: self.options.delivery_on,
+            wait_until: self.options.wait_until,
+            subscriptions: self.options.subscriptions,
+            middleware: self.options.middleware,
+            event_handlers: EventHandlers::default(),
+            handle: ListenerHandle::new(),
+            head_height: 0,
+            pending_start,
+            expected_chain: self.options.expected_chain,
+            replay_last: self.options.replay_last,
+            until_height: self.options.until_height,
+            max_events: self.options.max_events,
+            events_emitted: 0,
+            sequence,
+            recent_blocks: VecDeque::new(),
+            skipped_heights: BTreeSet::new(),
+            uncommitted_block: None,
+            polled_block: None,
+            account_matcher: self.options.account_matcher,
+            method_matcher: self.options.method_matcher,
+            checkpoint_store: self.options.checkpoint_store,
+            dead_letter_sink: self.options.dead_letter_sink,
+            include_raw_logs: self.options.include_raw_logs,
+            include_receipt_views: self.options.include_receipt_views,
+            include_execution_metrics: self.options.include_execution_metrics,
+            strict_validation: self.options.strict_validation,
+            log_decoder: self.options.log_decoder,
+            #[cfg(feature = "schema")]
+            schema_registry: self.options.schema_registry,
+            detect_payments: self.options.detect_payments,
+            detect_failures: self.options.detect_failures,
+            raw_log_handler: self.options.raw_log_handler,
+            state_change_handler: self.options.state_change_handler,
+            account_change_handler: self.options.account_change_handler,
+            payment_handler: self.options.payment_handler,
+            failure_handler: self.options.failure_handler,
+            block_processed_handler: self.options.block_processed_handler,
+            start_handler: self.options.start_handler,
+            block_start_handler: self.options.block_start_handler,
+            catch_up_handler: self.options.catch_up_handler,
+            stop_handler: self.options.stop_handler,
+            run_started: false,
+            caught_up: false,
+            error_handler: self.options.error_handler,
+            lag_alert: self.options.lag_alert,
+            retry_policy: self.options.retry_policy,
+            request_timeout: self.options.request_timeout,
+            call_deadline: self.options.call_deadline,
+            health_check: self.options.health_check,
+            runtime,
+            last_health_check: None,
+            rate_limiter: self.options.max_rps.map(RateLimiter::new),
+            dedupe: (self.options.delivery == DeliveryMode::ExactlyOnce).then(|| {
+                let mut dedupe = DedupeCache::new(self.options.dedupe_capacity);
+                for key in restored_deliveries {
+                    dedupe.insert_key(key);
+                }
+                Mutex::new(dedupe)
+            }),
+            response_cache: self
+                .options
+                .cache_capacity
+                .map(|capacity| Mutex::new(ResponseCache::new(capacity))),
+            earliest_block: Mutex::new(None),
+            relayed_receipts: Mutex::new(VecDeque::new()),
+            poll_timer: PollTimer::new(
+                self.options.poll_interval,
+                self.options.catch_up_interval,
+                self.options.adaptive_polling,
+            ),
+            chunk_concurrency: self.options.chunk_concurrency,
+            transaction_concurrency: self.options.transaction_concurrency,
+            backfill_concurrency: self.options.backfill_concurrency,
+            delivery_queue: self.options.delivery_queue,
+            #[cfg(feature = "lake")]
+            shard_filter: self.options.shard_filter,
+            #[cfg(feature = "lake")]
+            watched_shards: Mutex::new(None),
+            #[cfg(feature = "lake")]
+            lake,
+            #[cfg(feature = "websocket")]
+            subscription,
+        })
+    }
+}
+
+impl NearEventListener {
+    pub fn builder(rpc_url: &str) -> NearEventListenerBuilder<Unwatched> {
+        NearEventListenerBuilder::new(rpc_url)
+    }
+
+    // Fills in the network's RPC endpoint, archival fallback and poll interval;
+    // each can still be overridden on the returned builder.
+    pub fn builder_for(network: Network) -> NearEventListenerBuilder<Unwatched> {
+        let mut builder =
+            NearEventListenerBuilder::new(network.rpc_url()).poll_interval(network.poll_interval());
+        if let Some(archival_rpc_url) = network.archival_rpc_url() {
+            builder = builder.archival_rpc_url(archival_rpc_url);
+        }
+        builder
+    }
+
+    // Rebuilds the listener captured by `export_state`, resuming after its last
+    // processed block. Callbacks, subscriptions and stores can be added to the
+    // returned builder; a checkpoint store does not move the start position.
+    pub fn from_state(snapshot: ListenerSnapshot) -> NearEventListenerBuilder {
+        let mut builder = NearEventListenerBuilder::new(&snapshot.rpc_url)
+            .event_filter(snapshot.event_filter)
+            .finality(snapshot.finality)
+            .delivery(snapshot.delivery)
+            .dedupe_capacity(snapshot.dedupe_capacity)
+            .into_watched();
+        if let Some(archival_rpc_url) = &snapshot.archival_rpc_url {
+            builder = builder.archival_rpc_url(archival_rpc_url);
+        }
+        for account_id in &snapshot.account_ids {
+            builder = builder.account_id(account_id);
+        }
+        for pattern in &snapshot.account_patterns {
+            builder = builder.account_pattern(pattern);
+        }
+        for factory_id in &snapshot.factories {
+            builder = builder.factory(factory_id);
+        }
+        for method_name in &snapshot.method_names {
+            builder = builder.method_name(method_name);
+        }
+        for (account_id, key_prefix) in &snapshot.storage_watches {
+            builder = builder.watch_storage(account_id, key_prefix);
+        }
+        for account_id in &snapshot.account_watches {
+            builder = builder.watch_account_changes(account_id);
+        }
+        if snapshot.last_processed_block > 0 {
+            builder = builder.start_from(StartPosition::Height(snapshot.last_processed_block + 1));
+        }
+        builder.options.restored_sequence = Some(snapshot.sequence);
+        builder.options.restored_deliveries = snapshot.delivered;
+        builder
+    }
+
+    pub async fn start<F>(&mut self, mut callback: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) + Send + 'static,
+    {
+        self.start_async(move |envelope| {
+            callback(envelope);
+            future::ready(())
+        })
+        .await
+    }
+
+    // Calls `handler` for every delivered event whose `event` field equals
+    // `event`, alongside whichever way the listener is consumed.
+    pub fn on<F>(mut self, event: &str, handler: F) -> Self
+    where
+        F: Fn(EventEnvelope) + Send + Sync + 'static,
+    {
+        self.event_handlers.insert(event, Box::new(handler));
+        self
+    }
+
+    // Calls `handler` for delivered events that no `on` handler is registered for.
+    pub fn on_any<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(EventEnvelope) + Send + Sync + 'static,
+    {
+        self.event_handlers.set_fallback(Box::new(handler));
+        self
+    }
+
+    pub fn handle(&self) -> ListenerHandle {
+        self.handle.clone()
+    }
+
+    pub fn status(&self) -> ListenerStatus {
+        self.publish_status();
+        self.handle.status()
+    }
+
+    // Captures the cursor, the watched accounts, methods and event filter, and
+    // the exactly-once cache. As with a checkpoint, a block whose events may
+    // not all have been delivered yet is left to be processed again.
+    pub fn export_state(&self) -> ListenerSnapshot {
+        let last_processed_block = match &self.uncommitted_block {
+            Some((height, _)) => height.saturating_sub(1),
+            None => self.last_processed_block,
+        };
+        let (delivery, dedupe_capacity, delivered) = match &self.dedupe {
+            Some(dedupe) => {
+                let dedupe = dedupe.lock().unwrap();
+                (DeliveryMode::ExactlyOnce, dedupe.capacity(), dedupe.keys())
+            }
+            None => (
+                DeliveryMode::AtLeastOnce,
+                DEFAULT_DEDUPE_CAPACITY,
+                Vec::new(),
+            ),
+        };
+
+        ListenerSnapshot {
+            rpc_url: self.client.server_addr().to_string(),
+            archival_rpc_url: self
+                .archival_client
+                .as_ref()
+                .map(|client| client.server_addr().to_string()),
+            account_ids: self.account_ids.clone(),
+            account_patterns: self.account_patterns.clone(),
+            factories: self.factories.clone(),
+            storage_watches: self
+                .storage_watches
+                .iter()
+                .map(|(account_id, key_prefix)| (account_id.to_string(), key_prefix.clone()))
+                .collect(),
+            account_watches: self
+                .account_watches
+                .iter()
+                .map(|account_id| account_id.to_string())
+                .collect(),
+            method_names: self.method_names.clone(),
+            event_filter: self.event_filter.clone(),
+            finality: self.finality.clone(),
+            delivery,
+            dedupe_capacity,
+            last_processed_block,
+            sequence: self.sequence,
+            delivered,
+        }
+    }
+
+    // Polls on behalf of the subscriptions only, for listeners without
+    // accounts of their own.
+    pub async fn run(&mut self) -> Result<(), ListenerError> {
+        self.start(|_envelope| {}).await
+    }
+
+    pub fn subscribe<T: NearEvent + 'static>(
+        &mut self,
+    ) -> impl Stream<Item = Result<(EventEnvelope, T), ListenerError>> + Unpin + '_ {
+        self.stream().filter_map(|item| {
+            future::ready(match item {
+                Ok(envelope) if T::matches(&envelope.event) => {
+                    Some(T::from_event_log(&envelope.event).map(|event| (envelope, event)))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+        })
+    }
+
+    pub async fn start_typed<T, F>(&mut self, mut callback: F) -> Result<(), ListenerError>
+    where
+        T: DeserializeOwned,
+        F: FnMut(EventEnvelope, T) + Send + 'static,
+    {
+        let error_handler = self.error_handler.clone();
+        self.start(move |envelope| match envelope.event.parse_data::<T>() {
+            Ok(data) => callback(envelope, data),
+            Err(err) => {
+                dispatch_error(error_handler.as_ref(), &err);
+            }
+        })
+        .await
+    }
+
+    // Decodes each event with the registry, skipping events it has no decoder
+    // for. Decoding errors are handled as in `start_typed`.
+    pub async fn start_with_registry<T, F>(
+        &mut self,
+        registry: EventRegistry<T>,
+        mut callback: F,
+    ) -> Result<(), ListenerError>
+    where
+        T: 'static,
+        F: FnMut(EventEnvelope, T) + Send + 'static,
+    {
+        let error_handler = self.error_handler.clone();
+        self.start(move |envelope| match registry.decode(&envelope.event) {
+            Some(Ok(event)) => callback(envelope, event),
+            Some(Err(err)) => {
+                dispatch_error(error_handler.as_ref(), &err);
+            }
+            None => {}
+        })
+        .await
+    }
+
+    pub async fn start_async<F, Fut>(&mut self, callback: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        info!(
+            "Starting event listener for accounts: {:?}, methods: {:?}",
+            self.account_ids, self.method_names
+        );
+
+        self.start_polling(callback).await
+    }
+
+    pub async fn start_batched<F>(&mut self, callback: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(BlockEvents) + Send + 'static,
+    {
+        info!("Starting batched polling");
+        self.poll_batches(callback).await
+    }
+
+    // Groups events into tumbling windows and delivers each window once a
+    // block of the next one is processed. Every window with processed blocks
+    // is delivered, even without events; the window still open when the
+    // listener stops is delivered as it is.
+    pub async fn start_windowed<F>(
+        &mut self,
+        size: WindowSize,
+        mut callback: F,
+    ) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventWindow) + Send,
+    {
+        size.validate()?;
+        info!("Starting windowed polling");
+
+        let mut windows = TumblingWindows::new(size);
+        self.poll_batches(|batch| {
+            if let Some(window) = windows.push(batch) {
+                callback(window);
+            }
+        })
+        .await?;
+        if let Some(window) = windows.finish() {
+            callback(window);
+        }
+        Ok(())
+    }
+
+    async fn poll_batches<F>(&mut self, callback: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(BlockEvents) + Send,
+    {
+        let result = self.poll_batched_blocks(callback).await;
+        self.end_run(result.as_ref().err());
+        result
+    }
+
+    async fn poll_batched_blocks<F>(&mut self, mut callback: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(BlockEvents) + Send,
+    {
+        let _running = RunGuard::new(self.handle.tracker().clone());
+
+        while !self.is_finished() {
+            self.polled_block = None;
+            match self.poll_next_block().await {
+                Ok(notifications) => {
+                    let mut events = Vec::new();
+                    let notifications = self.intercept(self.deduplicate(notifications)).await?;
+                    for notification in self.route(notifications) {
+                        match notification {
+                            EventNotification::Applied(envelope)
+                            | EventNotification::Reverted(envelope) => events.push(envelope),
+                            EventNotification::RawLog(raw_log) => self.deliver_raw_log(raw_log),
+                            EventNotification::StateChange(change) => {
+                                self.deliver_state_change(change)
+                            }
+                            EventNotification::AccountChange(change) => {
+                                self.deliver_account_change(change)
+                            }
+                            EventNotification::Payment(payment) => self.deliver_payment(payment),
+                            EventNotification::Failure(failure) => self.deliver_failure(failure),
+                        }
+                    }
+
+                    let mut processed = None;
+                    if let Some((header, reverted)) = self.polled_block.take() {
+                        if !reverted {
+                            self.events_emitted += events.len();
+                            for envelope in &mut events {
+                                self.assign_sequence(envelope);
+                            }
+                            processed = Some((header.clone(), events.len()));
+                        }
+                        callback(BlockEvents {
+                            header,
+                            events,
+                            reverted,
+                        });
+                    }
+                    self.commit_block()?;
+                    if let Some((header, events_count)) = processed {
+                        self.deliver_block_processed(header, events_count);
+                    }
+                }
+                Err(err)
+                    if self.error_handler.is_some()
+                        && self.report_error(&err) == ErrorAction::Continue => {}
+                Err(err) => return Err(err),
+            }
+
+            if !self.is_finished() {
+                self.wait_for_next_poll().await;
+            }
+        }
+
+        self.publish_status();
+        info!(
+            "Stop condition reached at block {}",
+            self.last_processed_block
+        );
+        Ok(())
+    }
+
+    // Forwards every event to `sink`. Delivery failures go to the error
+    // handler; as with `start_typed`, returning `Stop` from it has no effect.
+    pub async fn start_sink<S>(&mut self, sink: S) -> Result<(), ListenerError>
+    where
+        S: EventSink + 'static,
+    {
+        let error_handler = self.error_handler.clone();
+        let sink = Arc::new(sink);
+        self.start_async(move |envelope| {
+            let sink = sink.clone();
+            let error_handler = error_handler.clone();
+            async move {
+                if let Err(err) = sink.send(&envelope).await {
+                    dispatch_error(error_handler.as_ref(), &err);
+                }
+            }
+        })
+        .await
+    }
+
+    // Spawns the polling loop and hands events over through a bounded channel.
+    // The task ends once the receiver is dropped, and returns the error that
+    // stopped it otherwise.
+    pub fn channel(
+        self,
+        buffer: usize,
+    ) -> (
+        mpsc::Receiver<EventEnvelope>,
+        TaskHandle<Result<(), ListenerError>>,
+    ) {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        let runtime = self.runtime.clone();
+        let task = TaskHandle::spawn(
+            runtime.as_ref(),
+            self.forward(move |envelope| {
+                let sender = sender.clone();
+                async move { sender.send(envelope).await.is_ok() }
+            }),
+        );
+
+        (receiver, task)
+    }
+
+    // Like `channel`, for several consumers: call `resubscribe` on the returned
+    // receiver for each of them. Slow consumers miss events once `capacity` is
+    // exceeded, and the task ends when every receiver is dropped.
+    pub fn broadcast(
+        self,
+        capacity: usize,
+    ) -> (
+        broadcast::Receiver<EventEnvelope>,
+        TaskHandle<Result<(), ListenerError>>,
+    ) {
+        let (sender, receiver) = broadcast::channel(capacity.max(1));
+        let runtime = self.runtime.clone();
+        let task = TaskHandle::spawn(
+            runtime.as_ref(),
+            self.forward(move |envelope| future::ready(sender.send(envelope).is_ok())),
+        );
+
+        (receiver, task)
+    }
+
+    pub(crate) fn runtime(&self) -> Arc<dyn Runtime> {
+        self.runtime.clone()
+    }
+
+    // Polls until `deliver` reports that nobody is listening anymore.
+    pub(crate) async fn forward<F, Fut>(mut self, mut deliver: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) -> Fut + Send,
+        Fut: Future<Output = bool> + Send,
+    {
+        loop {
+            let mut events = self.stream();
+            let err = loop {
+                match events.next().await {
+                    Some(Ok(envelope)) => {
+                        if !deliver(envelope).await {
+                            return Ok(());
+                        }
+                    }
+                    Some(Err(err)) => break err,
+                    None => return Ok(()),
+                }
+            };
+            drop(events);
+
+            if self.error_handler.is_none() || self.report_error(&err) == ErrorAction::Stop {
+                return Err(err);
+            }
+            self.wait_for_next_poll().await;
+        }
+    }
+
+    pub fn stream(
+        &mut self,
+    ) -> impl Stream<Item = Result<EventEnvelope, ListenerError>> + Unpin + '_ {
+        self.notifications().filter_map(|item| {
+            future::ready(match item {
+                Ok(EventNotification::Applied(envelope)) => Some(Ok(envelope)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+        })
+    }
+
+    pub fn notifications(
+        &mut self,
+    ) -> impl Stream<Item = Result<EventNotification, ListenerError>> + Unpin + '_ {
+        self.events().filter_map(|item| {
+            future::ready(match item {
+                Ok(event) => event.into_notification().map(Ok),
+                Err(e) => Some(Err(e)),
+            })
+        })
+    }
+
+    // Everything the listener reports, in one stream: events, reverts, raw
+    // logs, state and account changes, payments, and a `BlockBoundary` after
+    // the notifications of each processed block.
+    pub fn events(
+        &mut self,
+    ) -> impl Stream<Item = Result<ListenerEvent, ListenerError>> + Unpin + '_ {
+        let running = RunGuard::new(self.handle.tracker().clone());
+        let state = Some((self, VecDeque::new(), false, 0, None, running));
+
+        Box::pin(stream::unfold(state, |state| async move {
+            let (listener, mut pending, mut polled, mut block_events, yielded, running) = state?;
+
+            // Asking for the next item means the last event was handled.
+            if let Some(delivered) = yielded {
+                if let Err(err) = listener.save_delivered(&delivered) {
+                    listener.end_run(Some(&err));
+                    return Some((Err(err), None));
+                }
+            }
+            loop {
+                if listener.reached_max_events() {
+                    listener.publish_status();
+                    listener.end_run(None);
+                    return None;
+                }
+                if let Some(mut event) = pending.pop_front() {
+                    let mut yielded = None;
+                    match &mut event {
+                        ListenerEvent::Nep297(envelope) => {
+                            listener.events_emitted += 1;
+                            listener.assign_sequence(envelope);
+                            block_events += 1;
+                            yielded = listener
+                                .dedupe
+                                .is_some()
+                                .then(|| DeliveredEvent::from(&envelope.context));
+                        }
+                        ListenerEvent::BlockBoundary(header) => {
+                            listener.deliver_block_processed(header.clone(), block_events);
+                            block_events = 0;
+                        }
+                        _ => {}
+                    }
+                    let state = (listener, pending, polled, block_events, yielded, running);
+                    return Some((Ok(event), Some(state)));
+                }
+                if let Err(err) = listener.commit_block() {
+                    listener.end_run(Some(&err));
+                    return Some((Err(err), None));
+                }
+                if listener.is_finished() {
+                    listener.publish_status();
+                    listener.end_run(None);
+                    return None;
+                }
+
+                if polled {
+                    listener.wait_for_next_poll().await;
+                }
+                polled = true;
+
+                listener.polled_block = None;
+                let notifications = match listener.poll_next_block().await {
+                    Ok(notifications) => notifications,
+                    Err(err) => {
+                        listener.end_run(Some(&err));
+                        return Some((Err(err), None));
+                    }
+                };
+                match listener
+                    .intercept(listener.deduplicate(notifications))
+                    .await
+                {
+                    Ok(notifications) => pending.extend(
+                        listener
+                            .route(notifications)
+                            .into_iter()
+                            .map(ListenerEvent::from),
+                    ),
+                    Err(err) => {
+                        listener.end_run(Some(&err));
+                        return Some((Err(err), None));
+                    }
+                }
+                if let Some((header, false)) = listener.polled_block.take() {
+                    pending.push_back(ListenerEvent::BlockBoundary(header));
+                }
+            }
+        }))
+    }
+
+    pub async fn backfill<F>(
+        &mut self,
+        from_height: u64,
+        to_height: u64,
+        mut callback: F,
+    ) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) + Send,
+    {
+        self.verify_chain().await?;
+        info!("Backfilling blocks {} to {}", from_height, to_height);
+
+        // The stream borrows the listener, so events are numbered from a copy
+        // of its sequence. The copy is written back even when a block fails,
+        // as the blocks before it were delivered and checkpointed.
+        let mut sequence = self.sequence;
+        let mut skipped_heights = Vec::new();
+        let mut last_backfilled_block = None;
+        let result = async {
+            let mut blocks = stream::iter(from_height..=to_height)
+                .map(|height| {
+                    self.fetch_historical_block_events(height)
+                        .map(move |block_events| (height, block_events))
+                })
+                .buffered(self.backfill_concurrency.max(1));
+
+            while let Some((requested_height, block_events)) = blocks.next().await {
+                let Some((height, notifications)) = block_events? else {
+                    skipped_heights.push(requested_height);
+                    continue;
+                };
+                let advances = height > self.last_processed_block;
+                let envelopes = applied_envelopes(&notifications);
+                if advances {
+                    if let Some(store) = &self.checkpoint_store {
+                        store.begin_block(height)?;
+                    }
+                }
+
+                let notifications = self.intercept(self.deduplicate(notifications)).await?;
+                for notification in self.route(notifications) {
+                    match notification {
+                        EventNotification::Applied(mut envelope) => {
+                            sequence += 1;
+                            envelope.context.sequence = Some(sequence);
+                            let delivered = (advances && self.dedupe.is_some())
+                                .then(|| DeliveredEvent::from(&envelope.context));
+                            callback(envelope);
+                            if let (Some(delivered), Some(store)) =
+                                (delivered, &self.checkpoint_store)
+                            {
+                                store.save_delivered(height, &delivered)?;
+                            }
+                        }
+                        EventNotification::RawLog(raw_log) => self.deliver_raw_log(raw_log),
+                        EventNotification::StateChange(change) => self.deliver_state_change(change),
+                        EventNotification::AccountChange(change) => {
+                            self.deliver_account_change(change)
+                        }
+                        EventNotification::Payment(payment) => self.deliver_payment(payment),
+                        EventNotification::Failure(failure) => self.deliver_failure(failure),
+                        EventNotification::Reverted(_) => {}
+                    }
+                }
+
+                if advances {
+                    if let Some(store) = &self.checkpoint_store {
+                        store.save_progress(height, sequence, &envelopes)?;
+                    }
+                    last_backfilled_block = Some(height);
+                }
+            }
+            Ok::<_, ListenerError>(())
+        }
+        .await;
+
+        self.sequence = sequence;
+        for height in skipped_heights {
+            self.remember_skipped_height(height);
+        }
+
+        if let Some(height) = last_backfilled_block {
+            self.last_processed_block = height;
+            debug!("Saved new block height: {}", self.last_processed_block);
+        }
+
+        result
+    }
+
+    // Heights the listener passed over because no block was found there,
+    // merged into ranges. Such heights were usually never produced, but a
+    // node that missed a block can also report it as unknown.
+    pub fn gaps(&self) -> Vec<RangeInclusive<u64>> {
+        let mut gaps: Vec<RangeInclusive<u64>> = Vec::new();
+        for &height in &self.skipped_heights {
+            match gaps.last_mut() {
+                Some(gap) if *gap.end() + 1 == height => *gap = *gap.start()..=height,
+                _ => gaps.push(height..=height),
+            }
+        }
+        gaps
+    }
+
+    // Looks the skipped heights up again, on the archival node too, and
+    // delivers the events of every block found there with `late` set. Heights
+    // that are still missing stay in `gaps`; the checkpoint is not touched.
+    pub async fn repair_gaps<F>(&mut self, mut callback: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) + Send,
+    {
+        self.verify_chain().await?;
+        let heights: Vec<u64> = self.skipped_heights.iter().copied().collect();
+        for height in heights {
+            let Some(block) = self.fetch_missed_block(height).await? else {
+                continue;
+            };
+            info!("Repairing skipped block {}", height);
+
+            let mut notifications = self.collect_block_events(&block).await?;
+            for notification in &mut notifications {
+                match notification {
+                    EventNotification::Applied(envelope)
+                    | EventNotification::Reverted(envelope) => envelope.context.late = true,
+                    EventNotification::RawLog(raw_log) => raw_log.context.late = true,
+                    EventNotification::Failure(failure) => failure.context.late = true,
+                    EventNotification::StateChange(_)
+                    | EventNotification::AccountChange(_)
+                    | EventNotification::Payment(_) => {}
+                }
+            }
+
+            let notifications = self.intercept(self.deduplicate(notifications)).await?;
+            for notification in self.route(notifications) {
+                match notification {
+                    EventNotification::Applied(mut envelope) => {
+                        self.assign_sequence(&mut envelope);
+                        callback(envelope);
+                    }
+                    EventNotification::RawLog(raw_log) => self.deliver_raw_log(raw_log),
+                    EventNotification::StateChange(change) => self.deliver_state_change(change),
+                    EventNotification::AccountChange(change) => self.deliver_account_change(change),
+                    EventNotification::Payment(payment) => self.deliver_payment(payment),
+                    EventNotification::Failure(failure) => self.deliver_failure(failure),
+                    EventNotification::Reverted(_) => {}
+                }
+            }
+            self.skipped_heights.remove(&height);
+        }
+
+        Ok(())
+    }
+
+    // Delivers the envelopes of an NDJSON archive, such as the files written
+    // by `FileSink`, as if they had just been found on chain: the listener's
+    // filters, validation, middleware, subscriptions and event handlers apply,
+    // and events are numbered anew. Nothing is fetched over RPC, and neither
+    // the checkpoint nor `last_processed_block` moves. Lines that are not
+    // envelopes go to the error handler; blank lines are skipped.
+    pub async fn replay_from_file<F>(
+        &mut self,
+        path: impl AsRef<Path>,
+        mut callback: F,
+    ) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) + Send,
+    {
+        let path = path.as_ref();
+        let mut lines = read_lines(path);
+
+        while let Some(line) = lines.recv().await {
+            if self.handle.is_stopped() {
+                break;
+            }
+            let (number, line) = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let envelope = match serde_json::from_str::<EventEnvelope>(&line) {
+                Ok(envelope) => envelope,
+                Err(err) => {
+                    let err = ListenerError::SourceError(format!(
+                        "{}:{}: {}",
+                        path.display(),
+                        number,
+                        err
+                    ));
+                    if self.report_error(&err) == ErrorAction::Stop {
+                        return Err(err);
+                    }
+                    continue;
+                }
+            };
+
+            let context = &envelope.context;
+            if !self.accepts_executor(&context.account_id)
+                || !self.delivery_on.accepts(context.execution_status)
+            {
+                continue;
+            }
+            if let Err(err) = self.validate_event(&envelope.event) {
+                let log = format!("EVENT_JSON:{}", serde_json::to_string(&envelope.event)?);
+                self.reject_event(envelope.context, log, err)?;
+                continue;
+            }
+            if !self.wants_event(&envelope.event) {
+                continue;
+            }
+
+            let notifications = self
+                .intercept(vec![EventNotification::Applied(envelope)])
+                .await?;
+            for notification in self.route(notifications) {
+                if let EventNotification::Applied(mut envelope) = notification {
+                    self.assign_sequence(&mut envelope);
+                    callback(envelope);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_missed_block(&self, height: u64) -> Result<Option<BlockView>, ListenerError> {
+        let block_reference = BlockReference::BlockId(BlockId::Height(height));
+        for client in std::iter::once(&self.client).chain(&self.archival_client) {
+            let result = self
+                .call_with_retry(client, || methods::block::RpcBlockRequest {
+                    block_reference: block_reference.clone(),
+                })
+                .await?;
+            match result {
+                Ok(block) => return Ok(Some(block)),
+                Err(err) => match err.handler_error() {
+                    Some(RpcBlockError::UnknownBlock { .. }) => continue,
+                    _ => return Err(err.into()),
+                },
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn remember_skipped_height(&mut self, height: u64) {
+        self.skipped_heights.insert(height);
+        if self.skipped_heights.len() > SKIPPED_HEIGHTS_CAPACITY {
+            self.skipped_heights.pop_first();
+        }
+    }
+
+    async fn fetch_historical_block_events(
+        &self,
+        height: u64,
+    ) -> Result<Option<(u64, Vec<EventNotification>)>, ListenerError> {
+        let block_reference = BlockReference::BlockId(BlockId::Height(height));
+
+        match self.fetch_block(block_reference).await? {
+            Ok(block) => {
+                let notifications = self.collect_block_events(&block).await?;
+                Ok(Some((block.header.height, notifications)))
+            }
+            Err(err) => match err.handler_error() {
+                Some(RpcBlockError::UnknownBlock { .. }) => Ok(None),
+                _ => Err(err.into()),
+            },
+        }
+    }
+
+    async fn start_polling<F, Fut>(&mut self, mut callback: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let Some((capacity, overflow)) = self.delivery_queue else {
+            return self
+                .poll_and_deliver(move |envelope| callback(envelope).map(Ok))
+                .await;
+        };
+
+        // Blocks are checkpointed once their events are queued, so events
+        // still in the queue when the process dies are not delivered again.
+        let (sender, receiver) = delivery_queue(capacity, overflow);
+        // Awaiting the handle resumes a panic of the delivery task.
+        let (delivery, mut delivered) = async move {
+            while let Some(envelope) = receiver.pop().await {
+                callback(envelope).await;
+            }
+        }
+        .remote_handle();
+        self.runtime.spawn(delivery.boxed());
+
+        let result = {
+            let sender = &sender;
+            let polling = pin!(self.poll_and_deliver(move |envelope| sender.push(envelope)));
+            match future::select(polling, &mut delivered).await {
+                Either::Left((result, _)) => result,
+                // The queue is still open, so the task cannot have finished.
+                Either::Right(((), _)) => return Ok(()),
+            }
+        };
+        drop(sender);
+        delivered.await;
+        result
+    }
+
+    async fn poll_and_deliver<F, Fut>(&mut self, deliver: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) -> Fut + Send,
+        Fut: Future<Output = Result<(), ListenerError>> + Send,
+    {
+        let result = self.poll_and_deliver_blocks(deliver).await;
+        self.end_run(result.as_ref().err());
+        result
+    }
+
+    async fn poll_and_deliver_blocks<F, Fut>(&mut self, mut deliver: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(EventEnvelope) -> Fut + Send,
+        Fut: Future<Output = Result<(), ListenerError>> + Send,
+    {
+        info!("Starting polling");
+        let _running = RunGuard::new(self.handle.tracker().clone());
+
+        while !self.is_finished() {
+            self.polled_block = None;
+            match self.poll_next_block().await {
+                Ok(notifications) => {
+                    let notifications = self.intercept(self.deduplicate(notifications)).await?;
+                    let mut delivered_all = true;
+                    let mut events_count = 0;
+                    for notification in self.route(notifications) {
+                        if self.reached_max_events() {
+                            delivered_all = false;
+                            break;
+                        }
+                        match notification {
+                            EventNotification::Applied(mut envelope) => {
+                                self.events_emitted += 1;
+                                events_count += 1;
+                                self.assign_sequence(&mut envelope);
+                                let delivered = self
+                                    .dedupe
+                                    .is_some()
+                                    .then(|| DeliveredEvent::from(&envelope.context));
+                                match deliver(envelope).await {
+                                    Ok(()) => {
+                                        if let Some(delivered) = delivered {
+                                            self.save_delivered(&delivered)?;
+                                        }
+                                    }
+                                    Err(err)
+                                        if self.error_handler.is_some()
+                                            && self.report_error(&err) == ErrorAction::Continue => {
+                                    }
+                                    Err(err) => return Err(err),
+                                }
+                            }
+                            EventNotification::RawLog(raw_log) => self.deliver_raw_log(raw_log),
+                            EventNotification::StateChange(change) => {
+                                self.deliver_state_change(change)
+                            }
+                            EventNotification::AccountChange(change) => {
+                                self.deliver_account_change(change)
+                            }
+                            EventNotification::Payment(payment) => self.deliver_payment(payment),
+                            EventNotification::Failure(failure) => self.deliver_failure(failure),
+                            EventNotification::Reverted(_) => {}
+                        }
+                    }
+                    // A block cut short by `max_events` stays in progress, so
+                    // its remaining events are delivered after a restart.
+                    if delivered_all {
+                        self.commit_block()?;
+                        if let Some((header, false)) = self.polled_block.take() {
+                            self.deliver_block_processed(header, events_count);
+                        }
+                    }
+                }
+                Err(err)
+                    if self.error_handler.is_some()
+                        && self.report_error(&err) == ErrorAction::Continue => {}
+                Err(err) => return Err(err),
+            }
+
+            if !self.is_finished() {
+                self.wait_for_next_poll().await;
+            }
+        }
+
+        self.publish_status();
+        info!(
+            "Stop condition reached at block {}",
+            self.last_processed_block
+        );
+        Ok(())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.handle.is_stopped()
+            || self.reached_max_events()
+            || self
+                .until_height
+                .is_some_and(|height| self.last_processed_block >= height)
+    }
+
+    fn reached_max_events(&self) -> bool {
+        self.max_events
+            .is_some_and(|max_events| self.events_emitted >= max_events)
+    }
+
+    // In exactly-once mode, drops events that were already delivered and
+    // reverts of events that never were.
+    fn deduplicate(&self, notifications: Vec<EventNotification>) -> Vec<EventNotification> {
+        let Some(dedupe) = &self.dedupe else {
+            return notifications;
+        };
+        let mut dedupe = dedupe.lock().unwrap();
+
+        notifications
+            .into_iter()
+            .filter(|notification| match notification {
+                EventNotification::Applied(envelope) => dedupe.insert(&envelope.context),
+                EventNotification::Reverted(envelope) => dedupe.remove(&envelope.context),
+                EventNotification::RawLog(_)
+                | EventNotification::StateChange(_)
+                | EventNotification::AccountChange(_)
+                | EventNotification::Payment(_)
+                | EventNotification::Failure(_) => true,
+            })
+            .collect()
+    }
+
+    // In exactly-once mode, records a delivered event of the block in progress
+    // with the checkpoint, so that a restart does not deliver it again.
+    fn save_delivered(&self, event: &DeliveredEvent) -> Result<(), ListenerError> {
+        match (&self.checkpoint_store, &self.uncommitted_block) {
+            (Some(store), Some((height, _))) => store.save_delivered(*height, event),
+            _ => Ok(()),
+        }
+    }
+
+    // Numbers events in the order they reach the listener's own consumers.
+    fn assign_sequence(&mut self, envelope: &mut EventEnvelope) {
+        self.sequence += 1;
+        envelope.context.sequence = Some(self.sequence);
+    }
+
+    fn deliver_raw_log(&self, raw_log: RawLog) {
+        if let Some(handler) = &self.raw_log_handler {
+            handler(raw_log);
+        }
+    }
+
+    fn deliver_state_change(&self, change: StateChange) {
+        if let Some(handler) = &self.state_change_handler {
+            handler(change);
+        }
+    }
+
+    fn deliver_account_change(&self, change: AccountChange) {
+        if let Some(handler) = &self.account_change_handler {
+            handler(change);
+        }
+    }
+
+    fn deliver_payment(&self, payment: Payment) {
+        if let Some(handler) = &self.payment_handler {
+            handler(payment);
+        }
+    }
+
+    fn deliver_failure(&self, failure: FailedCall) {
+        if let Some(handler) = &self.failure_handler {
+            handler(failure);
+        }
+    }
+
+    fn deliver_block_processed(&self, header: BlockHeader, events_count: usize) {
+        if let Some(handler) = &self.block_processed_handler {
+            handler(BlockProcessed {
+                height: header.height,
+                hash: header.hash,
+                timestamp: header.timestamp,
+                events_count,
+            });
+        }
+    }
+
+    fn observe_block(&mut self, header: BlockHeader) {
+        self.poll_timer.observe_block(header.timestamp);
+        if self.poll_timer.is_catching_up() {
+            self.caught_up = false;
+        }
+        if let Some(handler) = &self.block_start_handler {
+            handler(header);
+        }
+    }
+
+    fn reach_head(&mut self) {
+        self.poll_timer.observe_head();
+        if !self.caught_up {
+            self.caught_up = true;
+            if let Some(handler) = &self.catch_up_handler {
+                handler(self.last_processed_block);
+            }
+        }
+    }
+
+    // Ends a run that was started by `poll_next_block`.
+    fn end_run(&mut self, err: Option<&ListenerError>) {
+        if !std::mem::take(&mut self.run_started) {
+            return;
+        }
+        if let Some(handler) = &self.stop_handler {
+            handler(err);
+        }
+    }
+
+    fn publish_status(&self) {
+        self.handle.tracker().update(
+            self.last_processed_block,
+            self.head_height.max(self.last_processed_block),
+            self.events_emitted,
+            self.poll_timer.is_catching_up(),
+        );
+    }
+
+    // Runs applied events through the middleware chain. Events a middleware
+    // drops, or fails on, are not delivered to any consumer.
+    async fn intercept(
+        &self,
+        notifications: Vec<EventNotification>,
+    ) -> Result<Vec<EventNotification>, ListenerError> {
+        if self.middleware.is_empty() {
+            return Ok(notifications);
+        }
+
+        let mut intercepted = Vec::with_capacity(notifications.len());
+        for notification in notifications {
+            let EventNotification::Applied(envelope) = notification else {
+                intercepted.push(notification);
+                continue;
+            };
+
+            let delivered = Mutex::new(Vec::new());
+            if let Err(err) = Next::new(&self.middleware, &delivered).run(envelope).await {
+                if self.report_error(&err) == ErrorAction::Stop {
+                    return Err(err);
+                }
+                continue;
+            }
+            intercepted.extend(
+                delivered
+                    .into_inner()
+                    .unwrap()
+                    .into_iter()
+                    .map(EventNotification::Applied),
+            );
+        }
+
+        Ok(intercepted)
+    }
+
+    // Hands applied events to every subscription that matches them and to the
+    // event handlers, and keeps only the notifications meant for the listener's
+    // own consumers.
+    fn route(&self, notifications: Vec<EventNotification>) -> Vec<EventNotification> {
+        if self.subscriptions.is_empty() && self.event_handlers.is_empty() {
+            return notifications;
+        }
+
+        notifications
+            .into_iter()
+            .filter(|notification| match notification {
+                EventNotification::Applied(envelope) => {
+                    for subscription in &self.subscriptions {
+                        subscription.deliver(envelope);
+                    }
+                    let is_own = self.is_own_event(envelope);
+                    if is_own {
+                        self.event_handlers.dispatch(envelope);
+                    }
+                    is_own
+                }
+                EventNotification::Reverted(envelope) => self.is_own_event(envelope),
+                EventNotification::RawLog(_)
+                | EventNotification::StateChange(_)
+                | EventNotification::AccountChange(_)
+                | EventNotification::Payment(_)
+                | EventNotification::Failure(_) => true,
+            })
+            .collect()
+    }
+
+    // Applies the changes made through the handle since the last poll.
+    fn apply_watch_changes(&mut self) {
+        let changes = self.handle.take_changes();
+        if changes.is_empty() {
+            return;
+        }
+
+        for change in changes {
+            match change {
+                WatchChange::AddAccount(account_id) => {
+                    if !self.account_ids.contains(&account_id) {
+                        self.account_ids.push(account_id);
+                    }
+                }
+                WatchChange::RemoveAccount(account_id) => {
+                    self.account_ids.retain(|watched| *watched != account_id)
+                }
+                WatchChange::AddMethod(method_name) => {
+                    if !self.method_names.contains(&method_name) {
+                        self.method_names.push(method_name);
+                    }
+                }
+                WatchChange::RemoveMethod(method_name) => {
+                    self.method_names.retain(|watched| *watched != method_name)
+                }
+                WatchChange::EventFilter(filter) => self.event_filter = filter,
+                WatchChange::AddSubscription(subscription) => self.subscriptions.push(subscription),
+                WatchChange::RemoveSubscription(id) => self
+                    .subscriptions
+                    .retain(|subscription| subscription.id != Some(id)),
+            }
+        }
+        // The watched accounts may now live on other shards.
+        #[cfg(feature = "lake")]
+        {
+            *self.watched_shards.lock().unwrap() = None;
+        }
+
+        info!(
+            "Now listening for accounts: {:?}, methods: {:?}",
+            self.account_ids, self.method_names
+        );
+    }
+
+    fn is_own_event(&self, envelope: &EventEnvelope) -> bool {
+        let context = &envelope.context;
+        self.matches_account(&context.receiver_id)
+            && context
+                .method_name
+                .as_deref()
+                .is_none_or(|method_name| self.matches_method(method_name))
+            && self.event_filter.matches(&envelope.event)
+            && self.matches_data(&envelope.event.data)
+    }
+
+    fn matches_data(&self, data: &Value) -> bool {
+        self.data_filters.iter().all(|filter| filter(data))
+    }
+
+    async fn wait_for_next_poll(&mut self) {
+        self.publish_status();
+        let delay = self.poll_timer.next_delay();
+        let stopped = pin!(self.handle.wait_for_stop());
+
+        #[cfg(feature = "websocket")]
+        if let Some(subscription) = self.subscription.as_mut() {
+            if !self.poll_timer.is_catching_up() {
+                future::select(
+                    pin!(subscription.wait(delay, self.runtime.as_ref())),
+                    stopped,
+                )
+                .await;
+                return;
+            }
+        }
+
+        future::select(self.runtime.sleep(delay), stopped).await;
+    }
+
+    async fn poll_next_block(&mut self) -> Result<Vec<EventNotification>, ListenerError> {
+        // Asking for the next block means the previous one was consumed.
+        self.commit_block()?;
+        if self.handle.is_paused() {
+            info!("Paused at block {}", self.last_processed_block);
+            self.handle.wait_while_paused().await;
+            if self.handle.is_stopped() {
+                return Ok(Vec::new());
+            }
+            info!("Resumed at block {}", self.last_processed_block);
+        }
+        self.apply_watch_changes();
+        self.verify_chain().await?;
+        self.check_health().await?;
+
+        if let Some(position) = &self.pending_start {
+            let start = self.resolve_start(position).await?;
+            self.last_processed_block = start.saturating_sub(self.replay_last);
+            self.pending_start = None;
+        }
+        if !self.run_started {
+            self.run_started = true;
+            self.caught_up = false;
+            if let Some(handler) = &self.start_handler {
+                handler(self.last_processed_block + 1);
+            }
+        }
+
+        debug!("Last processed block: {}", self.last_processed_block);
+
+        #[cfg(feature = "lake")]
+        if self.lake.is_some() {
+            return self.poll_next_lake_block().await;
+        }
+
+        if self.last_processed_block >= self.head_height {
+            match self
+                .fetch_block(BlockReference::Finality(self.finality.clone()))
+                .await?
+            {
+                Ok(head) => self.head_height = head.header.height,
+                Err(err) => {
+                    self.handle_block_error(err).await?;
+                    return Ok(Vec::new());
+                }
+            }
+
+            if self.last_processed_block >= self.head_height {
+                self.reach_head();
+                return Ok(Vec::new());
+            }
+        }
+
+        let block_reference = self.specify_block_reference();
+
+        match self.fetch_block(block_reference).await? {
+            Ok(block) => {
+                if let Some(reverted) = self.unwind_fork(&block)? {
+                    return Ok(reverted);
+                }
+
+                let header = BlockHeader {
+                    height: block.header.height,
+                    hash: block.header.hash.to_string(),
+                    timestamp: block.header.timestamp,
+                };
+                self.observe_block(header.clone());
+
+                let notifications = self.collect_block_events(&block).await?;
+                let envelopes = applied_envelopes(&notifications);
+
+                self.advance_to(block.header.height, &envelopes)?;
+                self.remember_block(&block, &envelopes);
+                self.polled_block = Some((header, false));
+                self.check_lag().await?;
+
+                Ok(notifications)
+            }
+            Err(err) => {
+                self.handle_block_error(err).await?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    // Lake blocks are only published once final, so there is no head gating
+    // or fork handling here. Receipts are matched directly from the shards.
+    #[cfg(feature = "lake")]
+    async fn poll_next_lake_block(&mut self) -> Result<Vec<EventNotification>, ListenerError> {
+        let after = self.last_processed_block;
+        let Some(lake) = self.lake.as_mut() else {
+            return Ok(Vec::new());
+        };
+
+        let Some(height) = lake.next_height(after).await? else {
+            self.reach_head();
+            return Ok(Vec::new());
+        };
+        let block = lake.block(height).await?;
+        let watched_shards = self.watched_shards(block.header.epoch_id, height).await?;
+        let Some(lake) = self.lake.as_ref() else {
+            return Ok(Vec::new());
+        };
+        let shards = lake
+            .shards(&block, |shard_id| {
+                watched_shards
+                    .as_ref()
+                    .is_none_or(|watched| watched.contains(&shard_id))
+            })
+            .await?;
+
+        let header = BlockHeader {
+            height: block.header.height,
+            hash: block.header.hash.to_string(),
+            timestamp: block.header.timestamp,
+        };
+        self.observe_block(header.clone());
+
+        let mut notifications = Vec::new();
+        for outcome in shards
+            .iter()
+            .flat_map(|shard| &shard.receipt_execution_outcomes)
+        {
+            let receipt = &outcome.receipt;
+            if receipt.creates_account() {
+                self.discover_factory_account(receipt.receiver_id.as_str());
+            }
+            let Some(signer_id) = receipt.signer_id() else {
+                continue;
+            };
+            let Some((method_name, gas, deposit)) =
+                receipt.function_calls().find(|(method_name, ..)| {
+                    self.watches_call(receipt.receiver_id.as_str(), method_name)
+                })
+            else {
+                continue;
+            };
+
+            let context = EventContext {
+                account_id: outcome.execution_outcome.outcome.executor_id.to_string(),
+                block_height: block.header.height,
+                block_hash: block.header.hash.to_string(),
+                block_timestamp: block.header.timestamp,
+                tx_hash: None,
+                signer_id: signer_id.to_string(),
+                receiver_id: receipt.receiver_id.to_string(),
+                method_name: Some(method_name.to_string()),
+                deposit,
+                gas,
+                execution_status: outcome.execution_outcome.outcome.status(),
+                receipt_id: Some(outcome.execution_outcome.id.to_string()),
+                log_index: 0,
+                sequence: None,
+                late: false,
+                receipt: None,
+                metrics: self
+                    .include_execution_metrics
+                    .then(|| outcome.execution_outcome.outcome.metrics()),
+            };
+            let failure = self.failed_call(&context, outcome.execution_outcome.outcome.failure());
+            self.collect_outcome_events(
+                &outcome.execution_outcome.outcome.logs,
+                context,
+                &mut notifications,
+            )?;
+            notifications.extend(failure.map(EventNotification::Failure));
+        }
+
+        self.advance_to(block.header.height, &applied_envelopes(&notifications))?;
+        self.polled_block = Some((header, false));
+        self.check_lag().await?;
+
+        Ok(notifications)
+    }
+
+    // The shards holding the watched accounts, or `None` when every shard has
+    // to be read. The shard layout can only change between epochs, so it is
+    // looked up once per epoch.
+    #[cfg(feature = "lake")]
+    async fn watched_shards(
+        &self,
+        epoch_id: CryptoHash,
+        height: u64,
+    ) -> Result<Option<HashSet<ShardId>>, ListenerError> {
+        // Accounts created by a factory can land on any shard.
+        let has_patterns = !self.account_patterns.is_empty()
+            || self.account_matcher.is_some()
+            || !self.factories.is_empty()
+            || self
+                .subscriptions
+                .iter()
+                .any(Subscription::has_account_patterns);
+        if !self.shard_filter || has_patterns {
+            return Ok(None);
+        }
+        if let Some((cached_epoch, shards)) = &*self.watched_shards.lock().unwrap() {
+            if *cached_epoch == epoch_id {
+                return Ok(Some(shards.clone()));
+            }
+        }
+
+        let config = self
+            .call_rpc(Some(height), || {
+                methods::EXPERIMENTAL_protocol_config::RpcProtocolConfigRequest {
+                    block_reference: BlockReference::BlockId(BlockId::Height(height)),
+                }
+            })
+            .await??;
+        let subscription_account_ids = self
+            .subscriptions
+            .iter()
+            .flat_map(|subscription| &subscription.account_ids);
+        let mut shards = HashSet::new();
+        for account_id in self.account_ids.iter().chain(subscription_account_ids) {
+            // Account IDs were validated by `build`.
+            if let Ok(account_id) = AccountId::from_str(account_id) {
+                shards.insert(account_id_to_shard_id(&account_id, &config.shard_layout));
+            }
+        }
+
+        *self.watched_shards.lock().unwrap() = Some((epoch_id, shards.clone()));
+        Ok(Some(shards))
+    }
+
+    // Blocks are unwound one at a time: after rewinding, the next poll fetches
+    // the canonical block at the orphan's height and compares again.
+    fn unwind_fork(
+        &mut self,
+        block: &BlockView,
+    ) -> Result<Option<Vec<EventNotification>>, ListenerError> {
+        match self.recent_blocks.back() {
+            Some(last) if last.hash != block.header.prev_hash => {}
+            _ => return Ok(None),
+        }
+        let Some(orphan) = self.recent_blocks.pop_back() else {
+            return Ok(None);
+        };
+
+        let fork_height = orphan.height - 1;
+        warn!(
+            "Block {} was orphaned, rewinding to {}",
+            orphan.height, fork_height
+        );
+        self.last_processed_block = fork_height;
+        if let Some(store) = &self.checkpoint_store {
+            store.revert_to(fork_height)?;
+        }
+        self.polled_block = Some((
+            BlockHeader {
+                height: orphan.height,
+                hash: orphan.hash.to_string(),
+                timestamp: orphan.timestamp,
+            },
+            true,
+        ));
+
+        Ok(Some(
+            orphan
+                .envelopes
+                .into_iter()
+                .rev()
+                .map(EventNotification::Reverted)
+                .collect(),
+        ))
+    }
+
+    fn remember_block(&mut self, block: &BlockView, envelopes: &[EventEnvelope]) {
+        if matches!(self.finality, Finality::Final) {
+            return;
+        }
+
+        self.recent_blocks.push_back(ProcessedBlock {
+            height: block.header.height,
+            hash: block.header.hash,
+            timestamp: block.header.timestamp,
+            envelopes: envelopes.to_vec(),
+        });
+        if self.recent_blocks.len() > REORG_WINDOW {
+            self.recent_blocks.pop_front();
+        }
+    }
+
+    pub async fn lag(&self) -> Result<u64, ListenerError> {
+        let head = self
+            .fetch_block(BlockReference::Finality(Finality::Final))
+            .await??;
+
+        Ok(head.header.height.saturating_sub(self.last_processed_block))
+    }
+
+    async fn check_lag(&mut self) -> Result<(), ListenerError> {
+        let Some(alert) = &mut self.lag_alert else {
+            return Ok(());
+        };
+        if alert
+            .last_check
+            .is_some_and(|checked_at| checked_at.elapsed() < LAG_CHECK_INTERVAL)
+        {
+            return Ok(());
+        }
+        alert.last_check = Some(Instant::now());
+
+        match self.lag().await {
+            Ok(lag) => {
+                if let Some(alert) = &self.lag_alert {
+                    if lag > alert.threshold {
+                        (alert.handler)(lag);
+                    }
+                }
+                Ok(())
+            }
+            Err(err) if self.report_error(&err) == ErrorAction::Stop => Err(err),
+            Err(_) => Ok(()),
+        }
+    }
+
+    async fn collect_block_events(
+        &self,
+        block: &BlockView,
+    ) -> Result<Vec<EventNotification>, ListenerError> {
+        let mut notifications = Vec::new();
+
+        let chunks = self.fetch_chunks(block).await?;
+        self.discover_factory_accounts(&chunks);
+        let transactions = self.match_transactions(&chunks);
+        let receipts = self.match_receipts(&chunks);
+        let payments = self.match_payments(&chunks);
+        let has_matches = !transactions.is_empty() || !receipts.is_empty() || !payments.is_empty();
+        if has_matches && !is_behind_head(block.header.timestamp) {
+            self.runtime.sleep(OUTCOME_DELAY).await;
+        }
+
+        // Buffered rather than unordered, so events keep their order in the block.
+        let status_fetches: Vec<_> = transactions
+            .iter()
+            .map(|transaction| self.get_logs(transaction, block.header.height))
+            .collect();
+        let transaction_outcomes: Vec<_> = stream::iter(status_fetches)
+            .buffered(self.transaction_concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        for (transaction, outcomes) in transactions.iter().zip(transaction_outcomes) {
+            // Only the receipt executing the watched call counts as its failure.
+            let mut call_failure = None;
+            for outcome in outcomes {
+                let context = EventContext {
+                    account_id: outcome.executor_id.to_string(),
+                    block_height: block.header.height,
+                    block_hash: block.header.hash.to_string(),
+                    block_timestamp: block.header.timestamp,
+                    tx_hash: Some(transaction.tx_hash.clone()),
+                    signer_id: transaction.signer_id.to_string(),
+                    receiver_id: transaction.receiver_id.to_string(),
+                    method_name: Some(transaction.method_name.clone()),
+                    deposit: Some(transaction.deposit),
+                    gas: Some(transaction.gas),
+                    execution_status: outcome.status,
+                    receipt_id: outcome.receipt_id.map(|id| id.to_string()),
+                    log_index: 0,
+                    sequence: None,
+                    late: false,
+                    receipt: outcome.receipt,
+                    metrics: outcome.metrics,
+                };
+                if call_failure.is_none() && outcome.executor_id == transaction.receiver_id {
+                    call_failure = self.failed_call(&context, outcome.failure);
+                }
+                self.collect_outcome_events(&outcome.logs, context, &mut notifications)?;
+            }
+            notifications.extend(call_failure.map(EventNotification::Failure));
+        }
+
+        if !receipts.is_empty() {
+            let light_client_head = self
+                .fetch_block(BlockReference::Finality(Finality::Final))
+                .await??
+                .header
+                .hash;
+
+            for receipt in receipts {
+                let outcome = self
+                    .get_receipt_logs(&receipt, light_client_head, block.header.height)
+                    .await?;
+                let context = EventContext {
+                    account_id: outcome.executor_id.to_string(),
+                    block_height: block.header.height,
+                    block_hash: block.header.hash.to_string(),
+                    block_timestamp: block.header.timestamp,
+                    tx_hash: None,
+                    signer_id: receipt.signer_id.to_string(),
+                    receiver_id: receipt.receiver_id.to_string(),
+                    method_name: Some(receipt.method_name.clone()),
+                    deposit: Some(receipt.deposit),
+                    gas: Some(receipt.gas),
+                    execution_status: outcome.status,
+                    receipt_id: Some(receipt.receipt_id),
+                    log_index: 0,
+                    sequence: None,
+                    late: false,
+                    receipt: outcome.receipt,
+                    metrics: outcome.metrics,
+                };
+                let failure = self.failed_call(&context, outcome.failure);
+                self.collect_outcome_events(&outcome.logs, context, &mut notifications)?;
+                notifications.extend(failure.map(EventNotification::Failure));
+            }
+        }
+
+        self.collect_payments(block, payments, &mut notifications)
+            .await?;
+        self.collect_state_changes(block, &mut notifications)
+            .await?;
+        self.collect_account_changes(block, &mut notifications)
+            .await?;
+        Ok(notifications)
+    }
+
+    // Delivers the payments whose receiving receipt succeeded; failed ones are
+    // refunded to the sender.
+    async fn collect_payments(
+        &self,
+        block: &BlockView,
+        payments: Vec<MatchedPayment>,
+        notifications: &mut Vec<EventNotification>,
+    ) -> Result<(), ListenerError> {
+        let mut light_client_head = None;
+        for payment in payments {
+            let (status, receipt_id) = match (payment.tx_hash, payment.receipt_id) {
+                (Some(tx_hash), _) => {
+                    self.transaction_receipt_status(
+                        tx_hash,
+                        &payment.signer_id,
+                        block.header.height,
+                    )
+                    .await?
+                }
+                (None, Some(receipt_id)) => {
+                    let head = match light_client_head {
+                        Some(head) => head,
+                        None => {
+                            let head = self
+                                .fetch_block(BlockReference::Finality(Finality::Final))
+                                .await??
+                                .header
+                                .hash;
+                            *light_client_head.insert(head)
+                        }
+                    };
+                    let receipt = MatchedReceipt {
+                        receipt_id: receipt_id.to_string(),
+                        predecessor_id: payment.sender_id.clone(),
+                        signer_id: payment.signer_id.clone(),
+                        receiver_id: payment.receiver_id.clone(),
+                        method_name: payment.method_name.clone().unwrap_or_default(),
+                        gas: 0,
+                        deposit: payment.amount,
+                    };
+                    let outcome = self
+                        .get_receipt_logs(&receipt, head, block.header.height)
+                        .await?;
+                    (outcome.status, Some(receipt_id))
+                }
+                (None, None) => continue,
+            };
+            if status != Some(ExecutionStatus::Success) {
+                continue;
+            }
+
+            notifications.push(EventNotification::Payment(Payment {
+                receiver_id: payment.receiver_id.to_string(),
+                sender_id: payment.sender_id.to_string(),
+                signer_id: payment.signer_id.to_string(),
+                amount: payment.amount,
+                method_name: payment.method_name,
+                block_height: block.header.height,
+                block_hash: block.header.hash.to_string(),
+                block_timestamp: block.header.timestamp,
+                tx_hash: payment.tx_hash.map(|tx_hash| tx_hash.to_string()),
+                receipt_id: receipt_id.map(|receipt_id| receipt_id.to_string()),
+            }));
+        }
+
+        Ok(())
+    }
+
+    // The outcome of the receipt a transaction was converted into, which is
+    // the one executed by its receiver.
+    async fn transaction_receipt_status(
+        &self,
+        tx_hash: CryptoHash,
+        signer_id: &AccountId,
+        height: u64,
+    ) -> Result<(Option<ExecutionStatus>, Option<CryptoHash>), ListenerError> {
+        let response = self
+            .call_rpc(Some(height), || methods::tx::RpcTransactionStatusRequest {
+                transaction_info: methods::tx::TransactionInfo::TransactionId {
+                    tx_hash,
+                    sender_account_id: signer_id.clone(),
+                },
+                wait_until: self.wait_until.clone(),
+            })
+            .await??;
+        let outcome = match &response.final_execution_outcome {
+            Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome)) => outcome,
+            Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(outcome)) => {
+                &outcome.final_outcome
+            }
+            None => return Ok((None, None)),
+        };
+
+        let receipt_id = outcome.transaction_outcome.outcome.receipt_ids.first();
+        let status = outcome
+            .receipts_outcome
+            .iter()
+            .find(|receipt_outcome| Some(&receipt_outcome.id) == receipt_id)
+            .and_then(|receipt_outcome| {
+                ExecutionStatus::of_receipt(&receipt_outcome.outcome.status)
+            });
+        Ok((status, receipt_id.copied()))
+    }
+
+    // Asks the node for the changes the block made to watched storage, with
+    // one request per distinct prefix since a request takes a single prefix.
+    async fn collect_state_changes(
+        &self,
+        block: &BlockView,
+        notifications: &mut Vec<EventNotification>,
+    ) -> Result<(), ListenerError> {
+        let mut prefixes: BTreeMap<&[u8], Vec<AccountId>> = BTreeMap::new();
+        for (account_id, key_prefix) in &self.storage_watches {
+            prefixes
+                .entry(key_prefix)
+                .or_default()
+                .push(account_id.clone());
+        }
+
+        for (key_prefix, account_ids) in prefixes {
+            let changes = self
+                .fetch_state_changes(block, || StateChangesRequestView::DataChanges {
+                    account_ids: account_ids.clone(),
+                    key_prefix: StoreKey::from(key_prefix.to_vec()),
+                })
+                .await?;
+
+            for change in changes {
+                let (account_id, key, value) = match change.value {
+                    StateChangeValueView::DataUpdate {
+                        account_id,
+                        key,
+                        value,
+                    } => (account_id, key, Some(value.into())),
+                    StateChangeValueView::DataDeletion { account_id, key } => {
+                        (account_id, key, None)
+                    }
+                    _ => continue,
+                };
+                let (tx_hash, receipt_id) = change_cause(&change.cause);
+                notifications.push(EventNotification::StateChange(StateChange {
+                    account_id: account_id.to_string(),
+                    block_height: block.header.height,
+                    block_hash: block.header.hash.to_string(),
+                    block_timestamp: block.header.timestamp,
+                    key: key.into(),
+                    value,
+                    tx_hash,
+                    receipt_id,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Reports balance changes, access keys added to or removed from, code
+    // deployed to and deletion of the accounts watched with
+    // `watch_account_changes`.
+    async fn collect_account_changes(
+        &self,
+        block: &BlockView,
+        notifications: &mut Vec<EventNotification>,
+    ) -> Result<(), ListenerError> {
+        if self.account_watches.is_empty() {
+            return Ok(());
+        }
+        let account_ids = &self.account_watches;
+        let mut changes = self
+            .fetch_state_changes(block, || StateChangesRequestView::AccountChanges {
+                account_ids: account_ids.clone(),
+            })
+            .await?;
+        changes.extend(
+            self.fetch_state_changes(block, || StateChangesRequestView::AllAccessKeyChanges {
+                account_ids: account_ids.clone(),
+            })
+            .await?,
+        );
+        changes.extend(
+            self.fetch_state_changes(block, || StateChangesRequestView::ContractCodeChanges {
+                account_ids: account_ids.clone(),
+            })
+            .await?,
+        );
+
+        // An account's balance can change several times in a block; each change
+        // is compared with the one before it.
+        let mut balances: HashMap<AccountId, u128> = HashMap::new();
+        for change in changes {
+            let (account_id, kind) = match change.value {
+                StateChangeValueView::AccountUpdate {
+                    account_id,
+                    account,
+                } => {
+                    let previous = match balances.get(&account_id) {
+                        Some(balance) => *balance,
+                        None => self.balance_before(block, &account_id).await?,
+                    };
+                    balances.insert(account_id.clone(), account.amount);
+                    if previous == account.amount {
+                        continue;
+                    }
+                    let kind = AccountChangeKind::Balance {
+                        previous,
+                        balance: account.amount,
+                    };
+                    (account_id, kind)
+                }
+                StateChangeValueView::AccountDeletion { account_id } => {
+                    (account_id, AccountChangeKind::AccountDeleted)
+                }
+                StateChangeValueView::AccessKeyUpdate {
+                    account_id,
+                    public_key,
+                    access_key,
+                } => {
+                    if !self
+                        .is_new_access_key(
+                            block,
+                            &change.cause,
+                            &access_key,
+                            QueryRequest::ViewAccessKey {
+                                account_id: account_id.clone(),
+                                public_key: public_key.clone(),
+                            },
+                        )
+                        .await?
+                    {
+                        continue;
+                    }
+                    let kind = AccountChangeKind::AccessKeyAdded {
+                        public_key: public_key.to_string(),
+                        full_access: access_key.permission == AccessKeyPermissionView::FullAccess,
+                    };
+                    (account_id, kind)
+                }
+                StateChangeValueView::AccessKeyDeletion {
+                    account_id,
+                    public_key,
+                } => {
+                    let kind = AccountChangeKind::AccessKeyRemoved {
+                        public_key: public_key.to_string(),
+                    };
+                    (account_id, kind)
+                }
+                StateChangeValueView::ContractCodeUpdate { account_id, code } => {
+                    let kind = AccountChangeKind::CodeDeployed {
+                        code_hash: CryptoHash::hash_bytes(&code).to_string(),
+                    };
+                    (account_id, kind)
+                }
+                _ => continue,
+            };
+
+            let (tx_hash, receipt_id) = change_cause(&change.cause);
+            notifications.push(EventNotification::AccountChange(AccountChange {
+                account_id: account_id.to_string(),
+                block_height: block.header.height,
+                block_hash: block.header.hash.to_string(),
+                block_timestamp: block.header.timestamp,
+                kind,
+                tx_hash,
+                receipt_id,
+            }));
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_state_changes<F>(
+        &self,
+        block: &BlockView,
+        make_request: F,
+    ) -> Result<Vec<StateChangeWithCauseView>, ListenerError>
+    where
+        F: Fn() -> StateChangesRequestView,
+    {
+        let response = self
+            .call_rpc(Some(block.header.height), || {
+                methods::EXPERIMENTAL_changes::RpcStateChangesInBlockByTypeRequest {
+                    block_reference: BlockReference::BlockId(BlockId::Hash(block.header.hash)),
+                    state_changes_request: make_request(),
+                }
+            })
+            .await??;
+        Ok(response.changes)
+    }
+
+    // The account's balance as of the previous block, zero if it did not exist.
+    async fn balance_before(
+        &self,
+        block: &BlockView,
+        account_id: &AccountId,
+    ) -> Result<u128, ListenerError> {
+        let result = self
+            .call_rpc(Some(block.header.height), || {
+                methods::query::RpcQueryRequest {
+                    block_reference: BlockReference::BlockId(BlockId::Hash(block.header.prev_hash)),
+                    request: QueryRequest::ViewAccount {
+                        account_id: account_id.clone(),
+                    },
+                }
+            })
+            .await?;
+        match result {
+            Ok(response) => match response.kind {
+                QueryResponseKind::ViewAccount(account) => Ok(account.amount),
+                _ => Err(ListenerError::RpcError(
+                    "unexpected response to view_account".to_string(),
+                )),
+            },
+            Err(err) => match err.handler_error() {
+                Some(RpcQueryError::UnknownAccount { .. }) => Ok(0),
+                _ => Err(err.into()),
+            },
+        }
+    }
+
+    // Signing a transaction updates the nonce of the signer's key, and a gas
+    // refund the allowance of a function call key; neither adds a key. Full
+    // access keys are only updated by receipts when added. Otherwise the key
+    // is looked up with `key_query` as of the previous block.
+    async fn is_new_access_key(
+        &self,
+        block: &BlockView,
+        cause: &StateChangeCauseView,
+        access_key: &AccessKeyView,
+        key_query: QueryRequest,
+    ) -> Result<bool, ListenerError> {
+        if matches!(cause, StateChangeCauseView::TransactionProcessing { .. }) {
+            return Ok(false);
+        }
+        if access_key.permission == AccessKeyPermissionView::FullAccess {
+            return Ok(true);
+        }
+
+        let result = self
+            .call_rpc(Some(block.header.height), || {
+                methods::query::RpcQueryRequest {
+                    block_reference: BlockReference::BlockId(BlockId::Hash(block.header.prev_hash)),
+                    request: key_query.clone(),
+                }
+            })
+            .await?;
+        match result {
+            Ok(_) => Ok(false),
+            Err(err) => match err.handler_error() {
+                Some(
+                    RpcQueryError::UnknownAccessKey { .. } | RpcQueryError::UnknownAccount { .. },
+                ) => Ok(true),
+                _ => Err(err.into()),
+            },
+        }
+    }
+
+    fn failed_call(&self, context: &EventContext, failure: Option<String>) -> Option<FailedCall> {
+        if !self.detect_failures {
+            return None;
+        }
+        Some(FailedCall {
+            context: context.clone(),
+            error: failure?,
+        })
+    }
+
+    fn collect_outcome_events(
+        &self,
+        logs: &[String],
+        context: EventContext,
+        notifications: &mut Vec<EventNotification>,
+    ) -> Result<(), ListenerError> {
+        if !self.accepts_executor(&context.account_id)
+            || !self.delivery_on.accepts(context.execution_status)
+        {
+            return Ok(());
+        }
+
+        for (log_index, log) in logs.iter().enumerate() {
+            let context = EventContext {
+                log_index,
+                ..context.clone()
+            };
+            let parsed = self.decode_log(log, &context).and_then(|event_log| {
+                self.validate_event(&event_log)?;
+                Ok(event_log)
+            });
+            let event_log = match parsed {
+                Ok(event_log) => event_log,
+                Err(ListenerError::InvalidEventFormat(_)) => {
+                    if self.include_raw_logs {
+                        notifications.push(EventNotification::RawLog(RawLog {
+                            context,
+                            log: log.clone(),
+                        }));
+                    }
+                    continue;
+                }
+                Err(err) => {
+                    self.reject_event(context, log.clone(), err)?;
+                    continue;
+                }
+            };
+
+            if self.wants_event(&event_log) {
+                notifications.push(EventNotification::Applied(EventEnvelope {
+                    context,
+                    event: event_log,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_event(&self, event_log: &EventLog) -> Result<(), ListenerError> {
+        if self.strict_validation {
+            event_log.validate()?;
+        }
+        #[cfg(feature = "schema")]
+        if let Some(registry) = &self.schema_registry {
+            registry.validate(event_log)?;
+        }
+        Ok(())
+    }
+
+    // Malformed events go to the dead letter sink when there is one; only a
+    // failure to hand them over is reported as an error.
+    fn reject_event(
+        &self,
+        context: EventContext,
+        log: String,
+        err: ListenerError,
+    ) -> Result<(), ListenerError> {
+        let err = match &self.dead_letter_sink {
+            Some(sink) => match sink.send(DeadLetter {
+                context,
+                log,
+                error: err.to_string(),
+            }) {
+                Ok(()) => return Ok(()),
+                Err(sink_err) => sink_err,
+            },
+            None => err,
+        };
+        if self.report_error(&err) == ErrorAction::Stop {
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn wants_event(&self, event_log: &EventLog) -> bool {
+        (self.event_filter.matches(event_log) && self.matches_data(&event_log.data))
+            || self
+                .subscriptions
+                .iter()
+                .any(|subscription| subscription.matches_event(event_log))
+    }
+
+    // Decoders of the subscriptions matching the call come first, then the
+    // listener's own; the first one recognizing the log decodes it.
+    fn decode_log(&self, log: &str, context: &EventContext) -> Result<EventLog, ListenerError> {
+        let method_name = context.method_name.as_deref().unwrap_or_default();
+        let decoders = self
+            .subscriptions
+            .iter()
+            .filter(|subscription| subscription.matches_call(&context.receiver_id, method_name))
+            .filter_map(Subscription::decoder)
+            .chain([self.log_decoder.as_ref()]);
+
+        for decoder in decoders {
+            match decoder.decode(log) {
+                Err(ListenerError::InvalidEventFormat(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(ListenerError::InvalidEventFormat(
+            "Log not recognized by any decoder".to_string(),
+        ))
+    }
+
+    fn report_error(&self, err: &ListenerError) -> ErrorAction {
+        dispatch_error(self.error_handler.as_ref(), err)
+    }
+
+    // The block is only checkpointed as done by `commit_block`, once its
+    // events have been delivered; until then the store records it as in progress.
+    fn advance_to(
+        &mut self,
+        height: u64,
+        envelopes: &[EventEnvelope],
+    ) -> Result<(), ListenerError> {
+        self.commit_block()?;
+        self.last_processed_block = height;
+        if let Some(store) = &self.checkpoint_store {
+            store.begin_block(height)?;
+        }
+        self.uncommitted_block = Some((height, envelopes.to_vec()));
+        Ok(())
+    }
+
+    fn commit_block(&mut self) -> Result<(), ListenerError> {
+        let Some((height, envelopes)) = &self.uncommitted_block else {
+            return Ok(());
+        };
+        if let Some(store) = &self.checkpoint_store {
+            store.save_progress(*height, self.sequence, envelopes)?;
+        }
+        debug!("Saved new block height: {}", height);
+        self.uncommitted_block = None;
+        Ok(())
+    }
+
+    fn specify_block_reference(&self) -> BlockReference {
+        BlockReference::BlockId(BlockId::Height(self.last_processed_block + 1))
+    }
+
+    async fn check_health(&mut self) -> Result<(), ListenerError> {
+        let due = match (&self.health_check, self.last_health_check) {
+            (Some(health_check), Some(checked_at)) => {
+                checked_at.elapsed() >= health_check.interval()
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if due {
+            self.probe_health().await?;
+        }
+        Ok(())
+    }
+
+    // Returns whether the current node is healthy. An unhealthy one is
+    // reported and, if there are fallbacks, replaced by the next of them.
+    async fn probe_health(&mut self) -> Result<bool, ListenerError> {
+        let Some(health_check) = &self.health_check else {
+            return Ok(true);
+        };
+        let problem = match self
+            .call_with_retry(&self.client, || methods::status::RpcStatusRequest)
+            .await
+            .and_then(|result| result.map_err(ListenerError::from))
+        {
+            Ok(status) => health_check.problem(&status.sync_info),
+            Err(err) => Some(format!("failed its status request: {}", err)),
+        };
+        self.last_health_check = Some(Instant::now());
+        let Some(problem) = problem else {
+            return Ok(true);
+        };
+
+        let rpc_url = self.client.server_addr().to_string();
+        let err = ListenerError::UnhealthyNode(format!("{} {}", rpc_url, problem));
+        if self.report_error(&err) == ErrorAction::Stop {
+            return Err(err);
+        }
+        if let Some(fallback) = self.fallback_clients.pop_front() {
+            warn!(
+                "Failing over from {} to {}",
+                rpc_url,
+                fallback.server_addr()
+            );
+            let unhealthy = std::mem::replace(&mut self.client, fallback);
+            self.fallback_clients.push_back(unhealthy);
+            // The new node is probed again on the next poll.
+            self.last_health_check = None;
+        }
+        Ok(false)
+    }
+
+    async fn verify_chain(&mut self) -> Result<(), ListenerError> {
+        let Some(expected) = &self.expected_chain else {
+            return Ok(());
+        };
+        for client in std::iter::once(&self.client).chain(&self.archival_client) {
+            let status = self
+                .call_with_retry(client, || methods::status::RpcStatusRequest)
+                .await??;
+            if status.chain_id != *expected {
+                return Err(ListenerError::InvalidConfig(format!(
+                    "expected chain `{}`, but {} is on `{}`",
+                    expected,
+                    client.server_addr(),
+                    status.chain_id
+                )));
+            }
         }
+        self.expected_chain = None;
+        Ok(())
     }
 
-    pub fn account_id(mut self, account_id: &str) -> Self {
-        self.account_id = account_id.to_string();
-        self
-    }
+    // Returns the height to resume after, so that the next poll fetches the
+    // first block of the requested position.
+    async fn resolve_start(&self, position: &StartPosition) -> Result<u64, ListenerError> {
+        if let StartPosition::Transaction { tx_hash, signer_id } = position {
+            let height = self.transaction_height(tx_hash, signer_id).await?;
+            info!("Starting from block {}", height);
+            return Ok(height.saturating_sub(1));
+        }
 
-    pub fn method_name(mut self, method_name: &str) -> Self {
-        self.method_name = method_name.to_string();
-        self
+        let head = self
+            .fetch_block(BlockReference::Finality(self.finality.clone()))
+            .await??;
+
+        let height = match position {
+            StartPosition::Timestamp(time) => {
+                let timestamp = time
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |since_epoch| since_epoch.as_nanos() as u64);
+                self.first_block_since(timestamp, &head).await?
+            }
+            _ => head.header.height,
+        };
+        info!("Starting from block {}", height);
+
+        Ok(height.saturating_sub(1))
     }
 
-    pub fn last_processed_block(mut self, block: u64) -> Self {
-        self.last_processed_block = block;
-        self
+    async fn transaction_height(
+        &self,
+        tx_hash: &str,
+        signer_id: &str,
+    ) -> Result<u64, ListenerError> {
+        let (tx_hash, signer_id) = parse_transaction_id(tx_hash, signer_id)?;
+        let make_request = || methods::tx::RpcTransactionStatusRequest {
+            transaction_info: methods::tx::TransactionInfo::TransactionId {
+                tx_hash,
+                sender_account_id: signer_id.clone(),
+            },
+            wait_until: self.wait_until.clone(),
+        };
+
+        // Transactions from epochs the regular node has pruned are unknown to it.
+        let mut client = &self.client;
+        let mut result = self.call_with_retry(client, make_request).await?;
+        if let (
+            Some(archival_client),
+            Err(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(_))),
+        ) = (&self.archival_client, &result)
+        {
+            client = archival_client;
+            result = self.call_with_retry(client, make_request).await?;
+        }
+
+        let block_hash = match result?.final_execution_outcome {
+            Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome)) => {
+                outcome.transaction_outcome.block_hash
+            }
+            Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(outcome)) => {
+                outcome.final_outcome.transaction_outcome.block_hash
+            }
+            None => return Err(ListenerError::TxNotFound(tx_hash.to_string())),
+        };
+        let block = self
+            .call_with_retry(client, || methods::block::RpcBlockRequest {
+                block_reference: BlockReference::BlockId(BlockId::Hash(block_hash)),
+            })
+            .await??;
+
+        Ok(block.header.height)
     }
 
-    pub fn build(self) -> Result<NearEventListener, ListenerError> {
-        if self.account_id.is_empty() {
-            return Err(ListenerError::MissingField("account_id".to_string()));
+    // Binary search over heights for the first block produced at or after
+    // `timestamp`. Heights without a block are resolved to the next block.
+    async fn first_block_since(
+        &self,
+        timestamp: u64,
+        head: &BlockView,
+    ) -> Result<u64, ListenerError> {
+        if head.header.timestamp < timestamp {
+            return Ok(head.header.height + 1);
         }
-        if self.method_name.is_empty() {
-            return Err(ListenerError::MissingField("method_name".to_string()));
+
+        let client = self.archival_client.as_ref().unwrap_or(&self.client);
+        let status = self
+            .call_with_retry(client, || methods::status::RpcStatusRequest)
+            .await??;
+        let mut low = status.sync_info.earliest_block_height.unwrap_or(0);
+        let mut high = head.header.height;
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let block = self.block_at_or_after(mid, head.header.height).await?;
+            if block.header.timestamp >= timestamp {
+                high = mid;
+            } else {
+                low = block.header.height + 1;
+            }
         }
 
-        let client = JsonRpcClient::connect(&self.rpc_url);
+        let first = self.block_at_or_after(low, head.header.height).await?;
+        Ok(first.header.height)
+    }
 
-        Ok(NearEventListener {
-            client,
-            account_id: self.account_id,
-            method_name: self.method_name,
-            last_processed_block: self.last_processed_block,
-        })
+    async fn block_at_or_after(&self, height: u64, head: u64) -> Result<BlockView, ListenerError> {
+        for height in height..=head {
+            match self
+                .fetch_block(BlockReference::BlockId(BlockId::Height(height)))
+                .await?
+            {
+                Ok(block) => return Ok(block),
+                Err(err) => match err.handler_error() {
+                    Some(RpcBlockError::UnknownBlock { .. }) => continue,
+                    _ => return Err(err.into()),
+                },
+            }
+        }
+
+        Err(ListenerError::BlockNotFound(format!(
+            "no block between {} and {}",
+            height, head
+        )))
     }
-}
 
-impl NearEventListener {
-    pub fn builder(rpc_url: &str) -> NearEventListenerBuilder {
-        NearEventListenerBuilder::new(rpc_url)
+    async fn fetch_block(
+        &self,
+        block_reference: BlockReference,
+    ) -> Result<MethodCallResult<BlockView, RpcBlockError>, ListenerError> {
+        let height = match block_reference {
+            BlockReference::BlockId(BlockId::Height(height)) => Some(height),
+            _ => None,
+        };
+        if let Some(block) = self.cached_block(&block_reference) {
+            return Ok(Ok(block));
+        }
+
+        let block = match self
+            .call_rpc(height, || methods::block::RpcBlockRequest {
+                block_reference: block_reference.clone(),
+            })
+            .await?
+        {
+            Ok(block) => block,
+            Err(err) => return Ok(Err(err)),
+        };
+        if let Some(cache) = &self.response_cache {
+            let is_final = self.finality == Finality::Final
+                || matches!(block_reference, BlockReference::Finality(Finality::Final));
+            cache.lock().unwrap().insert_block(&block, is_final);
+        }
+        Ok(Ok(block))
     }
 
-    pub async fn start<F>(&mut self, callback: F) -> Result<(), ListenerError>
-    where
-        F: FnMut(EventLog) + Send + 'static,
-    {
-        println!(
-            "Starting event listener for account: {}, method: {}",
-            self.account_id, self.method_name
-        );
+    // Finality references are never answered from the cache, as they resolve
+    // to newer blocks while the chain grows.
+    fn cached_block(&self, block_reference: &BlockReference) -> Option<BlockView> {
+        let mut cache = self.response_cache.as_ref()?.lock().unwrap();
+        let cached = match block_reference {
+            BlockReference::BlockId(BlockId::Hash(hash)) => cache.block_by_hash(hash),
+            BlockReference::BlockId(BlockId::Height(height)) => cache.final_block(*height),
+            _ => return None,
+        };
+        self.handle.tracker().record_cache_lookup(cached.is_some());
+        cached
+    }
 
-        self.start_polling(callback).await
+    async fn fetch_chunk(
+        &self,
+        chunk_hash: CryptoHash,
+        height: u64,
+    ) -> Result<Arc<ChunkView>, ListenerError> {
+        if let Some(cache) = &self.response_cache {
+            let cached = cache.lock().unwrap().chunk(&chunk_hash);
+            self.handle.tracker().record_cache_lookup(cached.is_some());
+            if let Some(chunk) = cached {
+                return Ok(chunk);
+            }
+        }
+
+        let chunk = Arc::new(
+            self.call_rpc(Some(height), || methods::chunk::RpcChunkRequest {
+                chunk_reference: ChunkReference::ChunkHash {
+                    chunk_id: chunk_hash,
+                },
+            })
+            .await??,
+        );
+        if let Some(cache) = &self.response_cache {
+            cache.lock().unwrap().insert_chunk(chunk.clone());
+        }
+        Ok(chunk)
     }
 
-    async fn start_polling<F>(&mut self, mut callback: F) -> Result<(), ListenerError>
+    // Requests about a block the primary node has already garbage collected are
+    // repeated against the archival node, when one is configured.
+    async fn call_rpc<M, F>(
+        &self,
+        height: Option<u64>,
+        make_request: F,
+    ) -> Result<MethodCallResult<M::Response, M::Error>, ListenerError>
     where
-        F: FnMut(EventLog) + Send + 'static,
+        M: RpcMethod,
+        F: Fn() -> M,
     {
-        println!("Starting polling...");
+        let result = self.call_with_retry(&self.client, &make_request).await?;
 
-        loop {
-            println!("Last processed block: {}", self.last_processed_block);
-            let block_reference = self.specify_block_reference();
+        match (&self.archival_client, height, &result) {
+            (
+                Some(archival_client),
+                Some(height),
+                Err(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(_))),
+            ) if self.is_pruned(height).await => {
+                self.call_with_retry(archival_client, &make_request).await
+            }
+            _ => Ok(result),
+        }
+    }
 
-            match self.fetch_block(block_reference).await {
-                Ok(block) => {
-                    println!("Processing block: {:#?}", block.header.height);
+    async fn is_pruned(&self, height: u64) -> bool {
+        let cached = *self.earliest_block.lock().unwrap();
+        let earliest = match cached {
+            Some((earliest, fetched_at)) if fetched_at.elapsed() < EARLIEST_BLOCK_TTL => {
+                Some(earliest)
+            }
+            _ => {
+                let Ok(Ok(status)) = self
+                    .call_with_retry(&self.client, || methods::status::RpcStatusRequest)
+                    .await
+                else {
+                    return false;
+                };
+                let earliest = status.sync_info.earliest_block_height;
+                if let Some(earliest) = earliest {
+                    *self.earliest_block.lock().unwrap() = Some((earliest, Instant::now()));
+                }
+                earliest
+            }
+        };
 
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        earliest.is_none_or(|earliest| height < earliest)
+    }
 
-                    if let Some((tx_hash, sender_account_id)) =
-                        self.find_transaction_in_block(&block).await?
-                    {
-                        let logs = self.get_logs(&tx_hash, &sender_account_id).await?;
-                        
-                        println!("Logs: {:?}", logs);
-                        println!("Logs length: {}", logs.len());
-                        for log in logs {
-                            if let Ok(event_log) = Self::process_log(&log) {
-                                println!("\nEmitted event: {:?}\n", event_log);
-                                callback(event_log);
-                            }
+    // Fails with `ListenerError::Timeout` when the listener's own
+    // `request_timeout` or `call_deadline` runs out; errors of the node or
+    // the transport are left to the caller.
+    async fn call_with_retry<M, F>(
+        &self,
+        client: &JsonRpcClient,
+        make_request: F,
+    ) -> Result<MethodCallResult<M::Response, M::Error>, ListenerError>
+    where
+        M: RpcMethod,
+        F: Fn() -> M,
+    {
+        let call = async {
+            let mut attempt = 1;
+            loop {
+                if let Some(rate_limiter) = &self.rate_limiter {
+                    rate_limiter.acquire(self.runtime.as_ref()).await;
+                }
+
+                let result = self
+                    .with_timeout(self.request_timeout, client.call(make_request()))
+                    .await;
+                let retry = match &result {
+                    Ok(Ok(_)) => false,
+                    Ok(Err(err)) => {
+                        if err.handler_error().is_none() {
+                            self.handle.tracker().record_rpc_error();
                         }
+                        self.retry_policy.should_retry(attempt, err)
+                    }
+                    Err(_) => {
+                        self.handle.tracker().record_rpc_error();
+                        attempt < self.retry_policy.max_attempts()
                     }
+                };
 
-                    self.last_processed_block = block.header.height;
-                    println!("Saved new block height: {}", self.last_processed_block);
+                if !retry {
+                    return result;
                 }
-                Err(err) => self.handle_block_error(err).await?,
+                self.runtime.sleep(self.retry_policy.backoff(attempt)).await;
+                attempt += 1;
             }
+        };
+        self.with_timeout(self.call_deadline, call).await?
+    }
 
-            tokio::time::sleep(Duration::from_secs(2)).await;
+    async fn with_timeout<T>(
+        &self,
+        timeout: Option<Duration>,
+        call: impl Future<Output = T>,
+    ) -> Result<T, ListenerError> {
+        let Some(timeout) = timeout else {
+            return Ok(call.await);
+        };
+        match future::select(pin!(call), self.runtime.sleep(timeout)).await {
+            Either::Left((result, _)) => Ok(result),
+            Either::Right(((), _)) => Err(ListenerError::Timeout),
         }
     }
 
-    fn specify_block_reference(&self) -> BlockReference {
-        if self.last_processed_block == 0 {
-            BlockReference::Finality(Finality::Final)
-        } else {
-            BlockReference::BlockId(BlockId::Height(self.last_processed_block + 1))
-        }
+    async fn fetch_chunks(&self, block: &BlockView) -> Result<Vec<Arc<ChunkView>>, ListenerError> {
+        let chunk_fetches: Vec<_> = block
+            .chunks
+            .iter()
+            .filter(|chunk_header| has_new_contents(chunk_header, block.header.height))
+            .map(|chunk_header| self.fetch_chunk(chunk_header.chunk_hash, block.header.height))
+            .collect();
+        stream::iter(chunk_fetches)
+            .buffered(self.chunk_concurrency.max(1))
+            .try_collect()
+            .await
     }
 
-    async fn fetch_block(
+    pub async fn find_transaction_in_block(
         &self,
-        block_reference: BlockReference,
-    ) -> Result<BlockView, JsonRpcError<RpcBlockError>> {
-        let block_request = methods::block::RpcBlockRequest { block_reference };
-        self.client.call(block_request).await
+        block: &BlockView,
+    ) -> Result<Vec<MatchedTransaction>, ListenerError> {
+        let chunks = self.fetch_chunks(block).await?;
+        Ok(self.match_transactions(&chunks))
     }
 
-    async fn fetch_chunk(&self, chunk_hash: CryptoHash) -> Result<ChunkView, ListenerError> {
-        let chunk_reference = ChunkReference::ChunkHash {
-            chunk_id: chunk_hash,
-        };
+    pub async fn find_receipts_in_block(
+        &self,
+        block: &BlockView,
+    ) -> Result<Vec<MatchedReceipt>, ListenerError> {
+        let chunks = self.fetch_chunks(block).await?;
+        Ok(self.match_receipts(&chunks))
+    }
+
+    fn match_transactions(&self, chunks: &[Arc<ChunkView>]) -> Vec<MatchedTransaction> {
+        let mut matches = Vec::new();
+
+        for chunk in chunks {
+            for transaction in &chunk.transactions {
+                if let Some(call) =
+                    self.watched_call(transaction.receiver_id.as_str(), &transaction.actions)
+                {
+                    matches.push(MatchedTransaction {
+                        tx_hash: transaction.hash.to_string(),
+                        signer_id: transaction.signer_id.clone(),
+                        receiver_id: transaction.receiver_id.clone(),
+                        method_name: call.method_name,
+                        gas: call.gas,
+                        deposit: call.deposit,
+                        relayer_id: None,
+                    });
+                } else if let Some((delegate_action, call)) =
+                    self.watched_delegate_call(&transaction.actions)
+                {
+                    matches.push(MatchedTransaction {
+                        tx_hash: transaction.hash.to_string(),
+                        signer_id: delegate_action.sender_id.clone(),
+                        receiver_id: delegate_action.receiver_id.clone(),
+                        method_name: call.method_name,
+                        gas: call.gas,
+                        deposit: call.deposit,
+                        relayer_id: Some(transaction.signer_id.clone()),
+                    });
+                }
+            }
+        }
+        matches
+    }
+
+    fn match_receipts(&self, chunks: &[Arc<ChunkView>]) -> Vec<MatchedReceipt> {
+        let mut matches = Vec::new();
 
-        let chunk_request = methods::chunk::RpcChunkRequest { chunk_reference };
+        for chunk in chunks {
+            for receipt in &chunk.receipts {
+                let Some(call) = self.watched_receipt_call(receipt) else {
+                    continue;
+                };
+                if self.is_relayed_receipt(&receipt.receipt_id.to_string()) {
+                    continue;
+                }
 
-        match self.client.call(chunk_request).await {
-            Ok(chunk) => Ok(chunk),
-            Err(e) => {
-                println!("Error fetching chunk: {:?}", e);
-                Err(ListenerError::RpcError(e.to_string()))
+                if let ReceiptEnumView::Action { signer_id, .. } = &receipt.receipt {
+                    matches.push(MatchedReceipt {
+                        receipt_id: receipt.receipt_id.to_string(),
+                        predecessor_id: receipt.predecessor_id.clone(),
+                        signer_id: signer_id.clone(),
+                        receiver_id: receipt.receiver_id.clone(),
+                        method_name: call.method_name,
+                        gas: call.gas,
+                        deposit: call.deposit,
+                    });
+                }
             }
         }
+        matches
     }
 
-    pub async fn find_transaction_in_block(
-        &self,
-        block: &BlockView,
-    ) -> Result<Option<(String, AccountId)>, ListenerError> {
-        for chunk_header in &block.chunks {
-            let chunk_hash = chunk_header.chunk_hash;
-            let chunk = self.fetch_chunk(chunk_hash).await?;
+    // Transactions and receipts that send NEAR to a watched account. Like calls,
+    // receipts created directly from a transaction are found through it, and
+    // gas refunds, which come from `system`, are not payments.
+    fn match_payments(&self, chunks: &[Arc<ChunkView>]) -> Vec<MatchedPayment> {
+        if !self.detect_payments {
+            return Vec::new();
+        }
+        let mut matches = Vec::new();
+
+        for chunk in chunks {
             for transaction in &chunk.transactions {
-                if transaction.receiver_id == self.account_id {
-                    for action in &transaction.actions {
-                        if let ActionView::FunctionCall {
-                            method_name: action_method_name,
-                            ..
-                        } = action
-                        {
-                            if *action_method_name == self.method_name {
-                                return Ok(Some((
-                                    transaction.hash.to_string(),
-                                    transaction.signer_id.clone(),
-                                )));
-                            }
-                        }
+                if !self.matches_account(transaction.receiver_id.as_str()) {
+                    continue;
+                }
+                if let Some((amount, method_name)) = attached_deposit(&transaction.actions) {
+                    matches.push(MatchedPayment {
+                        sender_id: transaction.signer_id.clone(),
+                        signer_id: transaction.signer_id.clone(),
+                        receiver_id: transaction.receiver_id.clone(),
+                        amount,
+                        method_name,
+                        tx_hash: Some(transaction.hash),
+                        receipt_id: None,
+                    });
+                }
+            }
+
+            for receipt in &chunk.receipts {
+                let ReceiptEnumView::Action {
+                    signer_id, actions, ..
+                } = &receipt.receipt
+                else {
+                    continue;
+                };
+                if receipt.predecessor_id == *signer_id
+                    || receipt.predecessor_id.as_str() == "system"
+                    || !self.matches_account(receipt.receiver_id.as_str())
+                {
+                    continue;
+                }
+                if let Some((amount, method_name)) = attached_deposit(actions) {
+                    matches.push(MatchedPayment {
+                        sender_id: receipt.predecessor_id.clone(),
+                        signer_id: signer_id.clone(),
+                        receiver_id: receipt.receiver_id.clone(),
+                        amount,
+                        method_name,
+                        tx_hash: None,
+                        receipt_id: Some(receipt.receipt_id),
+                    });
+                }
+            }
+        }
+        matches
+    }
+
+    fn discover_factory_accounts(&self, chunks: &[Arc<ChunkView>]) {
+        if self.factories.is_empty() {
+            return;
+        }
+
+        for chunk in chunks {
+            let transactions = chunk
+                .transactions
+                .iter()
+                .map(|transaction| (&transaction.receiver_id, &transaction.actions));
+            let receipts = chunk
+                .receipts
+                .iter()
+                .filter_map(|receipt| match &receipt.receipt {
+                    ReceiptEnumView::Action { actions, .. } => {
+                        Some((&receipt.receiver_id, actions))
                     }
+                    _ => None,
+                });
+            for (receiver_id, actions) in transactions.chain(receipts) {
+                if actions
+                    .iter()
+                    .any(|action| matches!(action, ActionView::CreateAccount))
+                {
+                    self.discover_factory_account(receiver_id.as_str());
                 }
             }
         }
-        Ok(None)
+    }
+
+    // Only the parent account can create a subaccount, so a new direct
+    // subaccount of a factory was created by it. The account is watched from
+    // the next poll on, like accounts added through the handle.
+    fn discover_factory_account(&self, account_id: &str) {
+        let created_by_factory = self.factories.iter().any(|factory| {
+            account_id
+                .strip_suffix(factory.as_str())
+                .and_then(|name| name.strip_suffix('.'))
+                .is_some_and(|name| !name.is_empty() && !name.contains('.'))
+        });
+        if created_by_factory && !self.matches_account(account_id) {
+            info!("Factory created {}", account_id);
+            self.handle
+                .push(WatchChange::AddAccount(account_id.to_string()));
+        }
+    }
+
+    fn watched_receipt_call(&self, receipt: &ReceiptView) -> Option<WatchedCall> {
+        let ReceiptEnumView::Action {
+            signer_id, actions, ..
+        } = &receipt.receipt
+        else {
+            return None;
+        };
+
+        // Receipts created directly from a signed transaction are covered by the
+        // transaction scan, so only receipts produced by other contracts count here.
+        if receipt.predecessor_id == *signer_id {
+            return None;
+        }
+        self.watched_call(receipt.receiver_id.as_str(), actions)
+    }
+
+    // Meta-transactions (NEP-366) wrap the calls of their sender in a delegate
+    // action, which the transaction signer relays to the sender's account.
+    fn watched_delegate_call<'a>(
+        &self,
+        actions: &'a [ActionView],
+    ) -> Option<(&'a DelegateAction, WatchedCall)> {
+        actions.iter().find_map(|action| {
+            let ActionView::Delegate {
+                delegate_action, ..
+            } = action
+            else {
+                return None;
+            };
+            let inner_actions: Vec<ActionView> = delegate_action
+                .get_actions()
+                .into_iter()
+                .map(ActionView::from)
+                .collect();
+            let call = self.watched_call(delegate_action.receiver_id.as_str(), &inner_actions)?;
+            Some((delegate_action, call))
+        })
+    }
+
+    fn watched_call(&self, receiver_id: &str, actions: &[ActionView]) -> Option<WatchedCall> {
+        actions.iter().find_map(|action| match action {
+            ActionView::FunctionCall {
+                method_name,
+                gas,
+                deposit,
+                ..
+            } if self.watches_call(receiver_id, method_name) => Some(WatchedCall {
+                method_name: method_name.clone(),
+                gas: *gas,
+                deposit: *deposit,
+            }),
+            _ => None,
+        })
+    }
+
+    // Whether the listener itself or one of its subscriptions is interested in
+    // calls of `method_name` on `account_id`.
+    fn watches_call(&self, account_id: &str, method_name: &str) -> bool {
+        (self.matches_account(account_id) && self.matches_method(method_name))
+            || self
+                .subscriptions
+                .iter()
+                .any(|subscription| subscription.matches_call(account_id, method_name))
+    }
+
+    fn accepts_executor(&self, executor_id: &str) -> bool {
+        match &self.executor_filter {
+            ExecutorFilter::WatchedAccounts => self.watches_account(executor_id),
+            ExecutorFilter::Accounts(account_ids) => account_ids
+                .iter()
+                .any(|account_id| account_id == executor_id),
+            ExecutorFilter::Any => true,
+        }
+    }
+
+    fn watches_account(&self, account_id: &str) -> bool {
+        self.matches_account(account_id)
+            || self
+                .subscriptions
+                .iter()
+                .any(|subscription| subscription.matches_account(account_id))
+    }
+
+    pub fn matches_account(&self, account_id: &str) -> bool {
+        self.account_ids.iter().any(|watched| watched == account_id)
+            || self
+                .account_patterns
+                .iter()
+                .any(|pattern| glob_matches(pattern, account_id))
+            || self
+                .account_matcher
+                .as_ref()
+                .is_some_and(|matcher| matcher(account_id))
+    }
+
+    pub fn matches_method(&self, method_name: &str) -> bool {
+        if self.method_names.is_empty() && self.method_matcher.is_none() {
+            return true;
+        }
+
+        self.method_names
+            .iter()
+            .any(|pattern| glob_matches(pattern, method_name))
+            || self
+                .method_matcher
+                .as_ref()
+                .is_some_and(|matcher| matcher(method_name))
     }
 
     async fn get_logs(
         &self,
-        tx_hash: &str,
-        sender_account_id: &AccountId,
-    ) -> Result<Vec<String>, ListenerError> {
-        let tx_hash = CryptoHash::from_str(tx_hash)
+        transaction: &MatchedTransaction,
+        height: u64,
+    ) -> Result<Vec<OutcomeLogs>, ListenerError> {
+        let tx_hash = CryptoHash::from_str(&transaction.tx_hash)
             .map_err(|e| ListenerError::InvalidEventFormat(e.to_string()))?;
-
-        let transaction_status_request = methods::tx::RpcTransactionStatusRequest {
-            transaction_info: methods::tx::TransactionInfo::TransactionId {
-                tx_hash,
-                sender_account_id: sender_account_id.clone(),
-            },
-            wait_until: near_primitives::views::TxExecutionStatus::None,
-        };
+        let sender_account_id = transaction
+            .relayer_id
+            .as_ref()
+            .unwrap_or(&transaction.signer_id);
 
         let transaction_status_response = self
-            .client
-            .call(transaction_status_request)
-            .await
-            .map_err(|e| ListenerError::RpcError(e.to_string()))?;
+            .call_rpc(Some(height), || methods::tx::RpcTransactionStatusRequest {
+                transaction_info: methods::tx::TransactionInfo::TransactionId {
+                    tx_hash,
+                    sender_account_id: sender_account_id.clone(),
+                },
+                wait_until: self.wait_until.clone(),
+            })
+            .await??;
+
+        let mut outcomes = Vec::new();
+        let metrics = self
+            .include_execution_metrics
+            .then(|| transaction_metrics(&transaction_status_response))
+            .flatten();
+        let extracted =
+            Self::extract_outcome_logs(&transaction_status_response, self.include_receipt_views);
+        for mut outcome in extracted {
+            outcome.metrics = metrics;
+            if let (Some(receipt_id), Some(details)) = (outcome.receipt_id, &mut outcome.receipt) {
+                if details.receipt.is_none() && !outcome.logs.is_empty() {
+                    details.receipt = self.fetch_known_receipt(receipt_id, height).await?;
+                }
+            }
+            if let Some(receipt_id) = outcome.receipt_id {
+                if !outcome.logs.is_empty() && self.watches_account(outcome.executor_id.as_str()) {
+                    // Delivered when the receipt itself shows up in a scanned chunk,
+                    // except for relayed calls, which are attributed to their sender here.
+                    let receipt = self.fetch_receipt(receipt_id, height).await?;
+                    if self.watched_receipt_call(&receipt).is_some() {
+                        if transaction.relayer_id.is_none() {
+                            continue;
+                        }
+                        self.remember_relayed_receipt(receipt_id.to_string());
+                    }
+                }
+            }
+            outcomes.push(outcome);
+        }
 
-        let logs = self.extract_logs(&transaction_status_response);
-        Ok(logs)
+        Ok(outcomes)
     }
 
-    pub fn extract_logs(&self, response: &RpcTransactionResponse) -> Vec<String> {
-        let mut logs = Vec::new();
+    fn remember_relayed_receipt(&self, receipt_id: String) {
+        let mut relayed_receipts = self.relayed_receipts.lock().unwrap();
+        relayed_receipts.push_back(receipt_id);
+        if relayed_receipts.len() > RELAYED_RECEIPTS_CAPACITY {
+            relayed_receipts.pop_front();
+        }
+    }
+
+    fn is_relayed_receipt(&self, receipt_id: &str) -> bool {
+        self.relayed_receipts
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|relayed| relayed == receipt_id)
+    }
 
-        if let Some(final_outcome_enum) = &response.final_execution_outcome {
-            match final_outcome_enum {
-                FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(final_outcome) => {
-                    logs.extend(final_outcome.transaction_outcome.outcome.logs.clone());
+    async fn get_receipt_logs(
+        &self,
+        receipt: &MatchedReceipt,
+        light_client_head: CryptoHash,
+        height: u64,
+    ) -> Result<OutcomeLogs, ListenerError> {
+        let receipt_id = CryptoHash::from_str(&receipt.receipt_id)
+            .map_err(|e| ListenerError::InvalidEventFormat(e.to_string()))?;
 
-                    for receipt_outcome in &final_outcome.receipts_outcome {
-                        logs.extend(receipt_outcome.outcome.logs.clone());
-                    }
+        let proof = self
+            .call_rpc(Some(height), || {
+                methods::light_client_proof::RpcLightClientExecutionProofRequest {
+                    id: TransactionOrReceiptId::Receipt {
+                        receipt_id,
+                        receiver_id: receipt.receiver_id.clone(),
+                    },
+                    light_client_head,
                 }
-                FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(
-                    final_outcome_with_receipt,
-                ) => {
-                    println!("Something is missing: {:?}", final_outcome_with_receipt);
+            })
+            .await??;
+
+        let outcome = proof.outcome_proof;
+        let details = if self.include_receipt_views {
+            Some(ReceiptDetails {
+                receipt: self.fetch_known_receipt(receipt_id, height).await?,
+                outcome: outcome.outcome.clone(),
+            })
+        } else {
+            None
+        };
+        Ok(OutcomeLogs {
+            receipt_id: Some(outcome.id),
+            executor_id: outcome.outcome.executor_id,
+            logs: outcome.outcome.logs,
+            status: ExecutionStatus::of_receipt(&outcome.outcome.status),
+            receipt: details,
+            failure: ExecutionStatus::failure_of(&outcome.outcome.status),
+            metrics: self.include_execution_metrics.then_some(ExecutionMetrics {
+                gas_burnt: outcome.outcome.gas_burnt,
+                tokens_burnt: outcome.outcome.tokens_burnt,
+                receipts: 1,
+            }),
+        })
+    }
+
+    // Like `fetch_receipt`, for receipts the node may have garbage collected.
+    async fn fetch_known_receipt(
+        &self,
+        receipt_id: CryptoHash,
+        height: u64,
+    ) -> Result<Option<ReceiptView>, ListenerError> {
+        let result = self
+            .call_rpc(Some(height), || {
+                methods::EXPERIMENTAL_receipt::RpcReceiptRequest {
+                    receipt_reference: ReceiptReference { receipt_id },
                 }
-            }
+            })
+            .await?;
+        match result {
+            Ok(receipt) => Ok(Some(receipt)),
+            Err(err) => match err.handler_error() {
+                Some(RpcReceiptError::UnknownReceipt { .. }) => Ok(None),
+                _ => Err(err.into()),
+            },
         }
+    }
 
-        logs
+    async fn fetch_receipt(
+        &self,
+        receipt_id: CryptoHash,
+        height: u64,
+    ) -> Result<ReceiptView, ListenerError> {
+        self.call_rpc(Some(height), || {
+            methods::EXPERIMENTAL_receipt::RpcReceiptRequest {
+                receipt_reference: ReceiptReference { receipt_id },
+            }
+        })
+        .await?
+        .map_err(ListenerError::from)
     }
 
-    pub fn process_log(log: &str) -> Result<EventLog, ListenerError> {
-        if !log.starts_with("EVENT_JSON:") {
-            return Err(ListenerError::InvalidEventFormat(
-                "Log does not start with EVENT_JSON:".to_string(),
-            ));
+    pub fn extract_logs(&self, response: &RpcTransactionResponse) -> Vec<String> {
+        Self::extract_outcome_logs(response, false)
+            .into_iter()
+            .filter(|outcome| self.accepts_executor(outcome.executor_id.as_str()))
+            .flat_map(|outcome| outcome.logs)
+            .collect()
+    }
+
+    pub fn extract_attributed_logs(&self, response: &RpcTransactionResponse) -> Vec<AttributedLog> {
+        let mut attributed = Vec::new();
+
+        for outcome in Self::extract_outcome_logs(response, false) {
+            let source = match outcome.receipt_id {
+                Some(receipt_id) => LogSource::Receipt {
+                    receipt_id: receipt_id.to_string(),
+                },
+                None => LogSource::Transaction,
+            };
+            attributed.extend(outcome.logs.into_iter().map(|log| AttributedLog {
+                source: source.clone(),
+                executor_id: outcome.executor_id.to_string(),
+                log,
+            }));
         }
+        attributed
+    }
 
-        let json_str = &log["EVENT_JSON:".len()..];
+    // Some nodes answer with the receipts of the transaction next to its
+    // outcomes; their views are attached when `with_receipts` is set.
+    fn extract_outcome_logs(
+        response: &RpcTransactionResponse,
+        with_receipts: bool,
+    ) -> Vec<OutcomeLogs> {
+        let mut logs = Vec::new();
 
-        let event_log: EventLog = serde_json::from_str(json_str).map_err(|e| {
-            println!("Error deserializing JSON: {}", e);
-            ListenerError::JsonError(e)
-        })?;
+        let (final_outcome, receipts) = match &response.final_execution_outcome {
+            Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(final_outcome)) => {
+                (final_outcome, &[][..])
+            }
+            Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(
+                final_outcome_with_receipt,
+            )) => (
+                &final_outcome_with_receipt.final_outcome,
+                final_outcome_with_receipt.receipts.as_slice(),
+            ),
+            None => return logs,
+        };
+
+        // Every log is judged by how the transaction as a whole ended.
+        let status = ExecutionStatus::of_transaction(&final_outcome.status);
+        let transaction_outcome = &final_outcome.transaction_outcome.outcome;
+        logs.push(OutcomeLogs {
+            receipt_id: None,
+            executor_id: transaction_outcome.executor_id.clone(),
+            logs: transaction_outcome.logs.clone(),
+            status,
+            receipt: None,
+            metrics: None,
+            failure: ExecutionStatus::failure_of(&transaction_outcome.status),
+        });
+
+        for receipt_outcome in &final_outcome.receipts_outcome {
+            let receipt = with_receipts.then(|| ReceiptDetails {
+                receipt: receipts
+                    .iter()
+                    .find(|receipt| receipt.receipt_id == receipt_outcome.id)
+                    .cloned(),
+                outcome: receipt_outcome.outcome.clone(),
+            });
+            logs.push(OutcomeLogs {
+                receipt_id: Some(receipt_outcome.id),
+                executor_id: receipt_outcome.outcome.executor_id.clone(),
+                logs: receipt_outcome.outcome.logs.clone(),
+                status,
+                receipt,
+                metrics: None,
+                failure: ExecutionStatus::failure_of(&receipt_outcome.outcome.status),
+            });
+        }
 
-        Ok(event_log)
+        logs
+    }
+
+    pub fn process_log(log: &str) -> Result<EventLog, ListenerError> {
+        EventJsonDecoder::new().decode(log)
     }
 
     async fn handle_block_error(
@@ -260,24 +4343,211 @@ impl NearEventListener {
     ) -> Result<(), ListenerError> {
         match err.handler_error() {
             Some(methods::block::RpcBlockError::UnknownBlock { .. }) => {
-                println!("(i) Unknown block!");
-                self.last_processed_block += 1;
-                println!("Saved new block height: {}", self.last_processed_block);
-                Ok(())
+                let height = self.last_processed_block + 1;
+                let final_head = self
+                    .fetch_block(BlockReference::Finality(Finality::Final))
+                    .await??
+                    .header
+                    .height;
+
+                // A missing height at or below the final head was skipped by the
+                // chain and will never be produced; anything above it may still be.
+                if height <= final_head {
+                    // A lagging or pruned node also answers unknown; only a
+                    // healthy one is trusted to have no block at this height.
+                    if self.health_check.is_some() && !self.probe_health().await? {
+                        return Ok(());
+                    }
+                    info!("Skipped block {}", height);
+                    self.remember_skipped_height(height);
+                    self.advance_to(height, &[])
+                } else {
+                    debug!("Block {} not produced yet", height);
+                    self.reach_head();
+                    Ok(())
+                }
             }
-            Some(err) => Err(ListenerError::RpcError(format!("Block error: {:?}", err))),
+            Some(_) => Err(err.into()),
             _ => match err {
-                JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(status)) => {
-                    println!("(i) Server error occurred: status code {}", status);
-                    tokio::time::sleep(Duration::from_secs(5)).await;
+                JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(_)) => {
+                    let err = ListenerError::from(err);
+                    if self.report_error(&err) == ErrorAction::Stop {
+                        return Err(err);
+                    }
+                    self.runtime.sleep(Duration::from_secs(5)).await;
 
                     Ok(())
                 }
-                _ => Err(ListenerError::RpcError(format!(
-                    "Non-handler error: {:?}",
-                    err
-                ))),
+                _ => Err(err.into()),
             },
         }
     }
 }
+
+fn parse_transaction_id(
+    tx_hash: &str,
+    signer_id: &str,
+) -> Result<(CryptoHash, AccountId), ListenerError> {
+    let tx_hash = CryptoHash::from_str(tx_hash).map_err(|e| {
+        ListenerError::InvalidConfig(format!("invalid tx_hash `{}`: {}", tx_hash, e))
+    })?;
+    let signer_id = AccountId::from_str(signer_id).map_err(|e| {
+        ListenerError::InvalidConfig(format!("invalid signer_id `{}`: {}", signer_id, e))
+    })?;
+    Ok((tx_hash, signer_id))
+}
+
+pub(crate) fn validate_http_url(field: &str, url: &str) -> Result<(), ListenerError> {
+    let invalid = |reason: String| {
+        ListenerError::InvalidConfig(format!("invalid {} `{}`: {}", field, url, reason))
+    };
+
+    let parsed = Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(invalid(format!(
+            "expected an http or https URL, got `{}`",
+            parsed.scheme()
+        )));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(invalid("missing host".to_string()));
+    }
+    Ok(())
+}
+
+// A shard that missed the block repeats its previous chunk header, whose
+// contents were already scanned. Otherwise the chunk holds nothing to match
+// when its transaction root is empty and no gas went into producing its
+// outgoing receipts.
+fn has_new_contents(chunk_header: &ChunkHeaderView, height: u64) -> bool {
+    chunk_header.height_included == height
+        && (chunk_header.tx_root != CryptoHash::default() || chunk_header.gas_used > 0)
+}
+
+// Drops watches covered by a shorter prefix of the same account, so that a
+// change is reported once. Accounts were validated by the builder.
+fn storage_watches(watches: &[(String, Vec<u8>)]) -> Vec<(AccountId, Vec<u8>)> {
+    let mut kept: Vec<(AccountId, Vec<u8>)> = Vec::new();
+    let mut watches = watches.to_vec();
+    watches.sort_by_key(|(_, key_prefix)| key_prefix.len());
+    for (account_id, key_prefix) in watches {
+        let Ok(account_id) = AccountId::from_str(&account_id) else {
+            continue;
+        };
+        if !kept.iter().any(|(kept_id, kept_prefix)| {
+            *kept_id == account_id && key_prefix.starts_with(kept_prefix)
+        }) {
+            kept.push((account_id, key_prefix));
+        }
+    }
+    kept
+}
+
+// Totals over the transaction's own outcome and those of all its receipts.
+fn transaction_metrics(response: &RpcTransactionResponse) -> Option<ExecutionMetrics> {
+    let final_outcome = match response.final_execution_outcome.as_ref()? {
+        FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(final_outcome) => final_outcome,
+        FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(outcome) => {
+            &outcome.final_outcome
+        }
+    };
+    let outcomes = std::iter::once(&final_outcome.transaction_outcome)
+        .chain(&final_outcome.receipts_outcome)
+        .map(|outcome| &outcome.outcome);
+
+    let mut metrics = ExecutionMetrics {
+        gas_burnt: 0,
+        tokens_burnt: 0,
+        receipts: final_outcome.receipts_outcome.len(),
+    };
+    for outcome in outcomes {
+        metrics.gas_burnt = metrics.gas_burnt.saturating_add(outcome.gas_burnt);
+        metrics.tokens_burnt = metrics.tokens_burnt.saturating_add(outcome.tokens_burnt);
+    }
+    Some(metrics)
+}
+
+// The NEAR attached to the actions, with the first method they call, if any.
+fn attached_deposit(actions: &[ActionView]) -> Option<(u128, Option<String>)> {
+    let mut amount = 0;
+    let mut method = None;
+    for action in actions {
+        match action {
+            ActionView::Transfer { deposit } => amount += deposit,
+            ActionView::FunctionCall {
+                method_name,
+                deposit,
+                ..
+            } => {
+                amount += deposit;
+                method.get_or_insert_with(|| method_name.clone());
+            }
+            _ => {}
+        }
+    }
+    (amount > 0).then_some((amount, method))
+}
+
+// The transaction or receipt that caused a state change, when it was one.
+fn change_cause(cause: &StateChangeCauseView) -> (Option<String>, Option<String>) {
+    match cause {
+        StateChangeCauseView::TransactionProcessing { tx_hash } => {
+            (Some(tx_hash.to_string()), None)
+        }
+        StateChangeCauseView::ActionReceiptProcessingStarted { receipt_hash }
+        | StateChangeCauseView::ActionReceiptGasReward { receipt_hash }
+        | StateChangeCauseView::ReceiptProcessing { receipt_hash }
+        | StateChangeCauseView::PostponedReceipt { receipt_hash } => {
+            (None, Some(receipt_hash.to_string()))
+        }
+        _ => (None, None),
+    }
+}
+
+// Reads the lines of `path` on a thread of its own, so that replaying a file
+// does not block the executor. Lines come with their number, and reading
+// stops at the first error or once the receiver is dropped.
+fn read_lines(path: &Path) -> mpsc::Receiver<Result<(usize, String), ListenerError>> {
+    let (sender, receiver) = mpsc::channel(REPLAY_BUFFER);
+    let path = path.to_path_buf();
+    thread::spawn(move || {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) => {
+                let err = ListenerError::SourceError(format!("{}: {}", path.display(), err));
+                let _ = sender.blocking_send(Err(err));
+                return;
+            }
+        };
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map(|line| (index + 1, line)).map_err(|e| {
+                ListenerError::SourceError(format!("{}:{}: {}", path.display(), index + 1, e))
+            });
+            let failed = line.is_err();
+            if sender.blocking_send(line).is_err() || failed {
+                return;
+            }
+        }
+    });
+    receiver
+}
+
+fn applied_envelopes(notifications: &[EventNotification]) -> Vec<EventEnvelope> {
+    notifications
+        .iter()
+        .filter_map(|notification| match notification {
+            EventNotification::Applied(envelope) => Some(envelope.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn dispatch_error(handler: Option<&ErrorHandler>, err: &ListenerError) -> ErrorAction {
+    match handler {
+        Some(handler) => handler(err),
+        None => {
+            error!("{}", err);
+            ErrorAction::Continue
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{EventEnvelope, ListenerError};
+use futures::future::{self, BoxFuture};
+use std::sync::Mutex;
+
+// A step events pass through before they reach any consumer. Implementations
+// can change the envelope, hold it back (e.g. to rate limit) or drop it by not
+// calling `next`.
+pub trait EventMiddleware: Send + Sync {
+    fn handle<'a>(
+        &'a self,
+        envelope: EventEnvelope,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<(), ListenerError>>;
+}
+
+// The rest of the chain after the current middleware.
+pub struct Next<'a> {
+    middleware: &'a [Box<dyn EventMiddleware>],
+    delivered: &'a Mutex<Vec<EventEnvelope>>,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        middleware: &'a [Box<dyn EventMiddleware>],
+        delivered: &'a Mutex<Vec<EventEnvelope>>,
+    ) -> Self {
+        Self {
+            middleware,
+            delivered,
+        }
+    }
+
+    pub fn run(self, envelope: EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => {
+                middleware.handle(envelope, Next::new(rest, self.delivered))
+            }
+            None => {
+                self.delivered.lock().unwrap().push(envelope);
+                Box::pin(future::ready(Ok(())))
+            }
+        }
+    }
+}
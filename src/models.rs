@@ -1,3 +1,9 @@
+use crate::{ListenerError, NearEvent};
+use near_primitives::views::{
+    ExecutionOutcomeView, ExecutionStatusView, FinalExecutionStatus, ReceiptView,
+};
+use near_sdk::AccountId;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -8,3 +14,498 @@ pub struct EventLog {
     pub event: String,
     pub data: Value,
 }
+
+impl EventLog {
+    pub fn parse_data<T: DeserializeOwned>(&self) -> Result<T, ListenerError> {
+        Ok(serde_json::from_value(self.data.clone())?)
+    }
+
+    // Checks the event against NEP-297: a non-empty standard and event name, a
+    // semver version, and `data` holding an array of objects.
+    pub fn validate(&self) -> Result<(), ListenerError> {
+        let violation = |message: String| Err(ListenerError::NonCompliantEvent(message));
+
+        if self.standard.is_empty() {
+            return violation("empty standard".to_string());
+        }
+        if self.event.is_empty() {
+            return violation("empty event".to_string());
+        }
+        if let Err(e) = semver::Version::parse(&self.version) {
+            return violation(format!("version `{}` is not semver: {}", self.version, e));
+        }
+        match &self.data {
+            Value::Array(entries) if entries.iter().all(Value::is_object) => Ok(()),
+            Value::Array(_) => violation("data entries must be objects".to_string()),
+            _ => violation("data must be an array".to_string()),
+        }
+    }
+
+    pub fn as_nep171(&self) -> Result<Nep171Event, ListenerError> {
+        Nep171Event::from_event_log(self)
+    }
+
+    pub fn as_nep141(&self) -> Result<Nep141Event, ListenerError> {
+        Nep141Event::from_event_log(self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventContext {
+    pub account_id: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub tx_hash: Option<String>,
+    pub signer_id: String,
+    pub receiver_id: String,
+    // The watched function call of the transaction or receipt that produced
+    // the event. Missing for events read back from older archives.
+    #[serde(default)]
+    pub method_name: Option<String>,
+    // Attached to that function call, in yoctoNEAR and gas units. The deposit
+    // is serialized as a decimal string.
+    #[serde(default, with = "near_primitives::serialize::dec_format")]
+    pub deposit: Option<u128>,
+    #[serde(default)]
+    pub gas: Option<u64>,
+    // Final status of the transaction, or of the receipt for events found
+    // through receipt scanning. None while it has not finished executing.
+    #[serde(default)]
+    pub execution_status: Option<ExecutionStatus>,
+    pub receipt_id: Option<String>,
+    // Position of the log within its execution outcome.
+    pub log_index: usize,
+    // Position of the event in the order the listener delivered it, counting
+    // from 1. None for events that did not reach the listener's own consumers.
+    #[serde(default)]
+    pub sequence: Option<u64>,
+    // Set on events found by `repair_gaps` in a block that was missing when the
+    // listener first reached its height.
+    #[serde(default)]
+    pub late: bool,
+    // The receipt that emitted the event and its outcome, with
+    // `include_receipt_views`. None for logs of the transaction itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<ReceiptDetails>,
+    // What executing the event cost, with `include_execution_metrics`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<ExecutionMetrics>,
+}
+
+// Gas and tokens burnt by the transaction behind an event, summed over the
+// transaction and every receipt it produced. Events found through receipt
+// scanning or in NEAR Lake have no transaction, so only their receipt counts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExecutionMetrics {
+    pub gas_burnt: u64,
+    // In yoctoNEAR, serialized as a decimal string.
+    #[serde(with = "near_primitives::serialize::dec_format")]
+    pub tokens_burnt: u128,
+    // Receipt outcomes included in the sums.
+    pub receipts: usize,
+}
+
+// As returned by the node: gas and tokens burnt, status and logs are in
+// `outcome`. `receipt` is None when the node no longer knows the receipt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReceiptDetails {
+    pub receipt: Option<ReceiptView>,
+    pub outcome: ExecutionOutcomeView,
+}
+
+impl EventContext {
+    pub fn source(&self) -> LogSource {
+        match &self.receipt_id {
+            Some(receipt_id) => LogSource::Receipt {
+                receipt_id: receipt_id.clone(),
+            },
+            None => LogSource::Transaction,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Success,
+    Failure,
+}
+
+impl ExecutionStatus {
+    pub(crate) fn of_transaction(status: &FinalExecutionStatus) -> Option<Self> {
+        match status {
+            FinalExecutionStatus::NotStarted | FinalExecutionStatus::Started => None,
+            FinalExecutionStatus::Failure(_) => Some(Self::Failure),
+            FinalExecutionStatus::SuccessValue(_) => Some(Self::Success),
+        }
+    }
+
+    // The error of a failed outcome, as a message.
+    pub(crate) fn failure_of(status: &ExecutionStatusView) -> Option<String> {
+        match status {
+            ExecutionStatusView::Failure(error) => Some(error.to_string()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn of_receipt(status: &ExecutionStatusView) -> Option<Self> {
+        match status {
+            ExecutionStatusView::Unknown => None,
+            ExecutionStatusView::Failure(_) => Some(Self::Failure),
+            ExecutionStatusView::SuccessValue(_) | ExecutionStatusView::SuccessReceiptId(_) => {
+                Some(Self::Success)
+            }
+        }
+    }
+}
+
+// Which execution outcome a log line was emitted in: the transaction's own, or
+// that of one of the receipts it produced.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LogSource {
+    Transaction,
+    Receipt { receipt_id: String },
+}
+
+// A log line together with the outcome and the account that emitted it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AttributedLog {
+    pub source: LogSource,
+    pub executor_id: String,
+    pub log: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventEnvelope {
+    pub context: EventContext,
+    pub event: EventLog,
+}
+
+// A log line that is not a NEP-297 event, delivered when raw logs are enabled.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RawLog {
+    pub context: EventContext,
+    pub log: String,
+}
+
+// A write to, or removal of, a watched storage key. `value` is `None` when the
+// key was removed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StateChange {
+    pub account_id: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub key: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+    // The transaction or receipt whose execution made the change, when known.
+    pub tx_hash: Option<String>,
+    pub receipt_id: Option<String>,
+}
+
+// A change to an account watched with `watch_account_changes`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountChange {
+    pub account_id: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub kind: AccountChangeKind,
+    pub tx_hash: Option<String>,
+    pub receipt_id: Option<String>,
+}
+
+// Balances are in yoctoNEAR. A balance change covers transfers as well as gas
+// and storage costs; `previous` is the balance before the change.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AccountChangeKind {
+    Balance {
+        previous: u128,
+        balance: u128,
+    },
+    AccessKeyAdded {
+        public_key: String,
+        full_access: bool,
+    },
+    AccessKeyRemoved {
+        public_key: String,
+    },
+    CodeDeployed {
+        code_hash: String,
+    },
+    AccountDeleted,
+}
+
+// NEAR received by a watched account, in a transfer or attached to a function
+// call. `sender_id` is the predecessor, i.e. the account the NEAR came from;
+// `method_name` is `None` for plain transfers. Amounts are in yoctoNEAR.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Payment {
+    pub receiver_id: String,
+    pub sender_id: String,
+    pub signer_id: String,
+    pub amount: u128,
+    pub method_name: Option<String>,
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub tx_hash: Option<String>,
+    pub receipt_id: Option<String>,
+}
+
+// A watched function call whose execution failed. `error` is the execution
+// error as the node describes it; `context.log_index` is meaningless here.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FailedCall {
+    pub context: EventContext,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventNotification {
+    Applied(EventEnvelope),
+    Reverted(EventEnvelope),
+    RawLog(RawLog),
+    StateChange(StateChange),
+    AccountChange(AccountChange),
+    Payment(Payment),
+    Failure(FailedCall),
+}
+
+// Everything the listener reports, as delivered by `events()`. New kinds of
+// notifications are added as variants, so matches need a catch-all arm.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ListenerEvent {
+    Nep297(EventEnvelope),
+    // An event delivered earlier whose block was orphaned.
+    Reorg(EventEnvelope),
+    RawLog(RawLog),
+    StateChange(StateChange),
+    AccountChange(AccountChange),
+    Payment(Payment),
+    Failure(FailedCall),
+    // Follows the notifications of a processed block, also when it had none.
+    BlockBoundary(BlockHeader),
+}
+
+impl ListenerEvent {
+    pub(crate) fn into_notification(self) -> Option<EventNotification> {
+        match self {
+            Self::Nep297(envelope) => Some(EventNotification::Applied(envelope)),
+            Self::Reorg(envelope) => Some(EventNotification::Reverted(envelope)),
+            Self::RawLog(raw_log) => Some(EventNotification::RawLog(raw_log)),
+            Self::StateChange(change) => Some(EventNotification::StateChange(change)),
+            Self::AccountChange(change) => Some(EventNotification::AccountChange(change)),
+            Self::Payment(payment) => Some(EventNotification::Payment(payment)),
+            Self::Failure(failure) => Some(EventNotification::Failure(failure)),
+            Self::BlockBoundary(_) => None,
+        }
+    }
+}
+
+impl From<EventNotification> for ListenerEvent {
+    fn from(notification: EventNotification) -> Self {
+        match notification {
+            EventNotification::Applied(envelope) => Self::Nep297(envelope),
+            EventNotification::Reverted(envelope) => Self::Reorg(envelope),
+            EventNotification::RawLog(raw_log) => Self::RawLog(raw_log),
+            EventNotification::StateChange(change) => Self::StateChange(change),
+            EventNotification::AccountChange(change) => Self::AccountChange(change),
+            EventNotification::Payment(payment) => Self::Payment(payment),
+            EventNotification::Failure(failure) => Self::Failure(failure),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlockHeader {
+    pub height: u64,
+    pub hash: String,
+    pub timestamp: u64,
+}
+
+// Reported by `on_block_processed` once a block is done. `events_count` is
+// the number of events delivered from it, which may be zero.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlockProcessed {
+    pub height: u64,
+    pub hash: String,
+    pub timestamp: u64,
+    pub events_count: usize,
+}
+
+// All events of one block, delivered together by `start_batched`. A reverted
+// batch lists the events of an orphaned block that are no longer valid.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlockEvents {
+    pub header: BlockHeader,
+    pub events: Vec<EventEnvelope>,
+    pub reverted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedTransaction {
+    pub tx_hash: String,
+    pub signer_id: AccountId,
+    pub receiver_id: AccountId,
+    pub method_name: String,
+    pub gas: u64,
+    pub deposit: u128,
+    // For relayed meta-transactions, the account that signed and submitted the
+    // transaction; `signer_id` is then the sender of the delegate action.
+    pub relayer_id: Option<AccountId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedReceipt {
+    pub receipt_id: String,
+    pub predecessor_id: AccountId,
+    pub signer_id: AccountId,
+    pub receiver_id: AccountId,
+    pub method_name: String,
+    pub gas: u64,
+    pub deposit: u128,
+}
+
+// NEP-171 (non-fungible token) events, any version. Each variant holds the
+// entries of the event's `data` array.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Nep171Event {
+    NftMint(Vec<NftMintLog>),
+    NftTransfer(Vec<NftTransferLog>),
+    NftBurn(Vec<NftBurnLog>),
+    ContractMetadataUpdate(Vec<NftContractMetadataUpdateLog>),
+}
+
+impl NearEvent for Nep171Event {
+    fn matches(event_log: &EventLog) -> bool {
+        event_log.standard == "nep171"
+            && [
+                "nft_mint",
+                "nft_transfer",
+                "nft_burn",
+                "contract_metadata_update",
+            ]
+            .contains(&event_log.event.as_str())
+    }
+
+    fn from_event_log(event_log: &EventLog) -> Result<Self, ListenerError> {
+        if event_log.standard != "nep171" {
+            return Err(unexpected_standard(event_log, "nep171"));
+        }
+        match event_log.event.as_str() {
+            "nft_mint" => Ok(Self::NftMint(event_log.parse_data()?)),
+            "nft_transfer" => Ok(Self::NftTransfer(event_log.parse_data()?)),
+            "nft_burn" => Ok(Self::NftBurn(event_log.parse_data()?)),
+            "contract_metadata_update" => Ok(Self::ContractMetadataUpdate(event_log.parse_data()?)),
+            other => Err(ListenerError::InvalidEventFormat(format!(
+                "Unexpected event: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NftMintLog {
+    pub owner_id: AccountId,
+    pub token_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NftTransferLog {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorized_id: Option<AccountId>,
+    pub old_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+    pub token_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NftBurnLog {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorized_id: Option<AccountId>,
+    pub owner_id: AccountId,
+    pub token_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NftContractMetadataUpdateLog {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+// NEP-141 (fungible token) events, any version. Amounts are decimal strings in
+// the logs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Nep141Event {
+    FtMint(Vec<FtMintLog>),
+    FtTransfer(Vec<FtTransferLog>),
+    FtBurn(Vec<FtBurnLog>),
+}
+
+impl NearEvent for Nep141Event {
+    fn matches(event_log: &EventLog) -> bool {
+        event_log.standard == "nep141"
+            && ["ft_mint", "ft_transfer", "ft_burn"].contains(&event_log.event.as_str())
+    }
+
+    fn from_event_log(event_log: &EventLog) -> Result<Self, ListenerError> {
+        if event_log.standard != "nep141" {
+            return Err(unexpected_standard(event_log, "nep141"));
+        }
+        match event_log.event.as_str() {
+            "ft_mint" => Ok(Self::FtMint(event_log.parse_data()?)),
+            "ft_transfer" => Ok(Self::FtTransfer(event_log.parse_data()?)),
+            "ft_burn" => Ok(Self::FtBurn(event_log.parse_data()?)),
+            other => Err(ListenerError::InvalidEventFormat(format!(
+                "Unexpected event: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FtMintLog {
+    pub owner_id: AccountId,
+    #[serde(with = "near_primitives::serialize::dec_format")]
+    pub amount: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FtTransferLog {
+    pub old_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+    #[serde(with = "near_primitives::serialize::dec_format")]
+    pub amount: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FtBurnLog {
+    pub owner_id: AccountId,
+    #[serde(with = "near_primitives::serialize::dec_format")]
+    pub amount: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+fn unexpected_standard(event_log: &EventLog, standard: &str) -> ListenerError {
+    ListenerError::InvalidEventFormat(format!(
+        "Event {} {} {} is not a {} event",
+        event_log.standard, event_log.version, event_log.event, standard
+    ))
+}
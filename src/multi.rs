@@ -0,0 +1,124 @@
+use crate::TaskHandle;
+use crate::{
+    EventEnvelope, ListenerError, NearEventListener, NearEventListenerBuilder, Network, Unwatched,
+};
+use futures::future::AbortHandle;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+const START_BUFFER: usize = 100;
+
+// An event together with the network, or provider, it was read from.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NetworkEvent {
+    pub network: String,
+    pub envelope: EventEnvelope,
+}
+
+// Runs several listeners side by side, e.g. the same watch list on mainnet and
+// testnet or against several RPC providers, and merges their events into one
+// feed tagged with where each came from.
+#[derive(Debug, Default)]
+pub struct MultiNetworkListener {
+    listeners: Vec<(String, NearEventListener)>,
+}
+
+impl MultiNetworkListener {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `network` is the tag carried by the listener's events.
+    pub fn add(mut self, network: &str, listener: NearEventListener) -> Self {
+        self.listeners.push((network.to_string(), listener));
+        self
+    }
+
+    // Builds one listener per network from the same configuration, each tagged
+    // with the network's chain ID.
+    pub fn for_networks<F>(networks: &[Network], configure: F) -> Result<Self, ListenerError>
+    where
+        F: Fn(NearEventListenerBuilder<Unwatched>) -> NearEventListenerBuilder,
+    {
+        let mut multi = Self::new();
+        for network in networks {
+            let listener = configure(NearEventListener::builder_for(network.clone())).build()?;
+            multi = multi.add(network.chain_id(), listener);
+        }
+        Ok(multi)
+    }
+
+    // Like `NearEventListener::channel`, for all listeners at once. The task
+    // ends when every listener is done or the receiver is dropped, and with
+    // the first error of any listener, which stops the others.
+    pub fn channel(
+        self,
+        buffer: usize,
+    ) -> (
+        mpsc::Receiver<NetworkEvent>,
+        TaskHandle<Result<(), ListenerError>>,
+    ) {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        let Some(runtime) = self
+            .listeners
+            .first()
+            .map(|(_, listener)| listener.runtime())
+        else {
+            return (receiver, TaskHandle::ready(Ok(())));
+        };
+
+        let mut tasks = FuturesUnordered::new();
+        for (network, listener) in self.listeners {
+            let sender = sender.clone();
+            let runtime = listener.runtime();
+            tasks.push(TaskHandle::spawn(
+                runtime.as_ref(),
+                listener.forward(move |envelope| {
+                    let sender = sender.clone();
+                    let event = NetworkEvent {
+                        network: network.clone(),
+                        envelope,
+                    };
+                    async move { sender.send(event).await.is_ok() }
+                }),
+            ));
+        }
+
+        let task = TaskHandle::spawn(runtime.as_ref(), async move {
+            // Stops the remaining listeners once one fails or the task is
+            // aborted.
+            let _guard = AbortOnDrop(tasks.iter().map(TaskHandle::abort_handle).collect());
+            while let Some(joined) = tasks.next().await {
+                if let Ok(Err(err)) = joined {
+                    return Err(err);
+                }
+            }
+            Ok(())
+        });
+
+        (receiver, task)
+    }
+
+    pub async fn start<F>(self, mut callback: F) -> Result<(), ListenerError>
+    where
+        F: FnMut(NetworkEvent),
+    {
+        let (mut receiver, task) = self.channel(START_BUFFER);
+        while let Some(event) = receiver.recv().await {
+            callback(event);
+        }
+
+        task.await.unwrap_or(Ok(()))
+    }
+}
+
+struct AbortOnDrop(Vec<AbortHandle>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for handle in &self.0 {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::sink::render_topic;
+use crate::{EventEnvelope, EventSink, ListenerError, SinkFormat};
+use futures::future::BoxFuture;
+
+// Publishes every event to a NATS subject. The subject may contain
+// `{account_id}`, `{standard}` and `{event}` placeholders.
+pub struct NatsSink {
+    client: async_nats::Client,
+    subject: String,
+    format: SinkFormat,
+}
+
+impl NatsSink {
+    pub async fn connect(url: &str, subject: &str) -> Result<Self, ListenerError> {
+        let client = async_nats::connect(url)
+            .await
+            .map_err(|e| ListenerError::SinkError(e.to_string()))?;
+        Ok(Self::from_client(client, subject))
+    }
+
+    pub fn from_client(client: async_nats::Client, subject: &str) -> Self {
+        Self {
+            client,
+            subject: subject.to_string(),
+            format: SinkFormat::default(),
+        }
+    }
+
+    pub fn format(mut self, format: SinkFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+impl EventSink for NatsSink {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        Box::pin(async move {
+            let payload = self.format.encode(envelope)?;
+            let subject = render_topic(&self.subject, envelope);
+
+            self.client
+                .publish(subject, payload.into())
+                .await
+                .map_err(|e| ListenerError::SinkError(e.to_string()))
+        })
+    }
+}
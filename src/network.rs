@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+// A NEAR network with its canonical RPC endpoints, for
+// `NearEventListener::builder_for`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Betanet,
+    // A local node or sandbox at the given RPC URL.
+    Localnet(String),
+}
+
+impl Network {
+    pub fn rpc_url(&self) -> &str {
+        match self {
+            Self::Mainnet => "https://rpc.mainnet.near.org",
+            Self::Testnet => "https://rpc.testnet.near.org",
+            Self::Betanet => "https://rpc.betanet.near.org",
+            Self::Localnet(rpc_url) => rpc_url,
+        }
+    }
+
+    // The chain ID nodes of public networks report; local nodes pick their
+    // own, so they are all tagged `localnet`.
+    pub fn chain_id(&self) -> &str {
+        match self {
+            Self::Mainnet => "mainnet",
+            Self::Testnet => "testnet",
+            Self::Betanet => "betanet",
+            Self::Localnet(_) => "localnet",
+        }
+    }
+
+    pub fn archival_rpc_url(&self) -> Option<&str> {
+        match self {
+            Self::Mainnet => Some("https://archival-rpc.mainnet.near.org"),
+            Self::Testnet => Some("https://archival-rpc.testnet.near.org"),
+            Self::Betanet | Self::Localnet(_) => None,
+        }
+    }
+
+    // Public networks produce a block about every second; local nodes are
+    // usually configured to go faster.
+    pub(crate) fn poll_interval(&self) -> Duration {
+        match self {
+            Self::Localnet(_) => Duration::from_millis(500),
+            _ => Duration::from_secs(1),
+        }
+    }
+}
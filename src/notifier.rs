@@ -0,0 +1,184 @@
+use crate::listener::validate_http_url;
+use crate::post::{post_with_retry, HttpRetry, PostError};
+use crate::runtime;
+use crate::{
+    EventEnvelope, EventFilter, EventSink, EventTemplate, ListenerError, RetryPolicy, Runtime,
+};
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+const DEFAULT_TEMPLATE: &str =
+    "{{ event }} on {{ account_id }} in block {{ block_height }}: {{ data | tojson }}";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatPlatform {
+    Discord,
+    Slack,
+    Telegram { chat_id: String },
+}
+
+// Posts a message to a chat for every matching event, e.g. mints of an NFT
+// collection or proposals of a DAO. Clones share the same connection pool.
+#[derive(Debug, Clone)]
+pub struct NotifierSink {
+    platform: ChatPlatform,
+    url: String,
+    template: EventTemplate,
+    filter: Option<EventFilter>,
+    retry: HttpRetry,
+    http: reqwest::Client,
+}
+
+impl NotifierSink {
+    // `url` is the channel's incoming webhook URL.
+    pub fn discord(url: &str) -> Result<Self, ListenerError> {
+        Self::new(ChatPlatform::Discord, url)
+    }
+
+    pub fn slack(url: &str) -> Result<Self, ListenerError> {
+        Self::new(ChatPlatform::Slack, url)
+    }
+
+    // Sends as the bot with the given token, which must be a member of the
+    // chat.
+    pub fn telegram(bot_token: &str, chat_id: &str) -> Result<Self, ListenerError> {
+        Self::telegram_with_api(TELEGRAM_API_URL, bot_token, chat_id)
+    }
+
+    // For a self-hosted Bot API server.
+    pub fn telegram_with_api(
+        api_url: &str,
+        bot_token: &str,
+        chat_id: &str,
+    ) -> Result<Self, ListenerError> {
+        let url = format!(
+            "{}/bot{}/sendMessage",
+            api_url.trim_end_matches('/'),
+            bot_token
+        );
+        Self::new(
+            ChatPlatform::Telegram {
+                chat_id: chat_id.to_string(),
+            },
+            &url,
+        )
+    }
+
+    fn new(platform: ChatPlatform, url: &str) -> Result<Self, ListenerError> {
+        validate_http_url("notifier url", url)?;
+
+        Ok(Self {
+            platform,
+            url: url.to_string(),
+            template: EventTemplate::new(DEFAULT_TEMPLATE)?,
+            filter: None,
+            retry: HttpRetry {
+                policy: RetryPolicy::new(3),
+                timeout: DEFAULT_TIMEOUT,
+                runtime: runtime::default_runtime(),
+                on_retry: None,
+            },
+            http: reqwest::Client::new(),
+        })
+    }
+
+    // Renders the message.
+    pub fn template(mut self, template: EventTemplate) -> Self {
+        self.template = template;
+        self
+    }
+
+    // Only events matching `filter` are posted; the others are skipped.
+    pub fn filter(mut self, filter: EventFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry.policy = policy;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.retry.timeout = timeout;
+        self
+    }
+
+    // Waits out the retry backoff on another executor than tokio. Without a
+    // runtime, failed notifications are retried right away.
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.retry.runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    // Called with the attempt number and its error whenever a failed
+    // notification is about to be retried.
+    pub fn on_retry<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(u32, &ListenerError) + Send + Sync + 'static,
+    {
+        self.retry.on_retry = Some(Arc::new(handler));
+        self
+    }
+
+    pub async fn send(&self, envelope: &EventEnvelope) -> Result<(), ListenerError> {
+        if self
+            .filter
+            .as_ref()
+            .is_some_and(|filter| !filter.matches(&envelope.event))
+        {
+            return Ok(());
+        }
+
+        let body = self.body(&self.template.render(envelope)?);
+        post_with_retry(
+            &self.http,
+            &self.url,
+            HeaderMap::new(),
+            body.to_string().as_bytes(),
+            &self.retry,
+            |failure| match failure {
+                PostError::Transport(err) => {
+                    ListenerError::SinkError(err.without_url().to_string())
+                }
+                PostError::Status(status) => ListenerError::SinkError(format!(
+                    "{} responded with {}",
+                    self.platform.name(),
+                    status
+                )),
+            },
+        )
+        .await
+    }
+
+    fn body(&self, message: &str) -> Value {
+        match &self.platform {
+            ChatPlatform::Discord => json!({ "content": message }),
+            ChatPlatform::Slack => json!({ "text": message }),
+            ChatPlatform::Telegram { chat_id } => json!({ "chat_id": chat_id, "text": message }),
+        }
+    }
+}
+
+impl ChatPlatform {
+    // The URL is not reported in errors, since it holds the webhook's secret
+    // or the bot's token.
+    fn name(&self) -> &'static str {
+        match self {
+            ChatPlatform::Discord => "Discord",
+            ChatPlatform::Slack => "Slack",
+            ChatPlatform::Telegram { .. } => "Telegram",
+        }
+    }
+}
+
+impl EventSink for NotifierSink {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        Box::pin(NotifierSink::send(self, envelope))
+    }
+}
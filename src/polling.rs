@@ -0,0 +1,77 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub(crate) const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+pub(crate) const DEFAULT_CATCH_UP_INTERVAL: Duration = Duration::from_millis(500);
+
+// Gives the receipts of transactions in a fresh block time to execute before
+// their outcomes are requested.
+pub(crate) const OUTCOME_DELAY: Duration = Duration::from_secs(2);
+
+// Minimum time between two head lookups made to report lag.
+pub(crate) const LAG_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// Blocks older than this are considered history rather than chain head.
+const CATCH_UP_LAG: Duration = Duration::from_secs(10);
+
+pub(crate) fn is_behind_head(block_timestamp: u64) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let produced_at = Duration::from_nanos(block_timestamp);
+
+    now.saturating_sub(produced_at) > CATCH_UP_LAG
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PollTimer {
+    poll_interval: Duration,
+    catch_up_interval: Duration,
+    adaptive: bool,
+    current_interval: Duration,
+    catching_up: bool,
+}
+
+impl PollTimer {
+    pub(crate) fn new(
+        poll_interval: Duration,
+        catch_up_interval: Duration,
+        adaptive: bool,
+    ) -> Self {
+        Self {
+            poll_interval,
+            catch_up_interval,
+            adaptive,
+            current_interval: poll_interval,
+            catching_up: false,
+        }
+    }
+
+    pub(crate) fn observe_block(&mut self, block_timestamp: u64) {
+        self.catching_up = is_behind_head(block_timestamp);
+    }
+
+    pub(crate) fn observe_head(&mut self) {
+        self.catching_up = false;
+    }
+
+    pub(crate) fn is_catching_up(&self) -> bool {
+        self.catching_up
+    }
+
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        if !self.adaptive {
+            return if self.catching_up {
+                self.catch_up_interval
+            } else {
+                self.poll_interval
+            };
+        }
+
+        self.current_interval = if self.catching_up {
+            (self.current_interval / 2).max(self.catch_up_interval)
+        } else {
+            (self.current_interval * 2).min(self.poll_interval)
+        };
+        self.current_interval
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{ListenerError, RetryPolicy, Runtime};
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::StatusCode;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) type RetryHandler = Arc<dyn Fn(u32, &ListenerError) + Send + Sync>;
+
+// How sinks that POST to HTTP endpoints time out and retry their requests.
+// `on_retry` is told about every failed attempt that is retried.
+#[derive(Clone)]
+pub(crate) struct HttpRetry {
+    pub(crate) policy: RetryPolicy,
+    pub(crate) timeout: Duration,
+    pub(crate) runtime: Option<Arc<dyn Runtime>>,
+    pub(crate) on_retry: Option<RetryHandler>,
+}
+
+impl fmt::Debug for HttpRetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpRetry")
+            .field("policy", &self.policy)
+            .field("timeout", &self.timeout)
+            .field("runtime", &self.runtime)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum PostError {
+    Transport(reqwest::Error),
+    Status(StatusCode),
+}
+
+impl PostError {
+    // Transport failures, rate limiting and server errors are worth retrying.
+    fn is_retryable(&self) -> bool {
+        match self {
+            PostError::Transport(_) => true,
+            PostError::Status(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+        }
+    }
+}
+
+// POSTs `body` as JSON until it is accepted or `retry` gives up. `error` turns
+// a failed attempt into the sink's error. Without a runtime, retries are sent
+// right away.
+pub(crate) async fn post_with_retry(
+    http: &reqwest::Client,
+    url: &str,
+    headers: HeaderMap,
+    body: &[u8],
+    retry: &HttpRetry,
+    error: impl Fn(PostError) -> ListenerError,
+) -> Result<(), ListenerError> {
+    let mut attempt = 1;
+    loop {
+        let response = http
+            .post(url)
+            .timeout(retry.timeout)
+            .header(CONTENT_TYPE, "application/json")
+            .headers(headers.clone())
+            .body(body.to_vec())
+            .send()
+            .await;
+        let failure = match response {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => PostError::Status(response.status()),
+            Err(err) => PostError::Transport(err),
+        };
+
+        let retryable = failure.is_retryable();
+        let err = error(failure);
+        if !retryable || attempt >= retry.policy.max_attempts() {
+            return Err(err);
+        }
+        if let Some(on_retry) = &retry.on_retry {
+            on_retry(attempt, &err);
+        }
+        if let Some(runtime) = &retry.runtime {
+            runtime.sleep(retry.policy.backoff(attempt)).await;
+        }
+        attempt += 1;
+    }
+}
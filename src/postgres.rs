@@ -0,0 +1,130 @@
+use crate::{EventEnvelope, EventSink, ListenerError, Runtime, TokioRuntime};
+use futures::future::BoxFuture;
+use tokio_postgres::{Client, NoTls, Statement};
+
+const DEFAULT_TABLE: &str = "near_events";
+
+// Inserts every event as a row of a table created on connect if missing. An
+// event is identified by its transaction hash, receipt ID and log index, so
+// writing it again, e.g. after a restart replayed its block, changes nothing.
+pub struct PostgresSink {
+    client: Client,
+    insert: Statement,
+}
+
+impl PostgresSink {
+    // `config` is a libpq-style connection string or URL, such as
+    // "host=localhost user=postgres" or "postgres://localhost/indexer". The
+    // connection is unencrypted; use `from_client` to connect over TLS.
+    pub async fn connect(config: &str) -> Result<Self, ListenerError> {
+        Self::connect_to_table(config, DEFAULT_TABLE).await
+    }
+
+    pub async fn connect_to_table(config: &str, table: &str) -> Result<Self, ListenerError> {
+        let (client, connection) = tokio_postgres::connect(config, NoTls)
+            .await
+            .map_err(to_sink_error)?;
+        // Drives the connection; once it fails, queries fail with it.
+        TokioRuntime.spawn(Box::pin(async move {
+            let _ = connection.await;
+        }));
+        Self::from_client(client, table).await
+    }
+
+    // `table` may be schema-qualified, e.g. "indexer.events".
+    pub async fn from_client(client: Client, table: &str) -> Result<Self, ListenerError> {
+        validate_table_name(table)?;
+        client
+            .batch_execute(&schema(table))
+            .await
+            .map_err(to_sink_error)?;
+        let insert = client
+            .prepare(&format!(
+                "INSERT INTO {} (block_height, block_hash, block_timestamp, tx_hash, receipt_id,
+                    log_index, account_id, standard, version, event, data)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                ON CONFLICT DO NOTHING",
+                table
+            ))
+            .await
+            .map_err(to_sink_error)?;
+
+        Ok(Self { client, insert })
+    }
+}
+
+impl EventSink for PostgresSink {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        Box::pin(async move {
+            let context = &envelope.context;
+            let event = &envelope.event;
+            self.client
+                .execute(
+                    &self.insert,
+                    &[
+                        &(context.block_height as i64),
+                        &context.block_hash,
+                        &(context.block_timestamp as i64),
+                        &context.tx_hash,
+                        &context.receipt_id,
+                        &(context.log_index as i32),
+                        &context.account_id,
+                        &event.standard,
+                        &event.version,
+                        &event.event,
+                        &event.data,
+                    ],
+                )
+                .await
+                .map(|_| ())
+                .map_err(to_sink_error)
+        })
+    }
+}
+
+// A missing transaction hash or receipt ID counts as empty in the event key,
+// since NULLs never conflict in a unique index.
+fn schema(table: &str) -> String {
+    let name = table.rsplit('.').next().unwrap_or(table);
+    format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id BIGSERIAL PRIMARY KEY,
+            block_height BIGINT NOT NULL,
+            block_hash TEXT NOT NULL,
+            block_timestamp BIGINT NOT NULL,
+            tx_hash TEXT,
+            receipt_id TEXT,
+            log_index INTEGER NOT NULL,
+            account_id TEXT NOT NULL,
+            standard TEXT NOT NULL,
+            version TEXT NOT NULL,
+            event TEXT NOT NULL,
+            data JSONB NOT NULL
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS {name}_event_key
+            ON {table} (COALESCE(tx_hash, ''), COALESCE(receipt_id, ''), log_index);
+        CREATE INDEX IF NOT EXISTS {name}_block_height ON {table} (block_height);"
+    )
+}
+
+// The name ends up in SQL as is, so only plain identifiers are accepted.
+fn validate_table_name(table: &str) -> Result<(), ListenerError> {
+    let is_identifier = |part: &str| {
+        part.chars()
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let parts: Vec<&str> = table.split('.').collect();
+    if parts.len() > 2 || !parts.iter().all(|part| is_identifier(part)) {
+        return Err(ListenerError::InvalidConfig(format!(
+            "invalid table name `{}`",
+            table
+        )));
+    }
+    Ok(())
+}
+
+fn to_sink_error(err: tokio_postgres::Error) -> ListenerError {
+    ListenerError::SinkError(err.to_string())
+}
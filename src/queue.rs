@@ -0,0 +1,109 @@
+use crate::{EventEnvelope, ListenerError};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+// What polling does when the delivery queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // Polling waits until the callback has made room.
+    #[default]
+    Block,
+    DropOldest,
+    DropNewest,
+    Error,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    events: VecDeque<EventEnvelope>,
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct DeliveryQueue {
+    capacity: usize,
+    policy: OverflowPolicy,
+    state: Mutex<QueueState>,
+    pushed: Notify,
+    popped: Notify,
+}
+
+// Hands events from the polling loop to a callback running in its own task.
+// The queue closes when the sender is dropped; the receiver still gets the
+// events queued until then.
+pub(crate) fn delivery_queue(
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> (QueueSender, QueueReceiver) {
+    let queue = Arc::new(DeliveryQueue {
+        capacity: capacity.max(1),
+        policy,
+        state: Mutex::default(),
+        pushed: Notify::new(),
+        popped: Notify::new(),
+    });
+
+    (QueueSender(queue.clone()), QueueReceiver(queue))
+}
+
+#[derive(Debug)]
+pub(crate) struct QueueSender(Arc<DeliveryQueue>);
+
+impl QueueSender {
+    pub(crate) async fn push(&self, envelope: EventEnvelope) -> Result<(), ListenerError> {
+        let queue = &self.0;
+        loop {
+            {
+                let mut state = queue.state.lock().unwrap();
+                if state.events.len() < queue.capacity {
+                    state.events.push_back(envelope);
+                    queue.pushed.notify_one();
+                    return Ok(());
+                }
+                match queue.policy {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::DropOldest => {
+                        state.events.pop_front();
+                        state.events.push_back(envelope);
+                        return Ok(());
+                    }
+                    OverflowPolicy::DropNewest => return Ok(()),
+                    OverflowPolicy::Error => {
+                        return Err(ListenerError::QueueFull(queue.capacity));
+                    }
+                }
+            }
+            queue.popped.notified().await;
+        }
+    }
+}
+
+impl Drop for QueueSender {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().closed = true;
+        self.0.pushed.notify_one();
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct QueueReceiver(Arc<DeliveryQueue>);
+
+impl QueueReceiver {
+    pub(crate) async fn pop(&self) -> Option<EventEnvelope> {
+        let queue = &self.0;
+        loop {
+            {
+                let mut state = queue.state.lock().unwrap();
+                if let Some(envelope) = state.events.pop_front() {
+                    queue.popped.notify_one();
+                    return Some(envelope);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            queue.pushed.notified().await;
+        }
+    }
+}
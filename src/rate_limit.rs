@@ -0,0 +1,49 @@
+use crate::Runtime;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+// Token bucket shared by every RPC call of a listener. Callers that find the
+// bucket empty wait their turn instead of failing.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    rate: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(max_rps: u32) -> Self {
+        let rate = f64::from(max_rps.max(1));
+        Self {
+            rate,
+            bucket: Mutex::new(Bucket {
+                tokens: rate,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    pub(crate) async fn acquire(&self, runtime: &dyn Runtime) {
+        // Holding the lock while sleeping keeps waiting callers in FIFO order.
+        let mut bucket = self.bucket.lock().await;
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.rate);
+        bucket.refilled_at = now;
+
+        if bucket.tokens < 1.0 {
+            let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate);
+            runtime.sleep(wait).await;
+            bucket.tokens = 1.0;
+            bucket.refilled_at = Instant::now();
+        }
+
+        bucket.tokens -= 1.0;
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{EventLog, ListenerError};
+use semver::{Version, VersionReq};
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+type Decoder<T> = Box<dyn Fn(&EventLog) -> Result<T, ListenerError> + Send + Sync>;
+
+struct Registration<T> {
+    standard: String,
+    event: String,
+    versions: VersionReq,
+    decoder: Decoder<T>,
+}
+
+// Typed decoders keyed on standard, event and version range. When a contract
+// bumps an event version, the schema for the new version is registered next to
+// the old one; each log is decoded by the first registration covering it.
+pub struct EventRegistry<T> {
+    registrations: Vec<Registration<T>>,
+}
+
+impl<T> Default for EventRegistry<T> {
+    fn default() -> Self {
+        Self {
+            registrations: Vec::new(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for EventRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.registrations.iter().map(|registration| {
+                format!(
+                    "{}/{} {}",
+                    registration.standard, registration.event, registration.versions
+                )
+            }))
+            .finish()
+    }
+}
+
+impl<T: 'static> EventRegistry<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `versions` is a semver requirement such as "^1.0" or ">=2.0.0, <3.0.0".
+    pub fn register<D>(
+        &mut self,
+        standard: &str,
+        event: &str,
+        versions: &str,
+    ) -> Result<&mut Self, ListenerError>
+    where
+        D: DeserializeOwned + Into<T> + 'static,
+    {
+        let versions = VersionReq::parse(versions).map_err(|e| {
+            ListenerError::InvalidConfig(format!("invalid version range `{}`: {}", versions, e))
+        })?;
+        self.registrations.push(Registration {
+            standard: standard.to_string(),
+            event: event.to_string(),
+            versions,
+            decoder: Box::new(|event_log| event_log.parse_data::<D>().map(Into::into)),
+        });
+        Ok(self)
+    }
+
+    pub fn matches(&self, event_log: &EventLog) -> bool {
+        self.find(event_log).is_some()
+    }
+
+    // `None` when no registration covers the event, including events whose
+    // version is not a semantic version.
+    pub fn decode(&self, event_log: &EventLog) -> Option<Result<T, ListenerError>> {
+        self.find(event_log)
+            .map(|registration| (registration.decoder)(event_log))
+    }
+
+    fn find(&self, event_log: &EventLog) -> Option<&Registration<T>> {
+        let version = Version::parse(&event_log.version).ok()?;
+        self.registrations.iter().find(|registration| {
+            registration.standard == event_log.standard
+                && registration.event == event_log.event
+                && registration.versions.matches(&version)
+        })
+    }
+}
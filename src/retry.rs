@@ -0,0 +1,103 @@
+use crate::error::is_timeout;
+use near_jsonrpc_client::errors::{
+    JsonRpcError, JsonRpcServerError, JsonRpcServerResponseStatusError, JsonRpcTransportRecvError,
+    JsonRpcTransportSendError, RpcTransportError,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            jitter: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+
+        if !self.jitter {
+            return backoff;
+        }
+
+        // Equal jitter: pick a point in [backoff / 2, backoff] so that many
+        // listeners failing at once do not retry in lockstep.
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos();
+        let half = backoff / 2;
+        half + half.mul_f64(f64::from(seed % 1000) / 1000.0)
+    }
+
+    pub(crate) fn should_retry<E>(&self, attempt: u32, err: &JsonRpcError<E>) -> bool {
+        attempt < self.max_attempts && is_retryable(err)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+pub(crate) fn is_retryable<E>(err: &JsonRpcError<E>) -> bool {
+    match err {
+        JsonRpcError::TransportError(err) if is_timeout(err) => true,
+        JsonRpcError::TransportError(RpcTransportError::SendError(
+            JsonRpcTransportSendError::PayloadSendError(_),
+        )) => true,
+        JsonRpcError::TransportError(RpcTransportError::RecvError(
+            JsonRpcTransportRecvError::PayloadRecvError(_),
+        )) => true,
+        JsonRpcError::ServerError(JsonRpcServerError::InternalError { .. }) => true,
+        JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(status)) => {
+            match status {
+                JsonRpcServerResponseStatusError::TooManyRequests => true,
+                JsonRpcServerResponseStatusError::Unexpected { status } => status.is_server_error(),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,120 @@
+use futures::channel::oneshot::{self, Canceled};
+use futures::future::{self, AbortHandle, BoxFuture};
+use futures::FutureExt;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::thread;
+use std::time::Duration;
+
+// The executor services the listener needs for its own timers and background
+// tasks: poll intervals, retry backoff, rate limiting, the delivery queue and
+// the tasks behind `channel` and `broadcast`. Implement it to run the listener
+// under async-std or a custom executor.
+pub trait Runtime: Send + Sync + 'static {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    // Runs `task` in the background. The listener keeps track of the task's
+    // completion itself, so no handle is returned.
+    fn spawn(&self, task: BoxFuture<'static, ()>);
+}
+
+impl fmt::Debug for dyn Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Runtime")
+    }
+}
+
+// The default runtime, behind the `tokio-runtime` feature. Needs to be used
+// from within a tokio runtime.
+#[cfg(feature = "tokio-runtime")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio-runtime")]
+impl Runtime for TokioRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        tokio::time::sleep(duration).boxed()
+    }
+
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        tokio::spawn(task);
+    }
+}
+
+// The runtime used when none is set: `TokioRuntime` if the feature is
+// enabled.
+pub(crate) fn default_runtime() -> Option<Arc<dyn Runtime>> {
+    #[cfg(feature = "tokio-runtime")]
+    return Some(Arc::new(TokioRuntime));
+    #[cfg(not(feature = "tokio-runtime"))]
+    None
+}
+
+// A task started on a `Runtime`. Awaiting it returns the task's output, or
+// `Canceled` if it was aborted, and resumes a panic of the task. Dropping the
+// handle leaves the task running.
+pub struct TaskHandle<T> {
+    output: oneshot::Receiver<thread::Result<T>>,
+    abort: AbortHandle,
+}
+
+impl<T: Send + 'static> TaskHandle<T> {
+    pub(crate) fn spawn<F>(runtime: &dyn Runtime, task: F) -> Self
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let (sender, output) = oneshot::channel();
+        let (task, abort) = future::abortable(AssertUnwindSafe(task).catch_unwind());
+        runtime.spawn(Box::pin(async move {
+            if let Ok(result) = task.await {
+                let _ = sender.send(result);
+            }
+        }));
+        Self { output, abort }
+    }
+
+    // A handle to a task that already finished with `output`.
+    pub(crate) fn ready(output: T) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        let _ = sender.send(Ok(output));
+        Self {
+            output: receiver,
+            abort: AbortHandle::new_pair().0,
+        }
+    }
+}
+
+impl<T> TaskHandle<T> {
+    // Stops the task at its next await point.
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    pub(crate) fn abort_handle(&self) -> AbortHandle {
+        self.abort.clone()
+    }
+}
+
+impl<T> Future for TaskHandle<T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match ready!(self.output.poll_unpin(cx)) {
+            Ok(Ok(output)) => Poll::Ready(Ok(output)),
+            Ok(Err(payload)) => panic::resume_unwind(payload),
+            Err(canceled) => Poll::Ready(Err(canceled)),
+        }
+    }
+}
+
+impl<T> fmt::Debug for TaskHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskHandle")
+            .field("aborted", &self.abort.is_aborted())
+            .finish_non_exhaustive()
+    }
+}
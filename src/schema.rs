@@ -0,0 +1,99 @@
+use crate::{EventLog, ListenerError};
+use jsonschema::Validator;
+use semver::{Version, VersionReq};
+use serde_json::Value;
+use std::fmt;
+
+struct SchemaRegistration {
+    standard: String,
+    event: String,
+    versions: VersionReq,
+    validator: Validator,
+}
+
+// JSON Schemas for the `data` of events, keyed on standard, event and version
+// range like `EventRegistry`. Events whose data does not match their schema are
+// handled like malformed events, so a contract changing its event layout shows
+// up in the dead letter sink instead of in consumers.
+#[derive(Default)]
+pub struct SchemaRegistry {
+    registrations: Vec<SchemaRegistration>,
+}
+
+impl fmt::Debug for SchemaRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.registrations.iter().map(|registration| {
+                format!(
+                    "{}/{} {}",
+                    registration.standard, registration.event, registration.versions
+                )
+            }))
+            .finish()
+    }
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `versions` is a semver requirement such as "^1.0" or ">=2.0.0, <3.0.0".
+    pub fn register(
+        &mut self,
+        standard: &str,
+        event: &str,
+        versions: &str,
+        schema: &Value,
+    ) -> Result<&mut Self, ListenerError> {
+        let versions = VersionReq::parse(versions).map_err(|e| {
+            ListenerError::InvalidConfig(format!("invalid version range `{}`: {}", versions, e))
+        })?;
+        let validator = jsonschema::validator_for(schema).map_err(|e| {
+            ListenerError::InvalidConfig(format!(
+                "invalid schema for {}/{}: {}",
+                standard, event, e
+            ))
+        })?;
+        self.registrations.push(SchemaRegistration {
+            standard: standard.to_string(),
+            event: event.to_string(),
+            versions,
+            validator,
+        });
+        Ok(self)
+    }
+
+    // Events without a registered schema, including events whose version is
+    // not a semantic version, pass.
+    pub fn validate(&self, event_log: &EventLog) -> Result<(), ListenerError> {
+        let Some(registration) = self.find(event_log) else {
+            return Ok(());
+        };
+
+        let errors: Vec<String> = registration
+            .validator
+            .iter_errors(&event_log.data)
+            .map(|error| format!("{} at `{}`", error, error.instance_path))
+            .collect();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(ListenerError::SchemaViolation(format!(
+            "{}/{} {}: {}",
+            event_log.standard,
+            event_log.event,
+            event_log.version,
+            errors.join("; ")
+        )))
+    }
+
+    fn find(&self, event_log: &EventLog) -> Option<&SchemaRegistration> {
+        let version = Version::parse(&event_log.version).ok()?;
+        self.registrations.iter().find(|registration| {
+            registration.standard == event_log.standard
+                && registration.event == event_log.event
+                && registration.versions.matches(&version)
+        })
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    EventEnvelope, EventSink, ListenerError, ListenerHandle, ListenerState, TaskHandle,
+    TokioRuntime,
+};
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::{Json, Router};
+use futures::future::BoxFuture;
+use futures::stream::{self, Stream};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const DEFAULT_BUFFER: usize = 1024;
+
+// An embedded HTTP server for dashboards and browsers: `/events` streams every
+// event it is sent as Server-Sent Events, and `/status` reports the listener's
+// status as JSON. It is an `EventSink`, so `start_sink` feeds it.
+#[derive(Debug, Clone)]
+pub struct EventServer {
+    events: broadcast::Sender<EventEnvelope>,
+    handle: ListenerHandle,
+}
+
+impl EventServer {
+    pub fn new(handle: ListenerHandle) -> Self {
+        Self {
+            events: broadcast::channel(DEFAULT_BUFFER).0,
+            handle,
+        }
+    }
+
+    // How many events a slow client may fall behind by before it misses some;
+    // 1024 by default.
+    pub fn buffer(mut self, capacity: usize) -> Self {
+        self.events = broadcast::channel(capacity.max(1)).0;
+        self
+    }
+
+    // The routes, to be mounted into an existing axum application.
+    pub fn router(&self) -> Router {
+        let router = Router::new()
+            .route("/events", get(events))
+            .route("/status", get(status));
+        #[cfg(feature = "graphql")]
+        let router = router.route("/graphql", crate::graphql::route(self.clone()));
+        router.with_state(self.clone())
+    }
+
+    // Binds `addr` and serves the routes in the background. Returns the bound
+    // address, which tells the port when binding port 0.
+    pub async fn serve(
+        &self,
+        addr: impl ToSocketAddrs,
+    ) -> Result<(SocketAddr, TaskHandle<()>), ListenerError> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| ListenerError::SinkError(e.to_string()))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| ListenerError::SinkError(e.to_string()))?;
+        let router = self.router();
+        // axum serves on tokio's I/O, so this does not go through a custom
+        // runtime.
+        let task = TaskHandle::spawn(&TokioRuntime, async move {
+            let _ = axum::serve(listener, router).await;
+        });
+        Ok((local_addr, task))
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<EventEnvelope> {
+        self.events.subscribe()
+    }
+
+    pub(crate) fn handle(&self) -> &ListenerHandle {
+        &self.handle
+    }
+}
+
+impl EventSink for EventServer {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        // Nobody listening is not an error.
+        let _ = self.events.send(envelope.clone());
+        Box::pin(async { Ok(()) })
+    }
+}
+
+// Each event is named after the NEP-297 event and carries the envelope as
+// JSON; its ID is the event's sequence number when it has one.
+async fn events(
+    State(server): State<EventServer>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = server.subscribe();
+    let stream = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(envelope) => {
+                    let mut event = Event::default().event(&envelope.event.event);
+                    if let Some(sequence) = envelope.context.sequence {
+                        event = event.id(sequence.to_string());
+                    }
+                    let Ok(event) = event.json_data(&envelope) else {
+                        continue;
+                    };
+                    return Some((Ok(event), receiver));
+                }
+                // A client too slow to keep up skips what it missed.
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn status(State(server): State<EventServer>) -> Json<Value> {
+    let status = server.handle.status();
+    Json(json!({
+        "state": state_name(status.state),
+        "last_processed_block": status.last_processed_block,
+        "head_block": status.head_block,
+        "events_emitted": status.events_emitted,
+        "rpc_errors": status.rpc_errors,
+        "cache_hits": status.cache_hits,
+        "cache_misses": status.cache_misses,
+        "uptime_ms": status.uptime.as_millis() as u64,
+    }))
+}
+
+pub(crate) fn state_name(state: ListenerState) -> &'static str {
+    match state {
+        ListenerState::Running => "running",
+        ListenerState::CatchingUp => "catching_up",
+        ListenerState::Paused => "paused",
+        ListenerState::Stopped => "stopped",
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{EventEnvelope, ListenerError};
+use futures::future::BoxFuture;
+
+// Avro schema of the records written by `SinkFormat::Avro`. The event data is
+// kept as a JSON string, since its shape differs between standards.
+pub const ENVELOPE_AVRO_SCHEMA: &str = r#"{
+  "type": "record",
+  "name": "EventEnvelope",
+  "namespace": "near_event_listener",
+  "fields": [
+    {"name": "account_id", "type": "string"},
+    {"name": "block_height", "type": "long"},
+    {"name": "block_hash", "type": "string"},
+    {"name": "block_timestamp", "type": "long"},
+    {"name": "tx_hash", "type": ["null", "string"]},
+    {"name": "signer_id", "type": "string"},
+    {"name": "receiver_id", "type": "string"},
+    {"name": "receipt_id", "type": ["null", "string"]},
+    {"name": "log_index", "type": "long"},
+    {"name": "standard", "type": "string"},
+    {"name": "version", "type": "string"},
+    {"name": "event", "type": "string"},
+    {"name": "data", "type": "string"}
+  ]
+}"#;
+
+pub trait EventSink: Send + Sync {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>>;
+}
+
+// What a message is keyed by, for sinks that partition by key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SinkKey {
+    #[default]
+    AccountId,
+    EventName,
+    None,
+}
+
+impl SinkKey {
+    pub fn key(&self, envelope: &EventEnvelope) -> Option<String> {
+        match self {
+            SinkKey::AccountId => Some(envelope.context.account_id.clone()),
+            SinkKey::EventName => Some(envelope.event.event.clone()),
+            SinkKey::None => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SinkFormat {
+    #[default]
+    Json,
+    // A single Avro binary datum matching `ENVELOPE_AVRO_SCHEMA`, without any
+    // container or schema registry framing.
+    Avro,
+}
+
+impl SinkFormat {
+    pub fn encode(&self, envelope: &EventEnvelope) -> Result<Vec<u8>, ListenerError> {
+        match self {
+            SinkFormat::Json => Ok(serde_json::to_vec(envelope)?),
+            SinkFormat::Avro => {
+                let context = &envelope.context;
+                let event = &envelope.event;
+                let mut datum = Vec::new();
+
+                write_avro_string(&mut datum, &context.account_id);
+                write_avro_long(&mut datum, context.block_height as i64);
+                write_avro_string(&mut datum, &context.block_hash);
+                write_avro_long(&mut datum, context.block_timestamp as i64);
+                write_avro_optional_string(&mut datum, context.tx_hash.as_deref());
+                write_avro_string(&mut datum, &context.signer_id);
+                write_avro_string(&mut datum, &context.receiver_id);
+                write_avro_optional_string(&mut datum, context.receipt_id.as_deref());
+                write_avro_long(&mut datum, context.log_index as i64);
+                write_avro_string(&mut datum, &event.standard);
+                write_avro_string(&mut datum, &event.version);
+                write_avro_string(&mut datum, &event.event);
+                write_avro_string(&mut datum, &serde_json::to_string(&event.data)?);
+
+                Ok(datum)
+            }
+        }
+    }
+}
+
+// Expands `{account_id}`, `{standard}` and `{event}` in a topic or subject.
+pub fn render_topic(template: &str, envelope: &EventEnvelope) -> String {
+    template
+        .replace("{account_id}", &envelope.context.account_id)
+        .replace("{standard}", &envelope.event.standard)
+        .replace("{event}", &envelope.event.event)
+}
+
+fn write_avro_long(datum: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        datum.push((zigzag as u8) | 0x80);
+        zigzag >>= 7;
+    }
+    datum.push(zigzag as u8);
+}
+
+fn write_avro_string(datum: &mut Vec<u8>, value: &str) {
+    write_avro_long(datum, value.len() as i64);
+    datum.extend_from_slice(value.as_bytes());
+}
+
+fn write_avro_optional_string(datum: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        None => write_avro_long(datum, 0),
+        Some(value) => {
+            write_avro_long(datum, 1);
+            write_avro_string(datum, value);
+        }
+    }
+}
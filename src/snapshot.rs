@@ -0,0 +1,47 @@
+use crate::dedupe::EventKey;
+use crate::{DeliveryMode, EventFilter, ListenerError};
+use near_primitives::types::Finality;
+use serde::{Deserialize, Serialize};
+
+// What a listener needs to carry on elsewhere: its cursor, what it watches
+// and, in exactly-once mode, the events it delivered last. Callbacks, matchers,
+// subscriptions, sinks and stores cannot be serialized and are not part of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListenerSnapshot {
+    pub rpc_url: String,
+    #[serde(default)]
+    pub archival_rpc_url: Option<String>,
+    #[serde(default)]
+    pub account_ids: Vec<String>,
+    #[serde(default)]
+    pub account_patterns: Vec<String>,
+    #[serde(default)]
+    pub factories: Vec<String>,
+    #[serde(default)]
+    pub method_names: Vec<String>,
+    // Watched storage, as account ID and key prefix.
+    #[serde(default)]
+    pub storage_watches: Vec<(String, Vec<u8>)>,
+    #[serde(default)]
+    pub account_watches: Vec<String>,
+    #[serde(default)]
+    pub event_filter: EventFilter,
+    pub finality: Finality,
+    pub delivery: DeliveryMode,
+    pub dedupe_capacity: usize,
+    pub last_processed_block: u64,
+    pub sequence: u64,
+    // Keys of the events held by the exactly-once cache, oldest first.
+    #[serde(default)]
+    pub(crate) delivered: Vec<EventKey>,
+}
+
+impl ListenerSnapshot {
+    pub fn to_json(&self) -> Result<String, ListenerError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ListenerError> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
@@ -0,0 +1,18 @@
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DataSource {
+    #[default]
+    Rpc,
+    #[cfg(feature = "lake")]
+    Lake {
+        s3_bucket: String,
+        region: String,
+        start_block: u64,
+    },
+    // Blocks are still read over JSON-RPC, but polling is triggered by the
+    // subscription's messages instead of a timer while it is connected.
+    #[cfg(feature = "websocket")]
+    WebSocket {
+        url: String,
+        subscribe_message: Option<String>,
+    },
+}
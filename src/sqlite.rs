@@ -0,0 +1,306 @@
+use crate::{
+    CheckpointStore, DeliveredEvent, EventContext, EventEnvelope, EventLog, ListenerError,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS checkpoint (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        height INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        block_height INTEGER NOT NULL,
+        block_hash TEXT NOT NULL,
+        block_timestamp INTEGER NOT NULL,
+        tx_hash TEXT,
+        receipt_id TEXT,
+        log_index INTEGER NOT NULL DEFAULT 0,
+        account_id TEXT NOT NULL,
+        signer_id TEXT NOT NULL,
+        receiver_id TEXT NOT NULL,
+        standard TEXT NOT NULL,
+        version TEXT NOT NULL,
+        event TEXT NOT NULL,
+        payload TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS events_block_height ON events (block_height);
+    CREATE TABLE IF NOT EXISTS sequence (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        value INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS delivered (
+        block_height INTEGER NOT NULL,
+        tx_hash TEXT,
+        receipt_id TEXT,
+        log_index INTEGER NOT NULL
+    );
+";
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ListenerError> {
+        Self::init(Connection::open(path).map_err(to_checkpoint_error)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, ListenerError> {
+        Self::init(Connection::open_in_memory().map_err(to_checkpoint_error)?)
+    }
+
+    fn init(connection: Connection) -> Result<Self, ListenerError> {
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(to_checkpoint_error)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    pub fn events(&self) -> Result<Vec<EventEnvelope>, ListenerError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare(
+                "SELECT account_id, block_height, block_hash, block_timestamp, tx_hash,
+                        signer_id, receiver_id, receipt_id, log_index, standard, version, event,
+                        payload
+                 FROM events ORDER BY id",
+            )
+            .map_err(to_checkpoint_error)?;
+
+        let rows = statement
+            .query_map([], |row| {
+                let context = EventContext {
+                    account_id: row.get(0)?,
+                    block_height: row.get::<_, i64>(1)? as u64,
+                    block_hash: row.get(2)?,
+                    block_timestamp: row.get::<_, i64>(3)? as u64,
+                    tx_hash: row.get(4)?,
+                    signer_id: row.get(5)?,
+                    receiver_id: row.get(6)?,
+                    method_name: None,
+                    deposit: None,
+                    gas: None,
+                    execution_status: None,
+                    receipt_id: row.get(7)?,
+                    log_index: row.get::<_, i64>(8)? as usize,
+                    sequence: None,
+                    late: false,
+                    receipt: None,
+                    metrics: None,
+                };
+                Ok((
+                    context,
+                    row.get::<_, String>(9)?,
+                    row.get::<_, String>(10)?,
+                    row.get::<_, String>(11)?,
+                    row.get::<_, String>(12)?,
+                ))
+            })
+            .map_err(to_checkpoint_error)?;
+
+        let mut envelopes = Vec::new();
+        for row in rows {
+            let (context, standard, version, event, payload) = row.map_err(to_checkpoint_error)?;
+
+            envelopes.push(EventEnvelope {
+                context,
+                event: EventLog {
+                    standard,
+                    version,
+                    event,
+                    data: serde_json::from_str(&payload)?,
+                },
+            });
+        }
+
+        Ok(envelopes)
+    }
+
+    // Archives the events of a block and checkpoints it in one transaction.
+    fn write_block(
+        &self,
+        height: u64,
+        sequence: Option<u64>,
+        envelopes: &[EventEnvelope],
+    ) -> Result<(), ListenerError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(to_checkpoint_error)?;
+
+        for envelope in envelopes {
+            let context = &envelope.context;
+            transaction
+                .execute(
+                    "INSERT INTO events
+                     (block_height, block_hash, block_timestamp, tx_hash, receipt_id, log_index,
+                      account_id, signer_id, receiver_id, standard, version, event, payload)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    params![
+                        context.block_height as i64,
+                        context.block_hash,
+                        context.block_timestamp as i64,
+                        context.tx_hash,
+                        context.receipt_id,
+                        context.log_index as i64,
+                        context.account_id,
+                        context.signer_id,
+                        context.receiver_id,
+                        envelope.event.standard,
+                        envelope.event.version,
+                        envelope.event.event,
+                        serde_json::to_string(&envelope.event.data)?,
+                    ],
+                )
+                .map_err(to_checkpoint_error)?;
+        }
+
+        transaction
+            .execute(
+                "INSERT INTO checkpoint (id, height) VALUES (0, ?1)
+                 ON CONFLICT (id) DO UPDATE SET height = excluded.height",
+                params![height as i64],
+            )
+            .map_err(to_checkpoint_error)?;
+        transaction
+            .execute("DELETE FROM delivered", [])
+            .map_err(to_checkpoint_error)?;
+
+        if let Some(sequence) = sequence {
+            transaction
+                .execute(
+                    "INSERT INTO sequence (id, value) VALUES (0, ?1)
+                     ON CONFLICT (id) DO UPDATE SET value = excluded.value",
+                    params![sequence as i64],
+                )
+                .map_err(to_checkpoint_error)?;
+        }
+
+        transaction.commit().map_err(to_checkpoint_error)
+    }
+}
+
+impl CheckpointStore for SqliteStore {
+    fn load(&self) -> Result<Option<u64>, ListenerError> {
+        let connection = self.connection.lock().unwrap();
+        let height = connection
+            .query_row("SELECT height FROM checkpoint WHERE id = 0", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .optional()
+            .map_err(to_checkpoint_error)?;
+
+        Ok(height.map(|height| height as u64))
+    }
+
+    fn save(&self, height: u64) -> Result<(), ListenerError> {
+        self.save_block(height, &[])
+    }
+
+    fn save_block(&self, height: u64, envelopes: &[EventEnvelope]) -> Result<(), ListenerError> {
+        self.write_block(height, None, envelopes)
+    }
+
+    fn load_sequence(&self) -> Result<Option<u64>, ListenerError> {
+        let connection = self.connection.lock().unwrap();
+        let sequence = connection
+            .query_row("SELECT value FROM sequence WHERE id = 0", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .optional()
+            .map_err(to_checkpoint_error)?;
+
+        Ok(sequence.map(|sequence| sequence as u64))
+    }
+
+    fn save_progress(
+        &self,
+        height: u64,
+        sequence: u64,
+        envelopes: &[EventEnvelope],
+    ) -> Result<(), ListenerError> {
+        self.write_block(height, Some(sequence), envelopes)
+    }
+
+    fn save_delivered(&self, height: u64, event: &DeliveredEvent) -> Result<(), ListenerError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO delivered (block_height, tx_hash, receipt_id, log_index)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    height as i64,
+                    event.tx_hash,
+                    event.receipt_id,
+                    event.log_index as i64
+                ],
+            )
+            .map(|_| ())
+            .map_err(to_checkpoint_error)
+    }
+
+    fn load_delivered(&self) -> Result<Vec<DeliveredEvent>, ListenerError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT tx_hash, receipt_id, log_index FROM delivered")
+            .map_err(to_checkpoint_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok(DeliveredEvent {
+                    tx_hash: row.get(0)?,
+                    receipt_id: row.get(1)?,
+                    log_index: row.get::<_, i64>(2)? as usize,
+                })
+            })
+            .map_err(to_checkpoint_error)?;
+
+        rows.collect::<Result<_, _>>().map_err(to_checkpoint_error)
+    }
+
+    // The checkpoint only moves once a block is done, so the events delivered
+    // of another block than `height` are stale.
+    fn begin_block(&self, height: u64) -> Result<(), ListenerError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "DELETE FROM delivered WHERE block_height != ?1",
+                params![height as i64],
+            )
+            .map(|_| ())
+            .map_err(to_checkpoint_error)
+    }
+
+    fn revert_to(&self, height: u64) -> Result<(), ListenerError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(to_checkpoint_error)?;
+
+        transaction
+            .execute("DELETE FROM delivered", [])
+            .map_err(to_checkpoint_error)?;
+
+        transaction
+            .execute(
+                "DELETE FROM events WHERE block_height > ?1",
+                params![height as i64],
+            )
+            .map_err(to_checkpoint_error)?;
+        transaction
+            .execute(
+                "INSERT INTO checkpoint (id, height) VALUES (0, ?1)
+                 ON CONFLICT (id) DO UPDATE SET height = excluded.height",
+                params![height as i64],
+            )
+            .map_err(to_checkpoint_error)?;
+
+        transaction.commit().map_err(to_checkpoint_error)
+    }
+}
+
+fn to_checkpoint_error(err: rusqlite::Error) -> ListenerError {
+    ListenerError::CheckpointError(err.to_string())
+}
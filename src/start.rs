@@ -0,0 +1,18 @@
+use std::time::SystemTime;
+
+// Where a listener begins reading blocks. `Checkpoint` resumes after the
+// height loaded from the checkpoint store or set with `last_processed_block`,
+// and starts from the latest block when there is neither. `Transaction` starts
+// at the block that included the transaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum StartPosition {
+    #[default]
+    Checkpoint,
+    Latest,
+    Height(u64),
+    Timestamp(SystemTime),
+    Transaction {
+        tx_hash: String,
+        signer_id: String,
+    },
+}
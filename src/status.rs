@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerState {
+    Running,
+    // Processing blocks that are well behind the chain head.
+    CatchingUp,
+    Paused,
+    Stopped,
+}
+
+// A snapshot of what a listener is doing, e.g. for a health endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenerStatus {
+    pub last_processed_block: u64,
+    pub head_block: u64,
+    pub events_emitted: usize,
+    // Failed RPC requests, counting retried attempts. Errors reported by the
+    // node about a specific request, such as an unknown block, are not included.
+    pub rpc_errors: u64,
+    // Lookups in the block and chunk cache; both stay zero without one.
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    // Time since the listener started polling; zero while it is stopped.
+    pub uptime: Duration,
+    pub state: ListenerState,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StatusTracker {
+    last_processed_block: AtomicU64,
+    head_block: AtomicU64,
+    events_emitted: AtomicUsize,
+    rpc_errors: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    catching_up: AtomicBool,
+    started_at: Mutex<Option<Instant>>,
+}
+
+impl StatusTracker {
+    pub(crate) fn update(
+        &self,
+        last_processed_block: u64,
+        head_block: u64,
+        events_emitted: usize,
+        catching_up: bool,
+    ) {
+        self.last_processed_block
+            .store(last_processed_block, Ordering::Relaxed);
+        self.head_block.store(head_block, Ordering::Relaxed);
+        self.events_emitted.store(events_emitted, Ordering::Relaxed);
+        self.catching_up.store(catching_up, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_rpc_error(&self) {
+        self.rpc_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, paused: bool) -> ListenerStatus {
+        let started_at = *self.started_at.lock().unwrap();
+        let state = match started_at {
+            None => ListenerState::Stopped,
+            Some(_) if paused => ListenerState::Paused,
+            Some(_) if self.catching_up.load(Ordering::Relaxed) => ListenerState::CatchingUp,
+            Some(_) => ListenerState::Running,
+        };
+
+        ListenerStatus {
+            last_processed_block: self.last_processed_block.load(Ordering::Relaxed),
+            head_block: self.head_block.load(Ordering::Relaxed),
+            events_emitted: self.events_emitted.load(Ordering::Relaxed),
+            rpc_errors: self.rpc_errors.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            uptime: started_at.map_or(Duration::ZERO, |started_at| started_at.elapsed()),
+            state,
+        }
+    }
+}
+
+// Marks the listener as running for as long as it is alive.
+pub(crate) struct RunGuard(Arc<StatusTracker>);
+
+impl RunGuard {
+    pub(crate) fn new(tracker: Arc<StatusTracker>) -> Self {
+        tracker
+            .started_at
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        Self(tracker)
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        *self.0.started_at.lock().unwrap() = None;
+    }
+}
@@ -0,0 +1,130 @@
+use crate::filter::glob_matches;
+use crate::{EventEnvelope, EventFilter, EventJsonDecoder, EventLog, LogDecoder};
+use std::fmt;
+
+type SubscriptionHandler = Box<dyn Fn(EventEnvelope) + Send + Sync>;
+
+// Identifies a subscription added through a `ListenerHandle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(pub(crate) u64);
+
+// An extra set of accounts, methods and event filter with its own callback.
+// All subscriptions of a listener share its block and chunk fetches.
+pub struct Subscription {
+    pub(crate) account_ids: Vec<String>,
+    account_patterns: Vec<String>,
+    method_names: Vec<String>,
+    event_filter: EventFilter,
+    handler: SubscriptionHandler,
+    log_decoder: Option<Box<dyn LogDecoder>>,
+    pub(crate) id: Option<SubscriptionId>,
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("account_ids", &self.account_ids)
+            .field("account_patterns", &self.account_patterns)
+            .field("method_names", &self.method_names)
+            .field("event_filter", &self.event_filter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Subscription {
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(EventEnvelope) + Send + Sync + 'static,
+    {
+        Self {
+            account_ids: Vec::new(),
+            account_patterns: Vec::new(),
+            method_names: Vec::new(),
+            event_filter: EventFilter::default(),
+            handler: Box::new(handler),
+            log_decoder: None,
+            id: None,
+        }
+    }
+
+    pub fn account_id(mut self, account_id: &str) -> Self {
+        self.account_ids.push(account_id.to_string());
+        self
+    }
+
+    pub fn account_pattern(mut self, pattern: &str) -> Self {
+        self.account_patterns.push(pattern.to_string());
+        self
+    }
+
+    pub fn method_name(mut self, method_name: &str) -> Self {
+        self.method_names.push(method_name.to_string());
+        self
+    }
+
+    pub fn event_filter(mut self, filter: EventFilter) -> Self {
+        self.event_filter = filter;
+        self
+    }
+
+    // Decodes the logs of this subscription's calls, ahead of the listener's
+    // own decoder, e.g. `BorshLogDecoder` for a contract that does not log
+    // `EVENT_JSON:`.
+    pub fn log_decoder<D>(mut self, decoder: D) -> Self
+    where
+        D: LogDecoder + 'static,
+    {
+        self.log_decoder = Some(Box::new(decoder));
+        self
+    }
+
+    // Reads this subscription's events after any of `prefixes`, for contracts
+    // logging NEP-297 JSON under a variant convention.
+    pub fn log_prefixes(self, prefixes: &[&str]) -> Self {
+        self.log_decoder(EventJsonDecoder::with_prefixes(prefixes))
+    }
+
+    pub(crate) fn decoder(&self) -> Option<&dyn LogDecoder> {
+        self.log_decoder.as_deref()
+    }
+
+    #[cfg(feature = "lake")]
+    pub(crate) fn has_account_patterns(&self) -> bool {
+        !self.account_patterns.is_empty()
+    }
+
+    pub(crate) fn has_accounts(&self) -> bool {
+        !self.account_ids.is_empty() || !self.account_patterns.is_empty()
+    }
+
+    pub(crate) fn matches_account(&self, account_id: &str) -> bool {
+        self.account_ids.iter().any(|watched| watched == account_id)
+            || self
+                .account_patterns
+                .iter()
+                .any(|pattern| glob_matches(pattern, account_id))
+    }
+
+    pub(crate) fn matches_call(&self, account_id: &str, method_name: &str) -> bool {
+        self.matches_account(account_id)
+            && (self.method_names.is_empty()
+                || self
+                    .method_names
+                    .iter()
+                    .any(|pattern| glob_matches(pattern, method_name)))
+    }
+
+    pub(crate) fn matches_event(&self, event_log: &EventLog) -> bool {
+        self.event_filter.matches(event_log)
+    }
+
+    pub(crate) fn deliver(&self, envelope: &EventEnvelope) {
+        let context = &envelope.context;
+        let method_name = context.method_name.as_deref().unwrap_or_default();
+        if self.matches_call(&context.receiver_id, method_name)
+            && self.matches_event(&envelope.event)
+        {
+            (self.handler)(envelope.clone());
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{EventContext, EventEnvelope, ListenerError};
+use minijinja::Environment;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+const NAME: &str = "event";
+
+// What a template sees: the fields of the event context such as `account_id`,
+// `block_height` and `tx_hash`, the event's `standard`, `version`, `event` and
+// `data`, and the whole `envelope`.
+#[derive(Serialize)]
+struct TemplateContext<'a> {
+    #[serde(flatten)]
+    context: &'a EventContext,
+    standard: &'a str,
+    version: &'a str,
+    event: &'a str,
+    data: &'a Value,
+    envelope: &'a EventEnvelope,
+}
+
+// A Jinja template (minijinja) rendering an event to text, shared by the
+// sinks and the CLI that format events, e.g.
+// "{{ data[0].owner_id }} minted {{ data[0].token_ids | join(', ') }}".
+// Undefined variables render as empty, and nothing is escaped. Clones share
+// the compiled template.
+#[derive(Clone)]
+pub struct EventTemplate {
+    env: Arc<Environment<'static>>,
+}
+
+impl fmt::Debug for EventTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_tuple("EventTemplate");
+        if let Ok(template) = self.env.get_template(NAME) {
+            debug.field(&template.source());
+        }
+        debug.finish()
+    }
+}
+
+impl EventTemplate {
+    // Fails when `source` is not a valid template.
+    pub fn new(source: &str) -> Result<Self, ListenerError> {
+        let mut env = Environment::new();
+        env.add_template_owned(NAME, source.to_string())
+            .map_err(|e| ListenerError::InvalidConfig(format!("invalid template: {:#}", e)))?;
+        Ok(Self { env: Arc::new(env) })
+    }
+
+    pub fn render(&self, envelope: &EventEnvelope) -> Result<String, ListenerError> {
+        let template = self
+            .env
+            .get_template(NAME)
+            .map_err(|e| ListenerError::TemplateError(e.to_string()))?;
+        template
+            .render(TemplateContext {
+                context: &envelope.context,
+                standard: &envelope.event.standard,
+                version: &envelope.event.version,
+                event: &envelope.event.event,
+                data: &envelope.event.data,
+                envelope,
+            })
+            .map_err(|e| ListenerError::TemplateError(format!("{:#}", e)))
+    }
+}
@@ -0,0 +1,1262 @@
+use crate::EventLog;
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::types::blocks::RpcBlockError;
+use near_jsonrpc_primitives::types::changes::{
+    RpcStateChangesError, RpcStateChangesInBlockByTypeRequest,
+};
+use near_jsonrpc_primitives::types::chunks::RpcChunkError;
+use near_jsonrpc_primitives::types::light_client::RpcLightClientProofError;
+use near_jsonrpc_primitives::types::query::RpcQueryError;
+use near_jsonrpc_primitives::types::receipts::RpcReceiptError;
+use near_jsonrpc_primitives::types::transactions::RpcTransactionError;
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::{BlockId, BlockReference};
+use near_primitives::views::{
+    BlockView, ChunkView, ExecutionOutcomeWithIdView, FinalExecutionOutcomeView, QueryRequest,
+    ReceiptView, StateChangeCauseView, StateChangeValueView, StateChangeWithCauseView,
+    StateChangesRequestView,
+};
+use near_sdk::AccountId;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+const AUTHOR: &str = "test.near";
+const ZERO_PUBLIC_KEY: &str = "ed25519:11111111111111111111111111111111";
+const ZERO_SIGNATURE: &str =
+    "ed25519:1111111111111111111111111111111111111111111111111111111111111111";
+// Fixture blocks are dated well in the past so the listener treats them as
+// history and does not wait for outcomes to settle.
+const GENESIS_TIMESTAMP: u64 = 1_600_000_000_000_000_000;
+const BLOCK_TIME: u64 = 1_000_000_000;
+// The minimum gas price, in yoctoNEAR per gas unit.
+const GAS_PRICE: u128 = 100_000_000;
+
+#[derive(Debug, Clone)]
+pub struct MockFunctionCall {
+    signer_id: AccountId,
+    receiver_id: AccountId,
+    method_name: String,
+    logs: Vec<String>,
+    callees: Vec<(AccountId, String, Vec<String>)>,
+    relayer_id: Option<AccountId>,
+    deposit: u128,
+    gas_burnt: u64,
+    transfer: bool,
+    failed: bool,
+}
+
+impl MockFunctionCall {
+    pub fn new(signer_id: &str, receiver_id: &str, method_name: &str) -> Self {
+        Self {
+            signer_id: parse_account_id(signer_id),
+            receiver_id: parse_account_id(receiver_id),
+            method_name: method_name.to_string(),
+            logs: Vec::new(),
+            callees: Vec::new(),
+            relayer_id: None,
+            deposit: 0,
+            gas_burnt: 0,
+            transfer: false,
+            failed: false,
+        }
+    }
+
+    // A plain transfer of `amount` yoctoNEAR instead of a function call.
+    pub fn transfer(signer_id: &str, receiver_id: &str, amount: u128) -> Self {
+        Self {
+            transfer: true,
+            deposit: amount,
+            ..Self::new(signer_id, receiver_id, "")
+        }
+    }
+
+    // Attaches `deposit` yoctoNEAR to the call.
+    pub fn deposit(mut self, deposit: u128) -> Self {
+        self.deposit = deposit;
+        self
+    }
+
+    // Makes executing the call burn `gas`, paid at the minimum gas price.
+    pub fn gas_burnt(mut self, gas: u64) -> Self {
+        self.gas_burnt = gas;
+        self
+    }
+
+    // Makes the call panic after emitting its logs, failing the transaction.
+    pub fn failed(mut self) -> Self {
+        self.failed = true;
+        self
+    }
+
+    pub fn log(mut self, log: &str) -> Self {
+        self.logs.push(log.to_string());
+        self
+    }
+
+    pub fn event(mut self, event: &EventLog) -> Self {
+        self.logs.push(event_json(event));
+        self
+    }
+
+    // Makes the call invoke `method_name` on another contract, whose receipt
+    // emits `event`. The receipt only shows up in the transaction outcome, not
+    // in a chunk of its own.
+    pub fn cross_contract_event(
+        mut self,
+        receiver_id: &str,
+        method_name: &str,
+        event: &EventLog,
+    ) -> Self {
+        self.callees.push((
+            parse_account_id(receiver_id),
+            method_name.to_string(),
+            vec![event_json(event)],
+        ));
+        self
+    }
+
+    // Submits the call as a meta-transaction: `relayer_id` signs a transaction
+    // carrying the call as a delegate action of the original signer.
+    pub fn relayed_by(mut self, relayer_id: &str) -> Self {
+        self.relayer_id = Some(parse_account_id(relayer_id));
+        self
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    blocks: BTreeMap<u64, BlockView>,
+    chunks: HashMap<CryptoHash, ChunkView>,
+    transactions: HashMap<CryptoHash, FinalExecutionOutcomeView>,
+    receipts: HashMap<CryptoHash, ReceiptView>,
+    receipt_outcomes: HashMap<CryptoHash, ExecutionOutcomeWithIdView>,
+    state_changes: BTreeMap<u64, Vec<StateChangeWithCauseView>>,
+    requests: HashMap<String, usize>,
+    syncing: bool,
+    head_age: Duration,
+    response_delay: Duration,
+}
+
+// A fake JSON-RPC node serving canned blocks, chunks and outcomes over HTTP on
+// a local port. Point a listener at `url()` to exercise it end to end.
+pub struct MockBlockSource {
+    url: String,
+    state: Arc<Mutex<MockState>>,
+    server: JoinHandle<()>,
+}
+
+impl MockBlockSource {
+    pub async fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let mock_state = state.clone();
+        let server = tokio::spawn(serve(listener, move |body| {
+            let state = mock_state.clone();
+            async move {
+                let delay = state.lock().unwrap().response_delay;
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                respond(&state, &body)
+            }
+        }));
+
+        Ok(Self { url, state, server })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    // How many requests for the JSON-RPC `method` were served so far.
+    pub fn request_count(&self, method: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        state.requests.get(method).copied().unwrap_or_default()
+    }
+
+    // Makes `status` report the node as syncing, or as synced again.
+    pub fn set_syncing(&self, syncing: bool) {
+        self.state.lock().unwrap().syncing = syncing;
+    }
+
+    // How long ago `status` says the head was produced; by default it is
+    // always fresh.
+    pub fn set_head_age(&self, age: Duration) {
+        self.state.lock().unwrap().head_age = age;
+    }
+
+    // Holds every response back for `delay`, like a slow or hung provider.
+    pub fn set_response_delay(&self, delay: Duration) {
+        self.state.lock().unwrap().response_delay = delay;
+    }
+
+    pub fn add_block(&self, block: BlockView) {
+        let mut state = self.state.lock().unwrap();
+        state.blocks.insert(block.header.height, block);
+    }
+
+    pub fn add_chunk(&self, chunk: ChunkView) {
+        let mut state = self.state.lock().unwrap();
+        state.chunks.insert(chunk.header.chunk_hash, chunk);
+    }
+
+    pub fn add_transaction(&self, outcome: FinalExecutionOutcomeView) {
+        let mut state = self.state.lock().unwrap();
+        state.transactions.insert(outcome.transaction.hash, outcome);
+    }
+
+    pub fn add_receipt(&self, receipt: ReceiptView) {
+        let mut state = self.state.lock().unwrap();
+        state.receipts.insert(receipt.receipt_id, receipt);
+    }
+
+    pub fn add_receipt_outcome(&self, outcome: ExecutionOutcomeWithIdView) {
+        let mut state = self.state.lock().unwrap();
+        state.receipt_outcomes.insert(outcome.id, outcome);
+    }
+
+    // Adds an empty single-chunk block at `height`, unless one is already there.
+    pub fn add_empty_block(&self, height: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.blocks.contains_key(&height) {
+            return;
+        }
+
+        let chunk = chunk_fixture(height);
+        state.blocks.insert(height, block_fixture(height, &chunk));
+        state.chunks.insert(chunk.header.chunk_hash, chunk);
+    }
+
+    // Includes a function call transaction in the block at `height`, along
+    // with the receipt it produces and its outcome. Returns the transaction hash.
+    pub fn add_function_call(&self, height: u64, call: MockFunctionCall) -> CryptoHash {
+        self.add_empty_block(height);
+
+        let mut state = self.state.lock().unwrap();
+        let block_hash = block_hash(height);
+        let chunk = state
+            .chunks
+            .get_mut(&chunk_hash(height))
+            .expect("fixture blocks have a chunk");
+
+        let tx_hash = CryptoHash::hash_bytes(
+            format!("tx-{}-{}", height, chunk.transactions.len()).as_bytes(),
+        );
+        let receipt_id = CryptoHash::hash_bytes(format!("receipt-{}", tx_hash).as_bytes());
+        let actions = if call.transfer {
+            json!([{ "Transfer": { "deposit": call.deposit.to_string() } }])
+        } else {
+            function_call_actions(&call.method_name, call.deposit)
+        };
+        let tx_signer_id = call.relayer_id.as_ref().unwrap_or(&call.signer_id);
+
+        let mut receipts_outcome = Vec::new();
+        let mut extra_receipts = Vec::new();
+        let (tx_receiver_id, tx_actions, first_receipt_id) = match &call.relayer_id {
+            None => (&call.receiver_id, actions.clone(), receipt_id),
+            Some(relayer_id) => {
+                let delegate_receipt_id =
+                    CryptoHash::hash_bytes(format!("delegate-{}", tx_hash).as_bytes());
+                let delegate_actions = json!([{
+                    "Delegate": {
+                        "delegate_action": {
+                            "sender_id": call.signer_id,
+                            "receiver_id": call.receiver_id,
+                            "actions": actions,
+                            "nonce": 0,
+                            "max_block_height": height + 100,
+                            "public_key": ZERO_PUBLIC_KEY,
+                        },
+                        "signature": ZERO_SIGNATURE,
+                    }
+                }]);
+                let delegate_outcome = outcome_fixture(
+                    delegate_receipt_id,
+                    block_hash,
+                    &call.signer_id,
+                    &[],
+                    json!({ "SuccessReceiptId": receipt_id }),
+                );
+
+                extra_receipts.push((
+                    receipt_fixture(
+                        delegate_receipt_id,
+                        relayer_id,
+                        relayer_id,
+                        &call.signer_id,
+                        delegate_actions.clone(),
+                    ),
+                    from_fixture(delegate_outcome.clone()),
+                ));
+                receipts_outcome.push(delegate_outcome);
+                (&call.signer_id, delegate_actions, delegate_receipt_id)
+            }
+        };
+
+        let transaction = json!({
+            "signer_id": tx_signer_id,
+            "public_key": ZERO_PUBLIC_KEY,
+            "nonce": chunk.transactions.len(),
+            "receiver_id": tx_receiver_id,
+            "actions": tx_actions,
+            "priority_fee": 0,
+            "signature": ZERO_SIGNATURE,
+            "hash": tx_hash,
+        });
+        let receipt = receipt_fixture(
+            receipt_id,
+            &call.signer_id,
+            tx_signer_id,
+            &call.receiver_id,
+            actions,
+        );
+        let status = if call.failed {
+            json!({
+                "Failure": {
+                    "ActionError": {
+                        "index": 0,
+                        "kind": {
+                            "FunctionCallError": {
+                                "ExecutionError": "Smart contract panicked",
+                            }
+                        },
+                    }
+                }
+            })
+        } else {
+            json!({ "SuccessValue": "" })
+        };
+        let mut receipt_outcome = outcome_fixture(
+            receipt_id,
+            block_hash,
+            &call.receiver_id,
+            &call.logs,
+            status.clone(),
+        );
+        receipt_outcome["outcome"]["gas_burnt"] = json!(call.gas_burnt);
+        receipt_outcome["outcome"]["tokens_burnt"] =
+            json!((call.gas_burnt as u128 * GAS_PRICE).to_string());
+        receipts_outcome.push(receipt_outcome.clone());
+
+        for (index, (callee_id, method_name, logs)) in call.callees.iter().enumerate() {
+            let callee_receipt_id =
+                CryptoHash::hash_bytes(format!("receipt-{}-{}", tx_hash, index + 1).as_bytes());
+            let callee_outcome = outcome_fixture(
+                callee_receipt_id,
+                block_hash,
+                callee_id,
+                logs,
+                json!({ "SuccessValue": "" }),
+            );
+
+            extra_receipts.push((
+                receipt_fixture(
+                    callee_receipt_id,
+                    &call.receiver_id,
+                    tx_signer_id,
+                    callee_id,
+                    function_call_actions(method_name, 0),
+                ),
+                from_fixture(callee_outcome.clone()),
+            ));
+            receipts_outcome.push(callee_outcome);
+        }
+        let outcome: FinalExecutionOutcomeView = from_fixture(json!({
+            "status": status,
+            "transaction": transaction,
+            "transaction_outcome": outcome_fixture(
+                tx_hash,
+                block_hash,
+                tx_signer_id,
+                &[],
+                json!({ "SuccessReceiptId": first_receipt_id }),
+            ),
+            "receipts_outcome": receipts_outcome,
+        }));
+
+        chunk.transactions.push(from_fixture(transaction));
+        // Listeners skip chunks whose header shows no transactions.
+        let tx_hashes: Vec<_> = chunk.transactions.iter().map(|tx| tx.hash).collect();
+        chunk.header.tx_root = CryptoHash::hash_borsh(tx_hashes);
+        state.sync_chunk_header(height);
+        state.receipts.insert(receipt_id, receipt);
+        state
+            .receipt_outcomes
+            .insert(receipt_id, from_fixture(receipt_outcome));
+        state.transactions.insert(tx_hash, outcome);
+        for (receipt, outcome) in extra_receipts {
+            state.receipt_outcomes.insert(receipt.receipt_id, outcome);
+            state.receipts.insert(receipt.receipt_id, receipt);
+        }
+
+        tx_hash
+    }
+
+    // Includes a receipt from `factory_id` that creates `account_id` in the
+    // block at `height`, as a factory contract's deployment of a child does.
+    pub fn add_account_creation(&self, height: u64, factory_id: &str, account_id: &str) {
+        self.add_empty_block(height);
+
+        let mut state = self.state.lock().unwrap();
+        let chunk = state
+            .chunks
+            .get_mut(&chunk_hash(height))
+            .expect("fixture blocks have a chunk");
+        let factory_id = parse_account_id(factory_id);
+        let receipt_id = CryptoHash::hash_bytes(
+            format!("create-{}-{}", height, chunk.receipts.len()).as_bytes(),
+        );
+        let receipt = receipt_fixture(
+            receipt_id,
+            &factory_id,
+            &factory_id,
+            &parse_account_id(account_id),
+            json!(["CreateAccount", { "Transfer": { "deposit": "1" } }]),
+        );
+
+        chunk.receipts.push(receipt.clone());
+        // Listeners skip chunks whose header shows no gas spent on receipts.
+        chunk.header.gas_used = chunk.header.gas_used.max(1);
+        state.sync_chunk_header(height);
+        state.receipts.insert(receipt_id, receipt);
+    }
+
+    // Records a write of `value` to `key` in the storage of `account_id` in
+    // the block at `height`, or the key's removal when `value` is `None`.
+    pub fn add_storage_change(
+        &self,
+        height: u64,
+        account_id: &str,
+        key: &[u8],
+        value: Option<&[u8]>,
+    ) {
+        let account_id = parse_account_id(account_id);
+        let key = key.to_vec().into();
+        let value = match value {
+            Some(value) => StateChangeValueView::DataUpdate {
+                account_id,
+                key,
+                value: value.to_vec().into(),
+            },
+            None => StateChangeValueView::DataDeletion { account_id, key },
+        };
+        self.add_state_change(height, value);
+    }
+
+    // Sets the balance of `account_id` in the block at `height`. Views of the
+    // account in later blocks return it.
+    pub fn add_balance_change(&self, height: u64, account_id: &str, amount: u128) {
+        let value = from_fixture(json!({
+            "type": "account_update",
+            "change": {
+                "account_id": account_id,
+                "amount": amount.to_string(),
+                "locked": "0",
+                "code_hash": CryptoHash::default(),
+                "storage_usage": 182,
+            },
+        }));
+        self.add_state_change(height, value);
+    }
+
+    // Adds `public_key` to `account_id` in the block at `height`, as a full
+    // access key or as a function call key for the account itself.
+    pub fn add_access_key(
+        &self,
+        height: u64,
+        account_id: &str,
+        public_key: &str,
+        full_access: bool,
+    ) {
+        let permission = if full_access {
+            json!("FullAccess")
+        } else {
+            json!({
+                "FunctionCall": {
+                    "allowance": "250000000000000000000000",
+                    "receiver_id": account_id,
+                    "method_names": [],
+                },
+            })
+        };
+        let value = from_fixture(json!({
+            "type": "access_key_update",
+            "change": {
+                "account_id": account_id,
+                "public_key": public_key,
+                "access_key": { "nonce": 0, "permission": permission },
+            },
+        }));
+        self.add_state_change(height, value);
+    }
+
+    pub fn remove_access_key(&self, height: u64, account_id: &str, public_key: &str) {
+        let value = from_fixture(json!({
+            "type": "access_key_deletion",
+            "change": { "account_id": account_id, "public_key": public_key },
+        }));
+        self.add_state_change(height, value);
+    }
+
+    pub fn add_code_deployment(&self, height: u64, account_id: &str, code: &[u8]) {
+        let value = StateChangeValueView::ContractCodeUpdate {
+            account_id: parse_account_id(account_id),
+            code: code.to_vec(),
+        };
+        self.add_state_change(height, value);
+    }
+
+    fn add_state_change(&self, height: u64, value: StateChangeValueView) {
+        self.add_empty_block(height);
+
+        let mut state = self.state.lock().unwrap();
+        let changes = state.state_changes.entry(height).or_default();
+        let receipt_hash =
+            CryptoHash::hash_bytes(format!("write-{}-{}", height, changes.len()).as_bytes());
+        changes.push(StateChangeWithCauseView {
+            cause: StateChangeCauseView::ReceiptProcessing { receipt_hash },
+            value,
+        });
+    }
+}
+
+impl Drop for MockBlockSource {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+// A proxy in front of a real node that saves every JSON-RPC response it
+// relays into a fixture directory, one file per distinct request, for
+// `PlaybackBlockSource` to serve. Point a listener at `url()` and run it over
+// the range to capture, e.g. with `backfill`.
+pub struct RecordingBlockSource {
+    url: String,
+    server: JoinHandle<()>,
+}
+
+struct Recorder {
+    rpc_url: String,
+    dir: PathBuf,
+    http: reqwest::Client,
+}
+
+impl RecordingBlockSource {
+    // Creates `dir` if needed. Fixtures already in it are kept, and replaced
+    // when the same request is made again.
+    pub async fn start(rpc_url: &str, dir: impl AsRef<Path>) -> io::Result<Self> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let recorder = Arc::new(Recorder {
+            rpc_url: rpc_url.to_string(),
+            dir: dir.as_ref().to_path_buf(),
+            http: reqwest::Client::new(),
+        });
+        let server = tokio::spawn(serve(listener, move |body| {
+            let recorder = recorder.clone();
+            async move { recorder.relay(&body).await }
+        }));
+
+        Ok(Self { url, server })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for RecordingBlockSource {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl Recorder {
+    // Failures to reach the node or to save the fixture are answered with an
+    // internal error, so a recording run does not silently miss responses.
+    async fn relay(&self, body: &[u8]) -> Value {
+        let request: Value = serde_json::from_slice(body).unwrap_or_default();
+        let response = match self.forward(body).await {
+            Ok(response) => response,
+            Err(err) => return error_response(&request, RpcError::new_internal_error(None, err)),
+        };
+
+        let mut fixture = json!({
+            "method": request["method"],
+            "params": request["params"],
+        });
+        for field in ["result", "error"] {
+            if let Some(value) = response.get(field) {
+                fixture[field] = value.clone();
+            }
+        }
+        let path = self
+            .dir
+            .join(fixture_name(&request["method"], &request["params"]));
+        let saved = serde_json::to_vec_pretty(&fixture)
+            .map_err(io::Error::from)
+            .and_then(|contents| std::fs::write(&path, contents));
+        if let Err(err) = saved {
+            let info = format!("failed to record {}: {}", path.display(), err);
+            return error_response(&request, RpcError::new_internal_error(None, info));
+        }
+        response
+    }
+
+    async fn forward(&self, body: &[u8]) -> Result<Value, String> {
+        let response = self
+            .http
+            .post(&self.rpc_url)
+            .header("Content-Type", "application/json")
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?;
+        serde_json::from_str(&response).map_err(|e| e.to_string())
+    }
+}
+
+// Serves the responses a `RecordingBlockSource` saved, so a listener can run
+// over the recorded range again without a network. Answers are the same on
+// every run; requests that were never recorded fail, blocks as unknown ones.
+pub struct PlaybackBlockSource {
+    url: String,
+    server: JoinHandle<()>,
+}
+
+impl PlaybackBlockSource {
+    pub async fn start(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut fixtures = HashMap::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let fixture: Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+            fixtures.insert(
+                fixture_name(&fixture["method"], &fixture["params"]),
+                fixture,
+            );
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let fixtures = Arc::new(fixtures);
+        let server = tokio::spawn(serve(listener, move |body| {
+            let fixtures = fixtures.clone();
+            async move { play(&fixtures, &body) }
+        }));
+
+        Ok(Self { url, server })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for PlaybackBlockSource {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl MockState {
+    // Copies the chunk header at `height` into its block after the chunk changed.
+    fn sync_chunk_header(&mut self, height: u64) {
+        let Some(header) = self
+            .chunks
+            .get(&chunk_hash(height))
+            .map(|chunk| chunk.header.clone())
+        else {
+            return;
+        };
+        if let Some(block) = self.blocks.get_mut(&height) {
+            for chunk_header in &mut block.chunks {
+                if chunk_header.chunk_hash == header.chunk_hash {
+                    *chunk_header = header.clone();
+                }
+            }
+        }
+    }
+
+    fn call(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "block" => {
+                let block = self.block(&params["block_id"]).ok_or_else(|| {
+                    RpcError::from(RpcBlockError::UnknownBlock {
+                        error_message: format!("{}", params["block_id"]),
+                    })
+                })?;
+                Ok(json!(block))
+            }
+            "chunk" => {
+                let chunk_hash = param_hash(params, "chunk_id");
+                let chunk = self.chunks.get(&chunk_hash).ok_or_else(|| {
+                    RpcError::from(RpcChunkError::UnknownChunk {
+                        chunk_hash: ChunkHash(chunk_hash),
+                    })
+                })?;
+                Ok(json!(chunk))
+            }
+            "tx" => {
+                let tx_hash = param_hash(params, "tx_hash");
+                let outcome = self.transactions.get(&tx_hash).ok_or_else(|| {
+                    RpcError::from(RpcTransactionError::UnknownTransaction {
+                        requested_transaction_hash: tx_hash,
+                    })
+                })?;
+                let mut response = json!(outcome);
+                response["final_execution_status"] = json!("FINAL");
+                Ok(response)
+            }
+            "EXPERIMENTAL_receipt" => {
+                let receipt_id = param_hash(params, "receipt_id");
+                let receipt = self.receipts.get(&receipt_id).ok_or_else(|| {
+                    RpcError::from(RpcReceiptError::UnknownReceipt { receipt_id })
+                })?;
+                Ok(json!(receipt))
+            }
+            "light_client_proof" => {
+                let receipt_id = param_hash(params, "receipt_id");
+                let outcome = self.receipt_outcomes.get(&receipt_id).ok_or_else(|| {
+                    RpcError::from(RpcLightClientProofError::UnknownTransactionOrReceipt {
+                        transaction_or_receipt_id: receipt_id,
+                    })
+                })?;
+                Ok(json!({
+                    "outcome_proof": outcome,
+                    "outcome_root_proof": [],
+                    "block_header_lite": light_block_fixture(outcome.block_hash),
+                    "block_proof": [],
+                }))
+            }
+            "EXPERIMENTAL_changes" => self.state_changes(params),
+            "query" => self.query(params),
+            "status" => Ok(self.status()),
+            _ => Err(RpcError::method_not_found(method.to_string())),
+        }
+    }
+
+    fn state_changes(&self, params: &Value) -> Result<Value, RpcError> {
+        let request: RpcStateChangesInBlockByTypeRequest = from_fixture(params.clone());
+        let block = self.block(&params["block_id"]).ok_or_else(|| {
+            RpcError::from(RpcStateChangesError::UnknownBlock {
+                error_message: format!("{}", params["block_id"]),
+            })
+        })?;
+
+        let changes: Vec<_> = self
+            .state_changes
+            .get(&block.header.height)
+            .into_iter()
+            .flatten()
+            .filter(|change| is_requested(&request.state_changes_request, &change.value))
+            .collect();
+        Ok(json!({
+            "block_hash": block.header.hash,
+            "changes": changes,
+        }))
+    }
+
+    // Views accounts and access keys as the changes recorded up to the block
+    // left them.
+    fn query(&self, params: &Value) -> Result<Value, RpcError> {
+        let block_hash = param_hash(params, "block_id");
+        let height = self.height_of(block_hash).ok_or_else(|| {
+            RpcError::from(RpcQueryError::UnknownBlock {
+                block_reference: BlockReference::BlockId(BlockId::Hash(block_hash)),
+            })
+        })?;
+        let request: QueryRequest = from_fixture(params.clone());
+        let (account_id, public_key) = match &request {
+            QueryRequest::ViewAccount { account_id } => (account_id, None),
+            QueryRequest::ViewAccessKey {
+                account_id,
+                public_key,
+            } => (account_id, Some(public_key)),
+            _ => return Err(RpcError::method_not_found("query".to_string())),
+        };
+
+        let mut found = None;
+        for change in self
+            .state_changes
+            .range(..=height)
+            .flat_map(|(_, changes)| changes)
+        {
+            match (&change.value, public_key) {
+                (
+                    StateChangeValueView::AccountUpdate {
+                        account_id: id,
+                        account,
+                    },
+                    None,
+                ) if id == account_id => found = Some(json!(account)),
+                (
+                    StateChangeValueView::AccessKeyUpdate {
+                        account_id: id,
+                        public_key: key,
+                        access_key,
+                    },
+                    Some(public_key),
+                ) if id == account_id && key == public_key => found = Some(json!(access_key)),
+                (
+                    StateChangeValueView::AccessKeyDeletion {
+                        account_id: id,
+                        public_key: key,
+                    },
+                    Some(public_key),
+                ) if id == account_id && key == public_key => found = None,
+                _ => {}
+            }
+        }
+
+        let Some(mut response) = found else {
+            let error = match public_key {
+                None => RpcQueryError::UnknownAccount {
+                    requested_account_id: account_id.clone(),
+                    block_height: height,
+                    block_hash,
+                },
+                Some(public_key) => RpcQueryError::UnknownAccessKey {
+                    public_key: public_key.clone(),
+                    block_height: height,
+                    block_hash,
+                },
+            };
+            return Err(RpcError::from(error));
+        };
+        response["block_height"] = json!(height);
+        response["block_hash"] = json!(block_hash);
+        Ok(response)
+    }
+
+    // Also finds the height of a block that was never added, from its hash.
+    fn height_of(&self, hash: CryptoHash) -> Option<u64> {
+        let latest = self.blocks.keys().next_back().copied().unwrap_or_default();
+        (0..=latest + 1).find(|height| block_hash(*height) == hash)
+    }
+
+    fn status(&self) -> Value {
+        let earliest = self.blocks.values().next();
+        let latest = self.blocks.values().next_back();
+        let latest_height = latest.map_or(0, |block| block.header.height);
+
+        json!({
+            "version": { "version": "mock", "build": "mock" },
+            "chain_id": "mock",
+            "protocol_version": 73,
+            "latest_protocol_version": 73,
+            "validators": [],
+            "sync_info": {
+                "latest_block_hash": block_hash(latest_height),
+                "latest_block_height": latest_height,
+                "latest_state_root": CryptoHash::default(),
+                "latest_block_time": iso_time(SystemTime::now() - self.head_age),
+                "syncing": self.syncing,
+                "earliest_block_hash": earliest.map(|block| block.header.hash),
+                "earliest_block_height": earliest.map(|block| block.header.height),
+                "earliest_block_time": null,
+            },
+            "validator_account_id": null,
+            "validator_public_key": null,
+            "node_public_key": ZERO_PUBLIC_KEY,
+            "node_key": null,
+            "uptime_sec": 0,
+            "genesis_hash": block_hash(0),
+        })
+    }
+
+    // Finality references resolve to the highest block loaded.
+    fn block(&self, block_id: &Value) -> Option<&BlockView> {
+        match block_id {
+            Value::Number(height) => self.blocks.get(&height.as_u64()?),
+            Value::String(hash) => {
+                let hash = CryptoHash::from_str(hash).ok()?;
+                self.blocks.values().find(|block| block.header.hash == hash)
+            }
+            _ => self.blocks.values().next_back(),
+        }
+    }
+}
+
+// Answers each request body with the JSON-RPC response `respond` returns.
+async fn serve<F, R>(listener: TcpListener, respond: F)
+where
+    F: Fn(Vec<u8>) -> R + Clone + Send + 'static,
+    R: Future<Output = Value> + Send + 'static,
+{
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(handle_connection(stream, respond.clone()));
+    }
+}
+
+// Just enough HTTP/1.1 for the JSON-RPC client: keep-alive connections
+// carrying POST requests with a Content-Length body.
+async fn handle_connection<F, R>(stream: TcpStream, respond: F)
+where
+    F: Fn(Vec<u8>) -> R,
+    R: Future<Output = Value>,
+{
+    let mut stream = BufReader::new(stream);
+
+    loop {
+        let mut line = String::new();
+        let mut content_length = 0;
+        loop {
+            line.clear();
+            match stream.read_line(&mut line).await {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            if line.trim_end().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        if stream.read_exact(&mut body).await.is_err() {
+            return;
+        }
+
+        let response = respond(body).await.to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        );
+        if stream
+            .get_mut()
+            .write_all(response.as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn respond(state: &Mutex<MockState>, body: &[u8]) -> Value {
+    let request: Value = serde_json::from_slice(body).unwrap_or_default();
+    let method = request["method"].as_str().unwrap_or_default();
+
+    let mut state = state.lock().unwrap();
+    *state.requests.entry(method.to_string()).or_default() += 1;
+    match state.call(method, &request["params"]) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+        Err(error) => error_response(&request, error),
+    }
+}
+
+fn play(fixtures: &HashMap<String, Value>, body: &[u8]) -> Value {
+    let request: Value = serde_json::from_slice(body).unwrap_or_default();
+    let method = request["method"].as_str().unwrap_or_default();
+
+    let Some(fixture) = fixtures.get(&fixture_name(&request["method"], &request["params"])) else {
+        let error = match method {
+            "block" => RpcError::from(RpcBlockError::UnknownBlock {
+                error_message: format!("{}", request["params"]),
+            }),
+            _ => RpcError::new_internal_error(
+                None,
+                format!("no fixture recorded for {} {}", method, request["params"]),
+            ),
+        };
+        return error_response(&request, error);
+    };
+    let mut response = json!({ "jsonrpc": "2.0", "id": request["id"] });
+    for field in ["result", "error"] {
+        if let Some(value) = fixture.get(field) {
+            response[field] = value.clone();
+        }
+    }
+    response
+}
+
+fn error_response(request: &Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": request["id"], "error": error })
+}
+
+// Requests are told apart by method and parameters; the request ID differs
+// between runs.
+fn fixture_name(method: &Value, params: &Value) -> String {
+    let params = CryptoHash::hash_bytes(params.to_string().as_bytes());
+    format!("{}-{}.json", method.as_str().unwrap_or_default(), params)
+}
+
+fn is_requested(request: &StateChangesRequestView, value: &StateChangeValueView) -> bool {
+    match (request, value) {
+        (
+            StateChangesRequestView::DataChanges {
+                account_ids,
+                key_prefix,
+            },
+            StateChangeValueView::DataUpdate {
+                account_id, key, ..
+            }
+            | StateChangeValueView::DataDeletion { account_id, key },
+        ) => account_ids.contains(account_id) && key.starts_with(key_prefix),
+        (
+            StateChangesRequestView::AccountChanges { account_ids },
+            StateChangeValueView::AccountUpdate { account_id, .. }
+            | StateChangeValueView::AccountDeletion { account_id },
+        )
+        | (
+            StateChangesRequestView::AllAccessKeyChanges { account_ids },
+            StateChangeValueView::AccessKeyUpdate { account_id, .. }
+            | StateChangeValueView::AccessKeyDeletion { account_id, .. },
+        )
+        | (
+            StateChangesRequestView::ContractCodeChanges { account_ids },
+            StateChangeValueView::ContractCodeUpdate { account_id, .. }
+            | StateChangeValueView::ContractCodeDeletion { account_id },
+        ) => account_ids.contains(account_id),
+        _ => false,
+    }
+}
+
+fn param_hash(params: &Value, name: &str) -> CryptoHash {
+    params[name]
+        .as_str()
+        .and_then(|hash| CryptoHash::from_str(hash).ok())
+        .unwrap_or_default()
+}
+
+fn parse_account_id(account_id: &str) -> AccountId {
+    account_id
+        .parse()
+        .unwrap_or_else(|_| panic!("invalid account id: {}", account_id))
+}
+
+fn from_fixture<T: DeserializeOwned>(fixture: Value) -> T {
+    serde_json::from_value(fixture).expect("fixtures match the RPC view types")
+}
+
+fn block_hash(height: u64) -> CryptoHash {
+    CryptoHash::hash_bytes(format!("block-{}", height).as_bytes())
+}
+
+fn chunk_hash(height: u64) -> CryptoHash {
+    CryptoHash::hash_bytes(format!("chunk-{}", height).as_bytes())
+}
+
+fn timestamp(height: u64) -> u64 {
+    GENESIS_TIMESTAMP + height * BLOCK_TIME
+}
+
+// Formats a time as RFC 3339 in UTC, the way nodes report it.
+fn iso_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+
+    // Civil date from days since the epoch, after Howard Hinnant.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn chunk_header_fixture(height: u64) -> Value {
+    json!({
+        "chunk_hash": chunk_hash(height),
+        "prev_block_hash": block_hash(height.saturating_sub(1)),
+        "outcome_root": CryptoHash::default(),
+        "prev_state_root": CryptoHash::default(),
+        "encoded_merkle_root": CryptoHash::default(),
+        "encoded_length": 0,
+        "height_created": height,
+        "height_included": height,
+        "shard_id": 0,
+        "gas_used": 0,
+        "gas_limit": 1_000_000_000_000_000u64,
+        "rent_paid": "0",
+        "validator_reward": "0",
+        "balance_burnt": "0",
+        "outgoing_receipts_root": CryptoHash::default(),
+        "tx_root": CryptoHash::default(),
+        "validator_proposals": [],
+        "congestion_info": null,
+        "signature": ZERO_SIGNATURE,
+    })
+}
+
+fn chunk_fixture(height: u64) -> ChunkView {
+    from_fixture(json!({
+        "author": AUTHOR,
+        "header": chunk_header_fixture(height),
+        "transactions": [],
+        "receipts": [],
+    }))
+}
+
+fn block_fixture(height: u64, chunk: &ChunkView) -> BlockView {
+    from_fixture(json!({
+        "author": AUTHOR,
+        "header": {
+            "height": height,
+            "prev_height": height.checked_sub(1),
+            "epoch_id": CryptoHash::default(),
+            "next_epoch_id": CryptoHash::default(),
+            "hash": block_hash(height),
+            "prev_hash": block_hash(height.saturating_sub(1)),
+            "prev_state_root": CryptoHash::default(),
+            "block_body_hash": null,
+            "chunk_receipts_root": CryptoHash::default(),
+            "chunk_headers_root": CryptoHash::default(),
+            "chunk_tx_root": CryptoHash::default(),
+            "outcome_root": CryptoHash::default(),
+            "chunks_included": 1,
+            "challenges_root": CryptoHash::default(),
+            "timestamp": timestamp(height),
+            "timestamp_nanosec": timestamp(height).to_string(),
+            "random_value": CryptoHash::default(),
+            "validator_proposals": [],
+            "chunk_mask": [true],
+            "gas_price": "0",
+            "block_ordinal": null,
+            "rent_paid": "0",
+            "validator_reward": "0",
+            "total_supply": "0",
+            "challenges_result": [],
+            "last_final_block": block_hash(height.saturating_sub(2)),
+            "last_ds_final_block": block_hash(height.saturating_sub(1)),
+            "next_bp_hash": CryptoHash::default(),
+            "block_merkle_root": CryptoHash::default(),
+            "epoch_sync_data_hash": null,
+            "approvals": [],
+            "signature": ZERO_SIGNATURE,
+            "latest_protocol_version": 73,
+        },
+        "chunks": [chunk.header],
+    }))
+}
+
+fn function_call_actions(method_name: &str, deposit: u128) -> Value {
+    json!([{
+        "FunctionCall": {
+            "method_name": method_name,
+            "args": "",
+            "gas": 30_000_000_000_000u64,
+            "deposit": deposit.to_string(),
+        }
+    }])
+}
+
+fn receipt_fixture(
+    receipt_id: CryptoHash,
+    predecessor_id: &AccountId,
+    signer_id: &AccountId,
+    receiver_id: &AccountId,
+    actions: Value,
+) -> ReceiptView {
+    from_fixture(json!({
+        "predecessor_id": predecessor_id,
+        "receiver_id": receiver_id,
+        "receipt_id": receipt_id,
+        "receipt": {
+            "Action": {
+                "signer_id": signer_id,
+                "signer_public_key": ZERO_PUBLIC_KEY,
+                "gas_price": "0",
+                "output_data_receivers": [],
+                "input_data_ids": [],
+                "actions": actions,
+            }
+        },
+        "priority": 0,
+    }))
+}
+
+fn event_json(event: &EventLog) -> String {
+    let event = serde_json::to_string(event).expect("event logs serialize to JSON");
+    format!("EVENT_JSON:{}", event)
+}
+
+fn outcome_fixture(
+    id: CryptoHash,
+    block_hash: CryptoHash,
+    executor_id: &AccountId,
+    logs: &[String],
+    status: Value,
+) -> Value {
+    let receipt_ids: Vec<CryptoHash> = match &status["SuccessReceiptId"] {
+        Value::Null => Vec::new(),
+        receipt_id => vec![from_fixture(receipt_id.clone())],
+    };
+
+    json!({
+        "proof": [],
+        "block_hash": block_hash,
+        "id": id,
+        "outcome": {
+            "logs": logs,
+            "receipt_ids": receipt_ids,
+            "gas_burnt": 0,
+            "tokens_burnt": "0",
+            "executor_id": executor_id,
+            "status": status,
+        },
+    })
+}
+
+fn light_block_fixture(block_hash: CryptoHash) -> Value {
+    json!({
+        "prev_block_hash": CryptoHash::default(),
+        "inner_rest_hash": block_hash,
+        "inner_lite": {
+            "height": 0,
+            "epoch_id": CryptoHash::default(),
+            "next_epoch_id": CryptoHash::default(),
+            "prev_state_root": CryptoHash::default(),
+            "outcome_root": CryptoHash::default(),
+            "timestamp": 0,
+            "timestamp_nanosec": "0",
+            "next_bp_hash": CryptoHash::default(),
+            "block_merkle_root": CryptoHash::default(),
+        },
+    })
+}
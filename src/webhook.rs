@@ -0,0 +1,189 @@
+use crate::listener::validate_http_url;
+use crate::post::{post_with_retry, HttpRetry, PostError, RetryHandler};
+use crate::runtime;
+#[cfg(feature = "templates")]
+use crate::EventTemplate;
+use crate::{EventEnvelope, EventSink, ListenerError, RetryPolicy, Runtime};
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WebhookMetrics {
+    pub delivered: u64,
+    pub failed: u64,
+    pub retries: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    retries: AtomicU64,
+}
+
+// POSTs every event envelope as JSON to an HTTP endpoint. Clones share the
+// same connection pool and metrics.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    url: String,
+    secret: Option<Vec<u8>>,
+    #[cfg(feature = "templates")]
+    template: Option<EventTemplate>,
+    retry: HttpRetry,
+    http: reqwest::Client,
+    counters: Arc<Counters>,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Result<Self, ListenerError> {
+        validate_http_url("webhook url", url)?;
+
+        let counters = Arc::<Counters>::default();
+        Ok(Self {
+            url: url.to_string(),
+            secret: None,
+            #[cfg(feature = "templates")]
+            template: None,
+            retry: HttpRetry {
+                policy: RetryPolicy::new(3),
+                timeout: DEFAULT_TIMEOUT,
+                runtime: runtime::default_runtime(),
+                on_retry: Some(count_retries(&counters, |_, _| {})),
+            },
+            http: reqwest::Client::new(),
+            counters,
+        })
+    }
+
+    // Signs every payload with HMAC-SHA256 and sends it in `X-Signature-256`
+    // as `sha256=<hex>`, so the receiver can check where it came from.
+    pub fn secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    // Sends the rendered template instead of the envelope, e.g. to match the
+    // payload another service expects. It should render JSON, since requests
+    // are still sent as `application/json`.
+    #[cfg(feature = "templates")]
+    pub fn template(mut self, template: EventTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry.policy = policy;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.retry.timeout = timeout;
+        self
+    }
+
+    // Waits out the retry backoff on another executor than tokio. Without a
+    // runtime, failed deliveries are retried right away.
+    pub fn runtime<R: Runtime>(mut self, runtime: R) -> Self {
+        self.retry.runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    // Called with the attempt number and its error whenever a failed
+    // delivery is about to be retried.
+    pub fn on_retry<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(u32, &ListenerError) + Send + Sync + 'static,
+    {
+        self.retry.on_retry = Some(count_retries(&self.counters, handler));
+        self
+    }
+
+    pub fn metrics(&self) -> WebhookMetrics {
+        WebhookMetrics {
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            retries: self.counters.retries.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn signature(secret: &[u8], body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+        mac.update(body);
+        let digest: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("sha256={}", digest)
+    }
+
+    pub async fn send(&self, envelope: &EventEnvelope) -> Result<(), ListenerError> {
+        #[cfg(feature = "templates")]
+        let body = match &self.template {
+            Some(template) => template.render(envelope)?.into_bytes(),
+            None => serde_json::to_vec(envelope)?,
+        };
+        #[cfg(not(feature = "templates"))]
+        let body = serde_json::to_vec(envelope)?;
+
+        let mut headers = HeaderMap::new();
+        if let Some(secret) = &self.secret {
+            let signature = HeaderValue::from_str(&Self::signature(secret, &body))
+                .expect("signatures are ASCII");
+            headers.insert(SIGNATURE_HEADER, signature);
+        }
+
+        let result = post_with_retry(
+            &self.http,
+            &self.url,
+            headers,
+            &body,
+            &self.retry,
+            // The URL often carries a secret token, so it stays out of the error.
+            |failure| match failure {
+                PostError::Transport(err) => {
+                    ListenerError::WebhookError(err.without_url().to_string())
+                }
+                PostError::Status(status) => {
+                    ListenerError::WebhookError(format!("webhook responded with {}", status))
+                }
+            },
+        )
+        .await;
+        let counter = match &result {
+            Ok(()) => &self.counters.delivered,
+            Err(_) => &self.counters.failed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+}
+
+// Counts retries into the sink's metrics before handing them to `handler`.
+fn count_retries<F>(counters: &Arc<Counters>, handler: F) -> RetryHandler
+where
+    F: Fn(u32, &ListenerError) + Send + Sync + 'static,
+{
+    let counters = counters.clone();
+    Arc::new(move |attempt, err| {
+        counters.retries.fetch_add(1, Ordering::Relaxed);
+        handler(attempt, err);
+    })
+}
+
+impl EventSink for WebhookSink {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        Box::pin(WebhookSink::send(self, envelope))
+    }
+}
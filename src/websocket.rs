@@ -0,0 +1,106 @@
+use crate::Runtime;
+use futures::future::{self, Either};
+use futures::{FutureExt, SinkExt, StreamExt};
+use log::{info, warn};
+use std::pin::pin;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+// Silence longer than this is treated as a stalled connection, and the
+// listener polls anyway.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug)]
+pub(crate) struct BlockSubscription {
+    url: String,
+    subscribe_message: Option<String>,
+    socket: Option<Socket>,
+    reconnect_at: Option<Instant>,
+}
+
+impl BlockSubscription {
+    pub(crate) fn new(url: &str, subscribe_message: Option<&str>) -> Self {
+        Self {
+            url: url.to_string(),
+            subscribe_message: subscribe_message.map(str::to_string),
+            socket: None,
+            reconnect_at: None,
+        }
+    }
+
+    // Returns once the node announces a new block. While disconnected this
+    // degrades to sleeping for `fallback`, and reconnects periodically.
+    pub(crate) async fn wait(&mut self, fallback: Duration, runtime: &dyn Runtime) {
+        if self.socket.is_none()
+            && self
+                .reconnect_at
+                .is_none_or(|reconnect_at| Instant::now() >= reconnect_at)
+        {
+            self.connect().await;
+        }
+
+        let Some(socket) = self.socket.as_mut() else {
+            runtime.sleep(fallback).await;
+            return;
+        };
+
+        let idle = runtime.sleep(IDLE_TIMEOUT);
+        let announced = match future::select(pin!(next_block_message(socket)), idle).await {
+            Either::Left((announced, _)) => Some(announced),
+            Either::Right(_) => None,
+        };
+        match announced {
+            Some(true) => {
+                // Announcements that queued up while the block was processed
+                // are covered by the next poll.
+                while let Some(Some(Ok(_))) = socket.next().now_or_never() {}
+            }
+            Some(false) => self.disconnect(),
+            None => {}
+        }
+    }
+
+    async fn connect(&mut self) {
+        match connect_async(self.url.as_str()).await {
+            Ok((mut socket, _)) => {
+                if let Some(message) = &self.subscribe_message {
+                    if let Err(err) = socket.send(Message::Text(message.clone())).await {
+                        warn!("WebSocket subscription failed: {}", err);
+                        self.disconnect();
+                        return;
+                    }
+                }
+                info!("Subscribed to new blocks at {}", self.url);
+                self.socket = Some(socket);
+                self.reconnect_at = None;
+            }
+            Err(err) => {
+                warn!("WebSocket connection failed: {}", err);
+                self.disconnect();
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if self.socket.take().is_some() {
+            warn!("WebSocket disconnected, falling back to polling");
+        }
+        self.reconnect_at = Some(Instant::now() + RECONNECT_INTERVAL);
+    }
+}
+
+async fn next_block_message(socket: &mut Socket) -> bool {
+    while let Some(message) = socket.next().await {
+        match message {
+            Ok(Message::Text(_) | Message::Binary(_)) => return true,
+            Ok(Message::Close(_)) | Err(_) => return false,
+            Ok(_) => continue,
+        }
+    }
+    false
+}
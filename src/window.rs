@@ -0,0 +1,119 @@
+use crate::{BlockEvents, EventEnvelope, ListenerError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// How `start_windowed` cuts the chain into tumbling windows. Windows are
+// aligned on multiples of their size: `Blocks(100)` covers heights 0..100,
+// 100..200 and so on, and `Duration` windows are measured in block time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSize {
+    Blocks(u64),
+    Duration(Duration),
+}
+
+impl WindowSize {
+    pub(crate) fn validate(&self) -> Result<(), ListenerError> {
+        let empty = match self {
+            WindowSize::Blocks(blocks) => *blocks == 0,
+            WindowSize::Duration(duration) => duration.as_nanos() == 0,
+        };
+        if empty {
+            return Err(ListenerError::InvalidConfig(
+                "window size must not be zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Returns the half-open range of the window containing the block, in
+    // heights or in nanosecond timestamps.
+    fn bounds(&self, height: u64, timestamp: u64) -> (u64, u64) {
+        let (position, size) = match self {
+            WindowSize::Blocks(blocks) => (height, *blocks),
+            WindowSize::Duration(duration) => (
+                timestamp,
+                u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX),
+            ),
+        };
+        let start = position - position % size;
+        (start, start.saturating_add(size))
+    }
+}
+
+// The events of one window. `start..end` is a range of block heights for
+// `WindowSize::Blocks` and of block timestamps in nanoseconds for
+// `WindowSize::Duration`; `first_block` and `last_block` are the heights of
+// the blocks the listener processed in it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventWindow {
+    pub start: u64,
+    pub end: u64,
+    pub first_block: u64,
+    pub last_block: u64,
+    pub events: Vec<EventEnvelope>,
+}
+
+impl EventWindow {
+    // Counts the window's events per key, e.g. per event name or sender.
+    pub fn count_by<K, F>(&self, key: F) -> BTreeMap<K, usize>
+    where
+        K: Ord,
+        F: Fn(&EventEnvelope) -> K,
+    {
+        let mut counts = BTreeMap::new();
+        for envelope in &self.events {
+            *counts.entry(key(envelope)).or_default() += 1;
+        }
+        counts
+    }
+}
+
+// Collects batches into the open window and hands it over once a block of a
+// later window arrives.
+#[derive(Debug)]
+pub(crate) struct TumblingWindows {
+    size: WindowSize,
+    open: Option<EventWindow>,
+}
+
+impl TumblingWindows {
+    pub(crate) fn new(size: WindowSize) -> Self {
+        Self { size, open: None }
+    }
+
+    // Returns the window that the batch closed, if any.
+    pub(crate) fn push(&mut self, batch: BlockEvents) -> Option<EventWindow> {
+        let header = batch.header;
+        if batch.reverted {
+            // Windows already handed over cannot be taken back.
+            if let Some(window) = &mut self.open {
+                window
+                    .events
+                    .retain(|envelope| envelope.context.block_hash != header.hash);
+            }
+            return None;
+        }
+
+        let (start, end) = self.size.bounds(header.height, header.timestamp);
+        let closed = match &self.open {
+            Some(window) if window.start == start => None,
+            _ => self.open.take(),
+        };
+        let window = self.open.get_or_insert_with(|| EventWindow {
+            start,
+            end,
+            first_block: header.height,
+            last_block: header.height,
+            events: Vec::new(),
+        });
+        window.last_block = header.height;
+        window.events.extend(batch.events);
+
+        closed
+    }
+
+    pub(crate) fn finish(self) -> Option<EventWindow> {
+        self.open
+    }
+}
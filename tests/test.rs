@@ -1,9 +1,26 @@
-use near_event_listener::{ListenerError, NearEventListener};
+use futures::StreamExt;
+use near_event_listener::{
+    CheckpointStore, DeadLetter, DeadLetterSink, ErrorAction, EventContext, EventFilter, EventLog,
+    EventRegistry, FileCheckpointStore, FileDeadLetterSink, Finality, ListenerError,
+    NearEventListener, Network, RetryPolicy, StartPosition,
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn temp_checkpoint_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "near_event_listener_{}_{}.checkpoint",
+            name,
+            std::process::id()
+        ))
+    }
+
     #[test]
     fn test_builder_success() {
         let listener = NearEventListener::builder("http://rpc.testnet.near.org")
@@ -14,15 +31,48 @@ mod tests {
 
         assert!(listener.is_ok());
         let listener = listener.unwrap();
-        assert_eq!(listener.account_id, "test.near");
-        assert_eq!(listener.method_name, "nft_mint");
+        assert_eq!(listener.account_ids, vec!["test.near"]);
+        assert_eq!(listener.method_names, vec!["nft_mint"]);
         assert_eq!(listener.last_processed_block, 1234);
     }
 
+    #[test]
+    fn test_builder_multiple_method_names() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .method_names(&["nft_mint", "nft_transfer"])
+            .method_name("nft_burn")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            listener.method_names,
+            vec!["nft_mint", "nft_transfer", "nft_burn"]
+        );
+    }
+
+    #[test]
+    fn test_builder_multiple_account_ids() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_ids(&["nft.near", "market.near"])
+            .account_id("token.near")
+            .method_name("nft_mint")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            listener.account_ids,
+            vec!["nft.near", "market.near", "token.near"]
+        );
+    }
+
     #[test]
     fn test_builder_missing_account_id() {
+        // Without any account the builder has no `build`; an empty list is
+        // only caught at run time.
         let listener = NearEventListener::builder("http://rpc.testnet.near.org")
             .method_name("nft_mint")
+            .account_ids(&[])
             .build();
 
         assert!(matches!(
@@ -32,17 +82,307 @@ mod tests {
     }
 
     #[test]
-    fn test_builder_missing_method_name() {
+    fn test_builder_rejects_invalid_rpc_url() {
+        for rpc_url in [
+            "rpc.testnet.near.org",
+            "ftp://rpc.testnet.near.org",
+            "http://",
+        ] {
+            let listener = NearEventListener::builder(rpc_url)
+                .account_id("test.near")
+                .build();
+
+            assert!(matches!(
+                listener.unwrap_err(),
+                ListenerError::InvalidConfig(message) if message.contains("rpc_url")
+            ));
+        }
+
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .archival_rpc_url("not a url")
+            .account_id("test.near")
+            .build();
+
+        assert!(matches!(
+            listener.unwrap_err(),
+            ListenerError::InvalidConfig(message) if message.contains("archival_rpc_url")
+        ));
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_account_id() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_ids(&["nft.near", "Not An Account"])
+            .build();
+
+        assert!(matches!(
+            listener.unwrap_err(),
+            ListenerError::InvalidConfig(message) if message.contains("Not An Account")
+        ));
+    }
+
+    #[test]
+    fn test_builder_rejects_shard_filter_without_lake() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("nft.near")
+            .shard_filter(true)
+            .build();
+
+        assert!(matches!(
+            listener.unwrap_err(),
+            ListenerError::InvalidConfig(message) if message.contains("shard_filter")
+        ));
+    }
+
+    #[test]
+    fn test_builder_without_method_name_matches_any_method() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .build()
+            .unwrap();
+
+        assert!(listener.method_names.is_empty());
+        assert!(listener.matches_method("nft_mint"));
+        assert!(listener.matches_method("ft_transfer_call"));
+    }
+
+    #[test]
+    fn test_builder_archival_rpc_url() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .build()
+            .unwrap();
+        assert!(listener.archival_client.is_none());
+
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .archival_rpc_url("http://archival-rpc.testnet.near.org")
+            .account_id("test.near")
+            .build()
+            .unwrap();
+        assert_eq!(
+            listener.archival_client.unwrap().server_addr(),
+            "http://archival-rpc.testnet.near.org"
+        );
+    }
+
+    #[test]
+    fn test_builder_sends_rpc_headers() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .archival_rpc_url("http://archival-rpc.testnet.near.org")
+            .rpc_api_key("secret")
+            .header("x-client", "indexer")
+            .proxy("http://127.0.0.1:8080")
+            .connect_timeout(Duration::from_secs(5))
+            .account_id("test.near")
+            .build()
+            .unwrap();
+
+        for client in [&listener.client, listener.archival_client.as_ref().unwrap()] {
+            assert_eq!(client.headers()["x-api-key"], "secret");
+            assert_eq!(client.headers()["x-client"], "indexer");
+        }
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_http_options() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .header("x-client", "line\nbreak")
+            .account_id("test.near")
+            .build();
+        assert!(matches!(
+            listener.unwrap_err(),
+            ListenerError::InvalidConfig(message) if message.contains("x-client")
+        ));
+
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .proxy("not a proxy")
+            .account_id("test.near")
+            .build();
+        assert!(matches!(
+            listener.unwrap_err(),
+            ListenerError::InvalidConfig(message) if message.contains("proxy")
+        ));
+    }
+
+    #[test]
+    fn test_builder_for_network() {
+        let listener = NearEventListener::builder_for(Network::Mainnet)
+            .account_id("test.near")
+            .build()
+            .unwrap();
+        assert_eq!(
+            listener.client.server_addr(),
+            "https://rpc.mainnet.near.org"
+        );
+        assert_eq!(
+            listener.archival_client.unwrap().server_addr(),
+            "https://archival-rpc.mainnet.near.org"
+        );
+
+        let listener =
+            NearEventListener::builder_for(Network::Localnet("http://localhost:3030".to_string()))
+                .account_id("test.near")
+                .build()
+                .unwrap();
+        assert_eq!(listener.client.server_addr(), "http://localhost:3030");
+        assert!(listener.archival_client.is_none());
+    }
+
+    #[test]
+    fn test_builder_finality() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .build()
+            .unwrap();
+        assert!(matches!(listener.finality, Finality::Final));
+
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .finality(Finality::DoomSlug)
+            .build()
+            .unwrap();
+        assert!(matches!(listener.finality, Finality::DoomSlug));
+    }
+
+    // Tests for checkpointing
+    #[test]
+    fn test_file_checkpoint_store_roundtrip() {
+        let path = temp_checkpoint_path("roundtrip");
+        let store = FileCheckpointStore::new(&path);
+
+        assert_eq!(store.load().unwrap(), None);
+        store.save(4321).unwrap();
+        assert_eq!(store.load().unwrap(), Some(4321));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_file_checkpoint_store_resumes_before_interrupted_block() {
+        let path = temp_checkpoint_path("interrupted");
+        let store = FileCheckpointStore::new(&path);
+
+        store.save(4321).unwrap();
+        store.begin_block(4322).unwrap();
+        assert_eq!(store.load().unwrap(), Some(4321));
+        store.save_block(4322, &[]).unwrap();
+        assert_eq!(store.load().unwrap(), Some(4322));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "done 4322");
+
+        // Checkpoints written before records were introduced hold a bare height.
+        std::fs::write(&path, "1234").unwrap();
+        assert_eq!(store.load().unwrap(), Some(1234));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_builder_resumes_from_checkpoint() {
+        let path = temp_checkpoint_path("resume");
+        FileCheckpointStore::new(&path).save(5678).unwrap();
+
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .method_name("nft_mint")
+            .last_processed_block(1234)
+            .checkpoint_store(Box::new(FileCheckpointStore::new(&path)))
+            .build()
+            .unwrap();
+
+        assert_eq!(listener.last_processed_block, 5678);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_builder_replays_last_blocks_of_checkpoint() {
+        let path = temp_checkpoint_path("replay");
+        FileCheckpointStore::new(&path).save(5678).unwrap();
+
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .checkpoint_store(Box::new(FileCheckpointStore::new(&path)))
+            .replay_last(100)
+            .build()
+            .unwrap();
+
+        assert_eq!(listener.last_processed_block, 5578);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_builder_start_height_ignores_checkpoint() {
+        let path = temp_checkpoint_path("start_height");
+        FileCheckpointStore::new(&path).save(5678).unwrap();
+
         let listener = NearEventListener::builder("http://rpc.testnet.near.org")
             .account_id("test.near")
+            .start_from(StartPosition::Height(1234))
+            .checkpoint_store(Box::new(FileCheckpointStore::new(&path)))
+            .build()
+            .unwrap();
+
+        assert_eq!(listener.last_processed_block, 1233);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_builder_start_after_tx_validates_hash() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .start_after_tx("not a hash", "deployer.near")
             .build();
 
         assert!(matches!(
             listener.unwrap_err(),
-            ListenerError::MissingField(field) if field == "method_name"
+            ListenerError::InvalidConfig(message) if message.contains("tx_hash")
         ));
     }
 
+    #[test]
+    fn test_file_checkpoint_store_invalid_contents() {
+        let path = temp_checkpoint_path("invalid");
+        std::fs::write(&path, "not a height").unwrap();
+
+        assert!(matches!(
+            FileCheckpointStore::new(&path).load().unwrap_err(),
+            ListenerError::CheckpointError(_)
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_file_checkpoint_store_revert_rewinds_height() {
+        let path = temp_checkpoint_path("revert");
+        let store = FileCheckpointStore::new(&path);
+
+        store.save(101).unwrap();
+        store.revert_to(99).unwrap();
+
+        assert_eq!(store.load().unwrap(), Some(99));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_checkpoint_store_keeps_sequence() {
+        let path = temp_checkpoint_path("sequence");
+        let store = FileCheckpointStore::new(&path);
+        assert_eq!(store.load_sequence().unwrap(), None);
+
+        store.save_progress(101, 7, &[]).unwrap();
+        store.begin_block(102).unwrap();
+        assert_eq!(store.load().unwrap(), Some(101));
+        assert_eq!(store.load_sequence().unwrap(), Some(7));
+
+        store.revert_to(99).unwrap();
+        assert_eq!(store.load().unwrap(), Some(99));
+        assert_eq!(store.load_sequence().unwrap(), Some(7));
+        std::fs::remove_file(&path).unwrap();
+    }
+
     // Tests for log processing
     #[test]
     fn test_process_log_success() {
@@ -56,6 +396,32 @@ mod tests {
         assert_eq!(event_log.event, "nft_mint");
     }
 
+    #[test]
+    fn test_parse_data_typed() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct NftMintData {
+            owner_id: String,
+            token_ids: Vec<String>,
+        }
+
+        let log = r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{"owner_id":"alice.near","token_ids":["1","2"]}]}"#;
+        let event_log = NearEventListener::process_log(log).unwrap();
+
+        let data: Vec<NftMintData> = event_log.parse_data().unwrap();
+        assert_eq!(
+            data,
+            vec![NftMintData {
+                owner_id: "alice.near".to_string(),
+                token_ids: vec!["1".to_string(), "2".to_string()],
+            }]
+        );
+
+        assert!(matches!(
+            event_log.parse_data::<Vec<u64>>().unwrap_err(),
+            ListenerError::JsonError(_)
+        ));
+    }
+
     #[test]
     fn test_process_log_invalid_format() {
         let log = "Invalid log format";
@@ -74,4 +440,387 @@ mod tests {
 
         assert!(matches!(result.unwrap_err(), ListenerError::JsonError(_)));
     }
+
+    #[test]
+    fn test_event_log_validate() {
+        let event_log = |version: &str, data: serde_json::Value| EventLog {
+            standard: "nep171".to_string(),
+            version: version.to_string(),
+            event: "nft_mint".to_string(),
+            data,
+        };
+
+        assert!(event_log("1.0.0", json!([{ "owner_id": "alice.near" }]))
+            .validate()
+            .is_ok());
+        for invalid in [
+            event_log("1.0", json!([{ "owner_id": "alice.near" }])),
+            event_log("1.0.0", json!({ "owner_id": "alice.near" })),
+            event_log("1.0.0", json!(["alice.near"])),
+        ] {
+            assert!(matches!(
+                invalid.validate(),
+                Err(ListenerError::NonCompliantEvent(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_event_registry_picks_decoder_by_version() {
+        let mut registry = EventRegistry::<String>::new();
+        registry
+            .register::<String>("nep171", "nft_mint", "^1.0")
+            .unwrap();
+        assert!(matches!(
+            registry.register::<String>("nep171", "nft_mint", "one"),
+            Err(ListenerError::InvalidConfig(_))
+        ));
+
+        let event_log = |version: &str, data: serde_json::Value| EventLog {
+            standard: "nep171".to_string(),
+            version: version.to_string(),
+            event: "nft_mint".to_string(),
+            data,
+        };
+        assert_eq!(
+            registry
+                .decode(&event_log("1.2.0", json!("minted")))
+                .unwrap()
+                .unwrap(),
+            "minted"
+        );
+        assert!(registry
+            .decode(&event_log("1.0.0", json!(1)))
+            .unwrap()
+            .is_err());
+        assert!(registry
+            .decode(&event_log("2.0.0", json!("minted")))
+            .is_none());
+        assert!(!registry.matches(&event_log("1.0", json!("minted"))));
+    }
+
+    // Tests for the event stream
+    #[tokio::test]
+    async fn test_stream_ends_after_error() {
+        let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+            .account_id("test.near")
+            .method_name("nft_mint")
+            .build()
+            .unwrap();
+
+        let mut events = listener.stream();
+
+        assert!(matches!(
+            events.next().await,
+            Some(Err(ListenerError::Transport(_)))
+        ));
+        assert!(events.next().await.is_none());
+    }
+
+    // Tests for async callbacks
+    #[tokio::test]
+    async fn test_start_async_returns_rpc_error() {
+        let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+            .account_id("test.near")
+            .method_name("nft_mint")
+            .build()
+            .unwrap();
+
+        let result = listener.start_async(|_envelope| async {}).await;
+
+        assert!(matches!(result.unwrap_err(), ListenerError::Transport(_)));
+    }
+
+    // Tests for error handling
+    #[tokio::test]
+    async fn test_on_error_continues_until_stop() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+
+        let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+            .account_id("test.near")
+            .poll_interval(Duration::from_millis(10))
+            .on_error(move |err| {
+                assert!(matches!(err, ListenerError::Transport(_)));
+                if handler_calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    ErrorAction::Continue
+                } else {
+                    ErrorAction::Stop
+                }
+            })
+            .build()
+            .unwrap();
+
+        let result = listener.start(|_envelope| {}).await;
+
+        assert!(matches!(result.unwrap_err(), ListenerError::Transport(_)));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    // Tests for dead letters
+    fn dead_letter(log: &str) -> DeadLetter {
+        DeadLetter {
+            context: EventContext {
+                account_id: "nft.near".to_string(),
+                block_height: 100,
+                block_hash: "4reLvkAWfqk5fsqio1KLudk46cqRz9erQdaHkWZKMJDZ".to_string(),
+                block_timestamp: 1_700_000_000_000_000_000,
+                tx_hash: None,
+                signer_id: "alice.near".to_string(),
+                receiver_id: "nft.near".to_string(),
+                method_name: None,
+                deposit: None,
+                gas: None,
+                execution_status: None,
+                receipt_id: Some("3NH2pBDfWKvBzKGDZzsaaL2RFvA2WC8FXWbZpvAfSrAy".to_string()),
+                log_index: 0,
+                sequence: None,
+                late: false,
+                receipt: None,
+                metrics: None,
+            },
+            log: log.to_string(),
+            error: "EOF while parsing an object".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_file_dead_letter_sink_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!(
+            "near_event_listener_dead_letters_{}.jsonl",
+            std::process::id()
+        ));
+        let sink = FileDeadLetterSink::new(&path);
+
+        sink.send(dead_letter("EVENT_JSON:{")).unwrap();
+        sink.send(dead_letter("EVENT_JSON:[]")).unwrap();
+
+        let letters: Vec<DeadLetter> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            letters,
+            vec![dead_letter("EVENT_JSON:{"), dead_letter("EVENT_JSON:[]")]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_channel_dead_letter_sink_fails_without_receiver() {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        sender.send(dead_letter("EVENT_JSON:{")).unwrap();
+        drop(receiver);
+
+        assert!(matches!(
+            DeadLetterSink::send(&sender, dead_letter("EVENT_JSON:{")),
+            Err(ListenerError::DeadLetterError(_))
+        ));
+    }
+
+    // Tests for retry policy
+    #[test]
+    fn test_retry_policy_backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1))
+            .jitter(false);
+
+        assert_eq!(policy.max_attempts(), 5);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_retry_policy_jitter_stays_within_bounds() {
+        let policy = RetryPolicy::new(3).initial_backoff(Duration::from_millis(200));
+
+        for attempt in 1..=3 {
+            let backoff = policy.backoff(attempt);
+            let ceiling = Duration::from_millis(200 << (attempt - 1));
+            assert!(backoff >= ceiling / 2 && backoff <= ceiling);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy_gives_up_after_max_attempts() {
+        let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+            .account_id("test.near")
+            .retry_policy(RetryPolicy::new(3).initial_backoff(Duration::from_millis(1)))
+            .build()
+            .unwrap();
+
+        let result = listener.start(|_envelope| {}).await;
+
+        assert!(matches!(result.unwrap_err(), ListenerError::Transport(_)));
+    }
+
+    // Tests for rate limiting
+    #[tokio::test]
+    async fn test_max_rps_queues_requests() {
+        let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+            .account_id("test.near")
+            .max_rps(2)
+            .retry_policy(
+                RetryPolicy::new(5)
+                    .initial_backoff(Duration::from_millis(1))
+                    .jitter(false),
+            )
+            .build()
+            .unwrap();
+
+        let started = std::time::Instant::now();
+        let result = listener.start(|_envelope| {}).await;
+
+        // Two requests fit in the initial burst, the other three wait 500ms each.
+        assert!(matches!(result.unwrap_err(), ListenerError::Transport(_)));
+        assert!(started.elapsed() >= Duration::from_millis(1400));
+    }
+
+    // Tests for lag monitoring
+    #[tokio::test]
+    async fn test_lag_returns_rpc_error_when_head_unavailable() {
+        let listener = NearEventListener::builder("http://127.0.0.1:1")
+            .account_id("test.near")
+            .on_lag(100, |_lag| {})
+            .build()
+            .unwrap();
+
+        let result = listener.lag().await;
+
+        assert!(matches!(result.unwrap_err(), ListenerError::Transport(_)));
+    }
+
+    // Tests for historical backfill
+    #[tokio::test]
+    async fn test_backfill_empty_range() {
+        let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+            .account_id("test.near")
+            .method_name("nft_mint")
+            .last_processed_block(100)
+            .build()
+            .unwrap();
+
+        listener.backfill(10, 5, |_envelope| {}).await.unwrap();
+
+        assert_eq!(listener.last_processed_block, 100);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_returns_rpc_error() {
+        let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+            .account_id("test.near")
+            .method_name("nft_mint")
+            .backfill_concurrency(4)
+            .build()
+            .unwrap();
+
+        let result = tokio::spawn(async move { listener.backfill(1, 20, |_envelope| {}).await })
+            .await
+            .unwrap();
+
+        assert!(matches!(result.unwrap_err(), ListenerError::Transport(_)));
+    }
+
+    // Tests for event filtering
+    fn nft_event(event: &str) -> EventLog {
+        EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: event.to_string(),
+            data: serde_json::json!([]),
+        }
+    }
+
+    #[test]
+    fn test_event_filter_default_matches_everything() {
+        assert!(EventFilter::new().matches(&nft_event("nft_mint")));
+    }
+
+    #[test]
+    fn test_event_filter_by_standard_and_event() {
+        let filter = EventFilter::new().standard("nep171").event("nft_mint");
+
+        assert!(filter.matches(&nft_event("nft_mint")));
+        assert!(!filter.matches(&nft_event("nft_burn")));
+        assert!(!EventFilter::new()
+            .standard("nep141")
+            .matches(&nft_event("nft_mint")));
+    }
+
+    #[test]
+    fn test_event_filter_accepts_alternatives() {
+        let filter = EventFilter::new()
+            .event("nft_mint")
+            .event("nft_transfer")
+            .version("1.0.0");
+
+        assert!(filter.matches(&nft_event("nft_mint")));
+        assert!(filter.matches(&nft_event("nft_transfer")));
+        assert!(!filter.matches(&nft_event("nft_burn")));
+        assert!(!EventFilter::new()
+            .version("2.0.0")
+            .matches(&nft_event("nft_mint")));
+    }
+
+    // Tests for method matching
+    #[test]
+    fn test_method_name_wildcards() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .method_names(&["nft_*", "ft_transfer?call"])
+            .build()
+            .unwrap();
+
+        assert!(listener.matches_method("nft_mint"));
+        assert!(listener.matches_method("nft_"));
+        assert!(listener.matches_method("ft_transfer_call"));
+        assert!(!listener.matches_method("ft_transfer"));
+        assert!(!listener.matches_method("storage_deposit"));
+    }
+
+    #[test]
+    fn test_method_matcher() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("test.near")
+            .method_matcher(|method_name| method_name.ends_with("_mint"))
+            .build()
+            .unwrap();
+
+        assert!(listener.matches_method("nft_mint"));
+        assert!(listener.matches_method("ft_mint"));
+        assert!(!listener.matches_method("nft_transfer"));
+    }
+
+    // Tests for account matching
+    #[test]
+    fn test_account_pattern() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_id("market.near")
+            .account_pattern("*.nft-factory.near")
+            .method_name("nft_mint")
+            .build()
+            .unwrap();
+
+        assert!(listener.matches_account("market.near"));
+        assert!(listener.matches_account("collection.nft-factory.near"));
+        assert!(!listener.matches_account("nft-factory.near"));
+        assert!(!listener.matches_account("collection.other-factory.near"));
+    }
+
+    #[test]
+    fn test_account_matcher() {
+        let listener = NearEventListener::builder("http://rpc.testnet.near.org")
+            .account_matcher(|account_id| account_id.starts_with("dao-"))
+            .method_name("act_proposal")
+            .build()
+            .unwrap();
+
+        assert!(listener.matches_account("dao-treasury.near"));
+        assert!(!listener.matches_account("treasury.near"));
+    }
 }
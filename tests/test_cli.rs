@@ -0,0 +1,92 @@
+#![cfg(all(feature = "cli", feature = "testing"))]
+
+use near_event_listener::testing::{MockBlockSource, MockFunctionCall};
+use near_event_listener::{EventEnvelope, EventLog};
+use serde_json::json;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+fn mint_event() -> EventLog {
+    EventLog {
+        standard: "nep171".to_string(),
+        version: "1.0.0".to_string(),
+        event: "nft_mint".to_string(),
+        data: json!([{ "owner_id": "alice.near", "token_ids": ["1"] }]),
+    }
+}
+
+// Runs the CLI against a chain with an `nft_mint` call in block 10 and an
+// `nft_burn` call in block 11, and returns what it wrote to its output file.
+async fn run_cli(extra_args: &[&str]) -> Vec<String> {
+    let mock = MockBlockSource::start().await.unwrap();
+    let event = mint_event();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&event),
+    );
+    mock.add_function_call(
+        11,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_burn").event(&event),
+    );
+
+    let output = std::env::temp_dir().join(format!(
+        "near_event_listener_cli_{}_{}.jsonl",
+        std::process::id(),
+        extra_args.len()
+    ));
+    let _ = std::fs::remove_file(&output);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_near-event-listener"))
+        .args([
+            "--rpc-url",
+            mock.url(),
+            "--account",
+            "nft.*",
+            "--method",
+            "nft_mint",
+        ])
+        .args(["--from-block", "10", "--output"])
+        .arg(&output)
+        .args(extra_args)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut lines = Vec::new();
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        lines = std::fs::read_to_string(&output)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        if !lines.is_empty() {
+            break;
+        }
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let _ = std::fs::remove_file(&output);
+    lines
+}
+
+#[tokio::test]
+async fn test_cli_writes_json_envelopes() {
+    let lines = run_cli(&[]).await;
+
+    assert_eq!(lines.len(), 1);
+    let envelope: EventEnvelope = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(envelope.event, mint_event());
+    assert_eq!(envelope.context.block_height, 10);
+    assert_eq!(envelope.context.account_id, "nft.near");
+}
+
+#[tokio::test]
+async fn test_cli_renders_template() {
+    let lines = run_cli(&[
+        "--template",
+        "{{ block_height }} {{ event }} {{ data[0].token_ids | join(',') }} by {{ signer_id }}",
+    ])
+    .await;
+
+    assert_eq!(lines, vec!["10 nft_mint 1 by alice.near"]);
+}
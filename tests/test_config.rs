@@ -0,0 +1,91 @@
+use near_event_listener::{ListenerError, NearEventListenerBuilder};
+#[cfg(feature = "config")]
+use near_event_listener::{EventFilter, Finality};
+#[cfg(feature = "config")]
+use std::path::PathBuf;
+
+#[cfg(feature = "config")]
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "near_event_listener_{}_{}",
+        name,
+        std::process::id()
+    ))
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_builder_from_config_file() {
+    let checkpoint_path = temp_path("config.checkpoint");
+    std::fs::write(&checkpoint_path, "555").unwrap();
+    let config_path = temp_path("listener.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            r#"
+rpc_url = "http://rpc.testnet.near.org"
+account_ids = ["nft.near", "market.near"]
+method_names = ["nft_mint"]
+standards = ["nep171"]
+finality = "optimistic"
+poll_interval_ms = 500
+checkpoint_path = {:?}
+"#,
+            checkpoint_path
+        ),
+    )
+    .unwrap();
+
+    let listener = NearEventListenerBuilder::from_config_file(&config_path)
+        .unwrap()
+        .build()
+        .unwrap();
+    let _ = std::fs::remove_file(&config_path);
+    let _ = std::fs::remove_file(&checkpoint_path);
+
+    assert_eq!(listener.account_ids, vec!["nft.near", "market.near"]);
+    assert_eq!(listener.method_names, vec!["nft_mint"]);
+    assert_eq!(listener.event_filter, EventFilter::new().standard("nep171"));
+    assert_eq!(listener.finality, Finality::None);
+    assert_eq!(listener.last_processed_block, 555);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_config_file_rejects_unknown_keys() {
+    let config_path = temp_path("unknown.toml");
+    std::fs::write(
+        &config_path,
+        "rpc_url = \"http://rpc.testnet.near.org\"\naccount_id = \"nft.near\"\n",
+    )
+    .unwrap();
+
+    let result = NearEventListenerBuilder::from_config_file(&config_path);
+    let _ = std::fs::remove_file(&config_path);
+
+    assert!(matches!(result, Err(ListenerError::InvalidConfig(_))));
+}
+
+#[test]
+fn test_builder_from_env() {
+    std::env::set_var("NEAR_EVENT_LISTENER_RPC_URL", "http://rpc.testnet.near.org");
+    std::env::set_var("NEAR_EVENT_LISTENER_ACCOUNT_IDS", "nft.near, market.near");
+    std::env::set_var("NEAR_EVENT_LISTENER_METHOD_NAMES", "nft_mint");
+    std::env::set_var("NEAR_EVENT_LISTENER_START_FROM", "1234");
+    std::env::set_var("NEAR_EVENT_LISTENER_MAX_RPS", "ten");
+
+    assert!(matches!(
+        NearEventListenerBuilder::from_env(),
+        Err(ListenerError::InvalidConfig(message)) if message.contains("NEAR_EVENT_LISTENER_MAX_RPS")
+    ));
+
+    std::env::set_var("NEAR_EVENT_LISTENER_MAX_RPS", "10");
+    let listener = NearEventListenerBuilder::from_env()
+        .unwrap()
+        .build()
+        .unwrap();
+
+    assert_eq!(listener.account_ids, vec!["nft.near", "market.near"]);
+    assert_eq!(listener.method_names, vec!["nft_mint"]);
+    assert_eq!(listener.last_processed_block, 1233);
+}
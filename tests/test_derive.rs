@@ -0,0 +1,124 @@
+use near_event_listener::{
+    EventLog, FtTransferLog, ListenerError, NearEvent, NearEventListener, Nep141Event, Nep171Event,
+    NftMintLog,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct TokenData {
+    owner_id: String,
+    token_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, NearEvent)]
+#[near_event(standard = "nep171", version = "1.0.0", event = "nft_mint")]
+struct NftMint(Vec<TokenData>);
+
+#[derive(Debug, PartialEq, NearEvent)]
+#[near_event(standard = "nep171")]
+enum NftEvent {
+    NftMint(Vec<TokenData>),
+    #[near_event(event = "nft_transfer")]
+    Transfer(Vec<serde_json::Value>),
+    ContractMetadataUpdate,
+}
+
+fn event_log(log: &str) -> EventLog {
+    NearEventListener::process_log(log).unwrap()
+}
+
+const MINT_LOG: &str = r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{"owner_id":"alice.near","token_ids":["1"]}]}"#;
+
+#[test]
+fn test_derive_struct_decodes_matching_event() {
+    let event_log = event_log(MINT_LOG);
+
+    assert!(NftMint::matches(&event_log));
+    assert_eq!(
+        NftMint::from_event_log(&event_log).unwrap(),
+        NftMint(vec![TokenData {
+            owner_id: "alice.near".to_string(),
+            token_ids: vec!["1".to_string()],
+        }])
+    );
+}
+
+#[test]
+fn test_derive_struct_rejects_mismatched_standard_and_version() {
+    let other_standard = event_log(
+        r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"nft_mint","data":[]}"#,
+    );
+    let other_version = event_log(
+        r#"EVENT_JSON:{"standard":"nep171","version":"2.0.0","event":"nft_mint","data":[]}"#,
+    );
+
+    assert!(!NftMint::matches(&other_standard));
+    assert!(!NftMint::matches(&other_version));
+    assert!(matches!(
+        NftMint::from_event_log(&other_standard).unwrap_err(),
+        ListenerError::InvalidEventFormat(_)
+    ));
+}
+
+#[test]
+fn test_derive_enum_dispatches_on_event_name() {
+    let transfer = event_log(
+        r#"EVENT_JSON:{"standard":"nep171","version":"1.2.0","event":"nft_transfer","data":[]}"#,
+    );
+    let metadata_update = event_log(
+        r#"EVENT_JSON:{"standard":"nep171","version":"1.1.0","event":"contract_metadata_update","data":[]}"#,
+    );
+    let burn = event_log(
+        r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_burn","data":[]}"#,
+    );
+
+    assert!(matches!(
+        NftEvent::from_event_log(&event_log(MINT_LOG)).unwrap(),
+        NftEvent::NftMint(tokens) if tokens.len() == 1
+    ));
+    assert_eq!(
+        NftEvent::from_event_log(&transfer).unwrap(),
+        NftEvent::Transfer(Vec::new())
+    );
+    assert_eq!(
+        NftEvent::from_event_log(&metadata_update).unwrap(),
+        NftEvent::ContractMetadataUpdate
+    );
+    assert!(!NftEvent::matches(&burn));
+}
+
+#[test]
+fn test_standard_nep171_events_decode() {
+    let mint = event_log(MINT_LOG);
+
+    assert!(Nep171Event::matches(&mint));
+    assert_eq!(
+        mint.as_nep171().unwrap(),
+        Nep171Event::NftMint(vec![NftMintLog {
+            owner_id: "alice.near".parse().unwrap(),
+            token_ids: vec!["1".to_string()],
+            memo: None,
+        }])
+    );
+    assert!(matches!(
+        mint.as_nep141(),
+        Err(ListenerError::InvalidEventFormat(_))
+    ));
+}
+
+#[test]
+fn test_standard_nep141_events_decode_amounts() {
+    let transfer = event_log(
+        r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{"old_owner_id":"alice.near","new_owner_id":"bob.near","amount":"340282366920938463463374607431768211455","memo":"rent"}]}"#,
+    );
+
+    assert_eq!(
+        transfer.as_nep141().unwrap(),
+        Nep141Event::FtTransfer(vec![FtTransferLog {
+            old_owner_id: "alice.near".parse().unwrap(),
+            new_owner_id: "bob.near".parse().unwrap(),
+            amount: u128::MAX,
+            memo: Some("rent".to_string()),
+        }])
+    );
+}
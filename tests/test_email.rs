@@ -0,0 +1,214 @@
+#![cfg(feature = "email")]
+
+use near_event_listener::{
+    EmailSink, EventContext, EventEnvelope, EventFilter, EventLog, EventTemplate, ListenerError,
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+// Accepts every message it is sent over plain SMTP and hands over the raw
+// message data.
+async fn serve() -> (String, mpsc::UnboundedReceiver<String>) {
+    serve_rejecting(0).await
+}
+
+// Like `serve`, but rejects the first `rejected` messages.
+async fn serve_rejecting(rejected: usize) -> (String, mpsc::UnboundedReceiver<String>) {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("smtp://{}", server.local_addr().unwrap());
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let rejected = Arc::new(AtomicUsize::new(rejected));
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = server.accept().await.unwrap();
+            let sender = sender.clone();
+            let rejected = rejected.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+                while let Some(line) = lines.next_line().await.unwrap() {
+                    let reply: &[u8] = match line.split(' ').next().unwrap() {
+                        "EHLO" | "HELO" => b"250 localhost\r\n",
+                        "DATA" => {
+                            writer.write_all(b"354 go ahead\r\n").await.unwrap();
+                            let mut data = String::new();
+                            while let Some(line) = lines.next_line().await.unwrap() {
+                                if line == "." {
+                                    break;
+                                }
+                                data.push_str(&line);
+                                data.push('\n');
+                            }
+                            let reject = rejected
+                                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                                    left.checked_sub(1)
+                                })
+                                .is_ok();
+                            if reject {
+                                b"451 try again later\r\n"
+                            } else {
+                                let _ = sender.send(data);
+                                b"250 OK\r\n"
+                            }
+                        }
+                        "QUIT" => {
+                            writer.write_all(b"221 bye\r\n").await.unwrap();
+                            break;
+                        }
+                        _ => b"250 OK\r\n",
+                    };
+                    writer.write_all(reply).await.unwrap();
+                }
+            });
+        }
+    });
+
+    (url, receiver)
+}
+
+fn envelope(event: &str, amount: &str) -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: "treasury.near".to_string(),
+            block_height: 10,
+            block_hash: "hash".to_string(),
+            block_timestamp: 0,
+            tx_hash: Some("tx".to_string()),
+            signer_id: "alice.near".to_string(),
+            receiver_id: "treasury.near".to_string(),
+            method_name: None,
+            deposit: None,
+            gas: None,
+            execution_status: None,
+            receipt_id: None,
+            log_index: 0,
+            sequence: None,
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep141".to_string(),
+            version: "1.0.0".to_string(),
+            event: event.to_string(),
+            data: json!([{ "amount": amount }]),
+        },
+    }
+}
+
+async fn next_message(receiver: &mut mpsc::UnboundedReceiver<String>) -> String {
+    tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_email_sink_sends_critical_events_and_digests() {
+    let (url, mut messages) = serve().await;
+    let sink = EmailSink::new(&url, "listener@example.com", &["ops@example.com"])
+        .unwrap()
+        .filter(EventFilter::new().standard("nep141"))
+        .critical(EventFilter::new().event("ft_burn"))
+        .subject(EventTemplate::new("ALERT {{ event }} on {{ account_id }}").unwrap())
+        .template(EventTemplate::new("{{ event }} of {{ data[0].amount }}").unwrap())
+        .max_digest_size(2);
+
+    sink.send(&envelope("ft_transfer", "1")).await.unwrap();
+    sink.send(&envelope("ft_burn", "500")).await.unwrap();
+
+    let alert = next_message(&mut messages).await;
+    assert!(alert.contains("Subject: ALERT ft_burn on treasury.near"));
+    assert!(alert.contains("To: ops@example.com"));
+    assert!(alert.contains("ft_burn of 500"));
+
+    // The second event fills the digest.
+    sink.send(&envelope("ft_transfer", "2")).await.unwrap();
+
+    let digest = next_message(&mut messages).await;
+    assert!(digest.contains("Subject: 2 NEAR events"));
+    assert!(digest.contains("ft_transfer of 1\nft_transfer of 2"));
+    assert!(messages.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_email_digest_is_sent_after_interval() {
+    let (url, mut messages) = serve().await;
+    let sink = EmailSink::new(&url, "listener@example.com", &["ops@example.com"])
+        .unwrap()
+        .template(EventTemplate::new("{{ event }}").unwrap())
+        .digest_interval(Duration::from_millis(50));
+
+    sink.send(&envelope("ft_transfer", "1")).await.unwrap();
+
+    let digest = next_message(&mut messages).await;
+    assert!(digest.contains("Subject: 1 NEAR event"));
+    assert!(digest.contains("ft_transfer"));
+}
+
+#[tokio::test]
+async fn test_failed_digest_is_kept_and_reported() {
+    let (url, mut messages) = serve_rejecting(1).await;
+    let sink = EmailSink::new(&url, "listener@example.com", &["ops@example.com"])
+        .unwrap()
+        .template(EventTemplate::new("{{ event }} of {{ data[0].amount }}").unwrap())
+        .max_digest_size(2)
+        .digest_interval(Duration::from_millis(50));
+
+    sink.send(&envelope("ft_transfer", "1")).await.unwrap();
+    assert!(sink.send(&envelope("ft_transfer", "2")).await.is_err());
+
+    // The rejected digest is retried once the interval has passed.
+    let digest = next_message(&mut messages).await;
+    assert!(digest.contains("ft_transfer of 1\nft_transfer of 2"));
+}
+
+#[tokio::test]
+async fn test_event_failing_to_render_does_not_break_the_digest() {
+    let (url, mut messages) = serve().await;
+    let sink = EmailSink::new(&url, "listener@example.com", &["ops@example.com"])
+        .unwrap()
+        .template(EventTemplate::new("{{ event }} of {{ data[0].amount | int }}").unwrap())
+        .max_digest_size(2);
+
+    sink.send(&envelope("ft_transfer", "1")).await.unwrap();
+    assert!(matches!(
+        sink.send(&envelope("ft_transfer", "lots")).await,
+        Err(ListenerError::TemplateError(_))
+    ));
+    sink.send(&envelope("ft_transfer", "2")).await.unwrap();
+
+    let digest = next_message(&mut messages).await;
+    assert!(digest.contains("Subject: 2 NEAR events"));
+    assert!(digest.contains("ft_transfer of 1\nft_transfer of 2"));
+}
+
+#[tokio::test]
+async fn test_digest_timer_failure_is_returned_by_next_send() {
+    let (url, mut messages) = serve_rejecting(1).await;
+    let sink = EmailSink::new(&url, "listener@example.com", &["ops@example.com"])
+        .unwrap()
+        .template(EventTemplate::new("{{ event }}").unwrap())
+        .digest_interval(Duration::from_millis(50));
+
+    sink.send(&envelope("ft_transfer", "1")).await.unwrap();
+
+    let digest = next_message(&mut messages).await;
+    assert!(digest.contains("ft_transfer"));
+    assert!(sink.send(&envelope("ft_transfer", "2")).await.is_err());
+    sink.flush().await.unwrap();
+    assert!(next_message(&mut messages).await.contains("ft_transfer"));
+}
+
+#[test]
+fn test_email_sink_rejects_invalid_addresses() {
+    assert!(EmailSink::new("smtp://localhost", "not an address", &["ops@example.com"]).is_err());
+    assert!(EmailSink::new("smtp://localhost", "listener@example.com", &[]).is_err());
+}
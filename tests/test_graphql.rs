@@ -0,0 +1,149 @@
+#![cfg(feature = "graphql")]
+
+use futures::{SinkExt, StreamExt};
+use near_event_listener::{
+    EventContext, EventEnvelope, EventLog, EventServer, EventSink, NearEventListener,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+
+fn envelope(account_id: &str, event: &str) -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: account_id.to_string(),
+            block_height: 64,
+            block_hash: "h".to_string(),
+            block_timestamp: 1,
+            tx_hash: Some("tx".to_string()),
+            signer_id: "a".to_string(),
+            receiver_id: "b".to_string(),
+            method_name: None,
+            deposit: None,
+            gas: None,
+            execution_status: None,
+            receipt_id: None,
+            log_index: 0,
+            sequence: None,
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: event.to_string(),
+            data: json!({"token_ids": ["1"]}),
+        },
+    }
+}
+
+async fn server() -> (EventServer, String) {
+    let listener = NearEventListener::builder("http://127.0.0.1:1")
+        .account_id("nft.near")
+        .build()
+        .unwrap();
+    let server = EventServer::new(listener.handle());
+    let (addr, _task) = server.serve("127.0.0.1:0").await.unwrap();
+    (server, format!("{}/graphql", addr))
+}
+
+#[tokio::test]
+async fn test_graphql_status_query() {
+    let (_server, url) = server().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}", url))
+        .header("content-type", "application/json")
+        .body(json!({"query": "{ status { state eventsEmitted } }"}).to_string())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let response: Value = serde_json::from_str(&response).unwrap();
+
+    assert_eq!(
+        response["data"]["status"],
+        json!({"state": "stopped", "eventsEmitted": 0})
+    );
+}
+
+#[tokio::test]
+async fn test_graphql_subscription_filters_events() {
+    let (server, url) = server().await;
+
+    let mut request = format!("ws://{}", url).into_client_request().unwrap();
+    request.headers_mut().insert(
+        "sec-websocket-protocol",
+        "graphql-transport-ws".parse().unwrap(),
+    );
+    let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+
+    socket
+        .send(Message::Text(
+            json!({"type": "connection_init"}).to_string(),
+        ))
+        .await
+        .unwrap();
+    let ack = socket.next().await.unwrap().unwrap().into_text().unwrap();
+    assert_eq!(
+        serde_json::from_str::<Value>(&ack).unwrap()["type"],
+        "connection_ack"
+    );
+
+    socket
+        .send(Message::Text(
+            json!({
+                "id": "1",
+                "type": "subscribe",
+                "payload": {
+                    "query": r#"subscription {
+                        events(accountId: "nft.near", event: "nft_mint") { accountId event data }
+                    }"#
+                }
+            })
+            .to_string(),
+        ))
+        .await
+        .unwrap();
+
+    // The subscription starts some time after the message is sent, so keep
+    // sending until an event gets through. Only the matching one may.
+    let sender = tokio::spawn(async move {
+        loop {
+            server
+                .send(&envelope("other.near", "nft_mint"))
+                .await
+                .unwrap();
+            server
+                .send(&envelope("nft.near", "nft_burn"))
+                .await
+                .unwrap();
+            server
+                .send(&envelope("nft.near", "nft_mint"))
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    });
+
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .into_text()
+        .unwrap();
+    sender.abort();
+
+    let message: Value = serde_json::from_str(&message).unwrap();
+    assert_eq!(message["type"], "next");
+    assert_eq!(message["id"], "1");
+    assert_eq!(
+        message["payload"]["data"]["events"],
+        json!({"accountId": "nft.near", "event": "nft_mint", "data": {"token_ids": ["1"]}})
+    );
+}
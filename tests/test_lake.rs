@@ -0,0 +1,192 @@
+#![cfg(feature = "lake")]
+
+use near_chain_configs::{GenesisConfig, ProtocolConfig, ProtocolConfigView};
+use near_event_listener::{DataSource, ListenerError, NearEventListener};
+use near_parameters::RuntimeConfig;
+use near_primitives::shard_layout::ShardLayout;
+use serde_json::{json, Value};
+use std::env;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+// Tests that change process-wide environment variables hold this lock.
+static ENV: Mutex<()> = Mutex::new(());
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/lake");
+
+fn lake_source(start_block: u64) -> DataSource {
+    DataSource::Lake {
+        s3_bucket: "near-lake-data-testnet".to_string(),
+        region: "eu-central-1".to_string(),
+        start_block,
+    }
+}
+
+// Serves the fixtures as a path-style S3 endpoint, and the protocol config of
+// a four-shard layout as the RPC endpoint. Returns its URL and the paths of
+// the objects read.
+async fn serve_lake() -> (String, Arc<Mutex<Vec<String>>>) {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    let objects = Arc::new(Mutex::new(Vec::new()));
+
+    let protocol_config = ProtocolConfigView::from(ProtocolConfig {
+        genesis_config: GenesisConfig {
+            shard_layout: ShardLayout::get_simple_nightshade_layout(),
+            ..GenesisConfig::default()
+        },
+        runtime_config: RuntimeConfig::test(),
+    });
+
+    let read = objects.clone();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = server.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).await.unwrap();
+            let mut length = 0;
+            let mut line = String::new();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).await.unwrap();
+                match line.trim_end().split_once(": ") {
+                    Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                        length = value.parse().unwrap()
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+
+            let target = request_line.split(' ').nth(1).unwrap();
+            let (path, query) = target.split_once('?').unwrap_or((target, ""));
+            let response = if request_line.starts_with("POST") {
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                Some(
+                    json!({ "jsonrpc": "2.0", "id": request["id"], "result": protocol_config })
+                        .to_string(),
+                )
+            } else if path == "/near-lake-data-testnet/" {
+                // Only block 100 is published.
+                let prefixes = if query.contains("start-after=000000000099") {
+                    "<CommonPrefixes><Prefix>000000000100/</Prefix></CommonPrefixes>"
+                } else {
+                    ""
+                };
+                Some(format!("<ListBucketResult>{}</ListBucketResult>", prefixes))
+            } else {
+                let key = path.trim_start_matches("/near-lake-data-testnet/");
+                read.lock().unwrap().push(key.to_string());
+                std::fs::read_to_string(Path::new(FIXTURES).join(key)).ok()
+            };
+
+            let (status, body) = match response {
+                Some(body) => ("200 OK", body),
+                None => ("404 Not Found", String::new()),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader
+                .into_inner()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+        }
+    });
+
+    (url, objects)
+}
+
+#[test]
+fn test_builder_lake_source() {
+    let _env = ENV.lock().unwrap();
+    env::remove_var("AWS_ACCESS_KEY_ID");
+    env::remove_var("AWS_SECRET_ACCESS_KEY");
+
+    let result = NearEventListener::builder("https://rpc.testnet.near.org")
+        .source(lake_source(100))
+        .account_id("account.testnet")
+        .build();
+    assert!(
+        matches!(result, Err(ListenerError::MissingField(field)) if field == "AWS_ACCESS_KEY_ID")
+    );
+
+    env::set_var("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE");
+    env::set_var(
+        "AWS_SECRET_ACCESS_KEY",
+        "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+    );
+
+    let listener = NearEventListener::builder("https://rpc.testnet.near.org")
+        .source(lake_source(100))
+        .account_id("account.testnet")
+        .build()
+        .unwrap();
+    assert_eq!(listener.last_processed_block, 99);
+
+    let listener = NearEventListener::builder("https://rpc.testnet.near.org")
+        .source(lake_source(100))
+        .account_id("account.testnet")
+        .last_processed_block(500)
+        .build()
+        .unwrap();
+    assert_eq!(listener.last_processed_block, 500);
+
+    let result = NearEventListener::builder("https://rpc.testnet.near.org")
+        .source(lake_source(100))
+        .account_id("account.testnet")
+        .shard_filter(true)
+        .build();
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn test_shard_filter_reads_only_the_watched_shard() {
+    let (url, objects) = serve_lake().await;
+
+    // `build` reads the environment, so the lock is released before polling.
+    let mut listener = {
+        let _env = ENV.lock().unwrap();
+        env::set_var("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE");
+        env::set_var(
+            "AWS_SECRET_ACCESS_KEY",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+        );
+        env::set_var("AWS_ENDPOINT_URL", &url);
+        let listener = NearEventListener::builder(&url)
+            .source(lake_source(100))
+            .account_id("art.testnet")
+            .shard_filter(true)
+            .max_events(1)
+            .build();
+        env::remove_var("AWS_ENDPOINT_URL");
+        listener.unwrap()
+    };
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let received = events.clone();
+    listener
+        .start(move |envelope| received.lock().unwrap().push(envelope))
+        .await
+        .unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].context.account_id, "art.testnet");
+    assert_eq!(events[0].context.block_height, 100);
+    // "art.testnet" sorts before "aurora", so it lives on shard 0.
+    assert_eq!(
+        *objects.lock().unwrap(),
+        vec!["000000000100/block.json", "000000000100/shard_0.json"]
+    );
+}
@@ -0,0 +1,172 @@
+#![cfg(feature = "notifier")]
+
+use near_event_listener::{
+    EventContext, EventEnvelope, EventFilter, EventLog, EventTemplate, ListenerError, NotifierSink,
+    RetryPolicy,
+};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+struct Request {
+    path: String,
+    body: Value,
+}
+
+// Answers one request per connection with the given statuses, in order.
+async fn serve(statuses: Vec<u16>) -> (String, JoinHandle<Vec<Request>>) {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for status in statuses {
+            let (stream, _) = server.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let path = line.split(' ').nth(1).unwrap().to_string();
+            let mut length = 0;
+            loop {
+                line.clear();
+                reader.read_line(&mut line).await.unwrap();
+                match line.trim_end().split_once(": ") {
+                    Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                        length = value.parse().unwrap()
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+
+            let response = format!(
+                "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            reader
+                .into_inner()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+            requests.push(Request {
+                path,
+                body: serde_json::from_slice(&body).unwrap(),
+            });
+        }
+        requests
+    });
+
+    (url, handle)
+}
+
+fn envelope(event: &str) -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: "nft.near".to_string(),
+            block_height: 10,
+            block_hash: "hash".to_string(),
+            block_timestamp: 0,
+            tx_hash: Some("tx".to_string()),
+            signer_id: "alice.near".to_string(),
+            receiver_id: "nft.near".to_string(),
+            method_name: None,
+            deposit: None,
+            gas: None,
+            execution_status: None,
+            receipt_id: None,
+            log_index: 0,
+            sequence: None,
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: event.to_string(),
+            data: json!([{ "owner_id": "alice.near", "token_ids": ["1", "2"] }]),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_discord_notifier_posts_matching_events() {
+    let (url, server) = serve(vec![429, 204]).await;
+    let retries = Arc::new(AtomicU32::new(0));
+    let sink = NotifierSink::discord(&format!("{}/api/webhooks/1/token", url))
+        .unwrap()
+        .filter(EventFilter::new().event("nft_mint"))
+        .template(
+            EventTemplate::new(
+                "{{ data[0].owner_id }} minted {{ data[0].token_ids | join(', ') }}",
+            )
+            .unwrap(),
+        )
+        .retry_policy(
+            RetryPolicy::new(2)
+                .initial_backoff(Duration::from_millis(1))
+                .jitter(false),
+        )
+        .on_retry({
+            let retries = retries.clone();
+            move |_, _| {
+                retries.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+    sink.send(&envelope("nft_burn")).await.unwrap();
+    sink.send(&envelope("nft_mint")).await.unwrap();
+    assert_eq!(retries.load(Ordering::Relaxed), 1);
+
+    let requests = server.await.unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].path, "/api/webhooks/1/token");
+    assert_eq!(
+        requests[1].body,
+        json!({"content": "alice.near minted 1, 2"})
+    );
+}
+
+#[tokio::test]
+async fn test_slack_and_telegram_payloads() {
+    let (url, server) = serve(vec![200, 200]).await;
+
+    NotifierSink::slack(&format!("{}/services/T0/B0/x", url))
+        .unwrap()
+        .template(EventTemplate::new("{{ event }}").unwrap())
+        .send(&envelope("nft_mint"))
+        .await
+        .unwrap();
+    NotifierSink::telegram_with_api(&url, "123:abc", "-100")
+        .unwrap()
+        .template(EventTemplate::new("{{ event }}").unwrap())
+        .send(&envelope("nft_mint"))
+        .await
+        .unwrap();
+
+    let requests = server.await.unwrap();
+    assert_eq!(requests[0].body, json!({"text": "nft_mint"}));
+    assert_eq!(requests[1].path, "/bot123:abc/sendMessage");
+    assert_eq!(
+        requests[1].body,
+        json!({"chat_id": "-100", "text": "nft_mint"})
+    );
+}
+
+#[tokio::test]
+async fn test_notifier_does_not_retry_client_errors() {
+    let (url, server) = serve(vec![404]).await;
+    let sink = NotifierSink::slack(&url).unwrap();
+
+    let err = sink.send(&envelope("nft_mint")).await.unwrap_err();
+
+    assert!(matches!(err, ListenerError::SinkError(message) if message.contains("Slack")));
+    assert_eq!(server.await.unwrap().len(), 1);
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "postgres")]
+
+use near_event_listener::{EventContext, EventEnvelope, EventLog, EventSink, PostgresSink};
+use serde_json::json;
+
+// The sink needs a running server, e.g.
+// `NEAR_EVENT_LISTENER_TEST_POSTGRES=postgres://postgres@localhost/postgres`.
+// Without one the test does nothing.
+fn test_database() -> Option<String> {
+    std::env::var("NEAR_EVENT_LISTENER_TEST_POSTGRES").ok()
+}
+
+fn envelope(tx_hash: Option<&str>, log_index: usize) -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: "nft.near".to_string(),
+            block_height: 100,
+            block_hash: "4reLvkAWfqk5fsqio1KLudk46cqRz9erQdaHkWZKMJDZ".to_string(),
+            block_timestamp: 1_700_000_000_000_000_000,
+            tx_hash: tx_hash.map(str::to_string),
+            signer_id: "alice.near".to_string(),
+            receiver_id: "nft.near".to_string(),
+            method_name: None,
+            deposit: None,
+            gas: None,
+            execution_status: None,
+            receipt_id: None,
+            log_index,
+            sequence: None,
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: "nft_mint".to_string(),
+            data: json!([{ "token_ids": ["1"] }]),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_postgres_sink_ignores_duplicates() {
+    let Some(config) = test_database() else {
+        return;
+    };
+    let table = format!("near_events_test_{}", std::process::id());
+    let sink = PostgresSink::connect_to_table(&config, &table)
+        .await
+        .unwrap();
+
+    // Receipt-scanned events have no transaction hash and still dedupe.
+    for _ in 0..2 {
+        for envelope in [
+            envelope(Some("9fDRQbTe3Xt4Brb2GXg5SiwN3eMCKE5yUA2XDUsC2a4H"), 0),
+            envelope(Some("9fDRQbTe3Xt4Brb2GXg5SiwN3eMCKE5yUA2XDUsC2a4H"), 1),
+            envelope(None, 0),
+        ] {
+            sink.send(&envelope).await.unwrap();
+        }
+    }
+
+    let (client, connection) = tokio_postgres::connect(&config, tokio_postgres::NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(connection);
+    let rows = client
+        .query(
+            &format!("SELECT data, tx_hash FROM {} ORDER BY id", table),
+            &[],
+        )
+        .await
+        .unwrap();
+    client
+        .batch_execute(&format!("DROP TABLE {}", table))
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(
+        rows[0].get::<_, serde_json::Value>(0),
+        json!([{ "token_ids": ["1"] }])
+    );
+    assert_eq!(rows[2].get::<_, Option<String>>(1), None);
+}
+
+#[tokio::test]
+async fn test_postgres_sink_rejects_invalid_table_names() {
+    let Some(config) = test_database() else {
+        return;
+    };
+    for table in ["events; DROP TABLE x", "a.b.c", "1events"] {
+        assert!(matches!(
+            PostgresSink::connect_to_table(&config, table).await,
+            Err(near_event_listener::ListenerError::InvalidConfig(_))
+        ));
+    }
+}
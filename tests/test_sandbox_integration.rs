@@ -125,7 +125,7 @@ impl ContractWrapper {
                 println!("Using nonce: {}", new_nonce);
                 Ok((new_nonce, access_key_query_response.block_hash))
             }
-            _ => Err(anyhow::anyhow!("Failed to extract current nonce").into()),
+            _ => Err(anyhow::anyhow!("Failed to extract current nonce")),
         }
     }
 
@@ -231,47 +231,22 @@ async fn test_integration_using_sandbox() -> anyhow::Result<()> {
         .set_greeting("Hello, World!".to_string(), "set_greeting".to_string())
         .await?;
 
-    // Obtain block height from the transaction
-    let block_height = if let Some(final_execution) = &tx_result.final_execution_outcome {
-        match final_execution {
-            near_primitives::views::FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(
-                outcome,
-            ) => {
-                let block = contract_wrapper
-                    .rpc_client
-                    .call(methods::block::RpcBlockRequest {
-                        block_reference: BlockReference::BlockId(
-                            near_primitives::types::BlockId::Hash(
-                                outcome.transaction_outcome.block_hash,
-                            ),
-                        ),
-                    })
-                    .await?;
-                block.header.height
-            }
-            _ => 0,
-        }
-    } else {
-        0
+    let Some(near_primitives::views::FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome)) =
+        &tx_result.final_execution_outcome
+    else {
+        anyhow::bail!("Missing transaction outcome");
     };
 
-    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
-    let tx_clone = tx.clone();
-
-    let mut listener = NearEventListener::builder(&contract_wrapper.worker.rpc_addr())
-        .account_id(&account_id.as_str())
+    let listener = NearEventListener::builder(&contract_wrapper.worker.rpc_addr())
+        .account_id(account_id.as_str())
         .method_name("set_greeting")
-        .last_processed_block(block_height - 1)
+        .start_after_tx(
+            &outcome.transaction.hash.to_string(),
+            outcome.transaction.signer_id.as_str(),
+        )
         .build()?;
 
-    let listener_handle = tokio::spawn(async move {
-        listener
-            .start(move |event_log| {
-                println!("Captured event: {:?}", event_log);
-                let _ = tx_clone.try_send(event_log.clone());
-            })
-            .await
-    });
+    let (mut rx, listener_handle) = listener.channel(1);
 
     let expected_event = EventLog {
         standard: "nep171".to_string(),
@@ -288,8 +263,9 @@ async fn test_integration_using_sandbox() -> anyhow::Result<()> {
         .map_err(|_| anyhow::anyhow!("Timeout waiting for event"))?
         .ok_or_else(|| anyhow::anyhow!("Channel closed"))?;
 
+    assert_eq!(received_event.context.account_id, account_id.as_str());
     assert_eq!(
-        received_event, expected_event,
+        received_event.event, expected_event,
         "El evento recibido no coincide con el esperado"
     );
 
@@ -0,0 +1,97 @@
+#![cfg(feature = "http-server")]
+
+use near_event_listener::{
+    EventContext, EventEnvelope, EventLog, EventServer, EventSink, NearEventListener,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn envelope() -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: "nft.near".to_string(),
+            block_height: 64,
+            block_hash: "h".to_string(),
+            block_timestamp: 1,
+            tx_hash: Some("tx".to_string()),
+            signer_id: "a".to_string(),
+            receiver_id: "b".to_string(),
+            method_name: None,
+            deposit: None,
+            gas: None,
+            execution_status: None,
+            receipt_id: None,
+            log_index: 0,
+            sequence: Some(7),
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: "nft_mint".to_string(),
+            data: json!({"token_ids": ["1"]}),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_server_streams_events_and_reports_status() {
+    let listener = NearEventListener::builder("http://127.0.0.1:1")
+        .account_id("nft.near")
+        .build()
+        .unwrap();
+    let server = EventServer::new(listener.handle());
+    let (addr, _task) = server.serve("127.0.0.1:0").await.unwrap();
+    let client = reqwest::Client::new();
+
+    let status: Value = serde_json::from_str(
+        &client
+            .get(format!("http://{}/status", addr))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    // The listener was never started.
+    assert_eq!(status["state"], "stopped");
+    assert_eq!(status["events_emitted"], 0);
+
+    let mut events = client
+        .get(format!("http://{}/events", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        events.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    // The client only receives events sent after it connected.
+    server.send(&envelope()).await.unwrap();
+
+    let mut received = String::new();
+    while !received.ends_with("\n\n") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), events.chunk())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+
+    let lines: Vec<&str> = received.lines().collect();
+    assert!(lines.contains(&"event: nft_mint"));
+    assert!(lines.contains(&"id: 7"));
+    let data = lines
+        .iter()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let data: Value = serde_json::from_str(data).unwrap();
+    assert_eq!(data["event"]["data"], json!({"token_ids": ["1"]}));
+    assert_eq!(data["context"]["tx_hash"], "tx");
+}
@@ -0,0 +1,113 @@
+use near_event_listener::{
+    render_topic, EventContext, EventEnvelope, EventLog, EventSink, FileSink, SinkFormat, SinkKey,
+};
+use serde_json::json;
+
+fn envelope(tx_hash: Option<&str>) -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: "nft.near".to_string(),
+            block_height: 64,
+            block_hash: "h".to_string(),
+            block_timestamp: 1,
+            tx_hash: tx_hash.map(str::to_string),
+            signer_id: "a".to_string(),
+            receiver_id: "b".to_string(),
+            method_name: None,
+            deposit: None,
+            gas: None,
+            execution_status: None,
+            receipt_id: None,
+            log_index: 2,
+            sequence: None,
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep171".to_string(),
+            version: "1".to_string(),
+            event: "nft_mint".to_string(),
+            data: json!(1),
+        },
+    }
+}
+
+#[test]
+fn test_sink_key_and_topic() {
+    let envelope = envelope(None);
+
+    assert_eq!(
+        SinkKey::AccountId.key(&envelope),
+        Some("nft.near".to_string())
+    );
+    assert_eq!(
+        SinkKey::EventName.key(&envelope),
+        Some("nft_mint".to_string())
+    );
+    assert_eq!(SinkKey::None.key(&envelope), None);
+    assert_eq!(
+        render_topic("near.{account_id}.{standard}.{event}", &envelope),
+        "near.nft.near.nep171.nft_mint"
+    );
+}
+
+#[test]
+fn test_avro_encoding_matches_schema() {
+    let encoded = SinkFormat::Avro.encode(&envelope(Some("tx"))).unwrap();
+
+    let mut expected = vec![16];
+    expected.extend_from_slice(b"nft.near");
+    // block_height 64 is zigzag encoded as 128, which takes two bytes.
+    expected.extend_from_slice(&[0x80, 0x01, 2, b'h', 2]);
+    expected.extend_from_slice(&[2, 4, b't', b'x']);
+    expected.extend_from_slice(&[2, b'a', 2, b'b', 0, 4]);
+    expected.extend_from_slice(b"\x0cnep171\x021\x10nft_mint\x021");
+    assert_eq!(encoded, expected);
+
+    let json = SinkFormat::Json.encode(&envelope(None)).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<EventEnvelope>(&json).unwrap(),
+        envelope(None)
+    );
+}
+
+#[tokio::test]
+async fn test_file_sink_writes_ndjson_and_rotates_by_size() {
+    let dir = std::env::temp_dir().join(format!(
+        "near_event_listener_file_sink_{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let line_len = serde_json::to_vec(&envelope(None)).unwrap().len() as u64 + 1;
+    let sink = FileSink::new(dir.join("events.jsonl")).max_bytes(line_len * 2);
+    for _ in 0..5 {
+        sink.send(&envelope(None)).await.unwrap();
+    }
+
+    let mut files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    assert_eq!(files.len(), 3);
+    assert!(files.contains(&dir.join("events.jsonl")));
+
+    let mut lines = 0;
+    for file in &files {
+        assert_eq!(file.extension().unwrap(), "jsonl");
+        let contents = std::fs::read_to_string(file).unwrap();
+        assert!(contents.len() as u64 <= line_len * 2);
+        for line in contents.lines() {
+            assert_eq!(
+                serde_json::from_str::<EventEnvelope>(line).unwrap(),
+                envelope(None)
+            );
+            lines += 1;
+        }
+    }
+    assert_eq!(lines, 5);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "sqlite")]
+
+use near_event_listener::{
+    CheckpointStore, DeliveredEvent, EventContext, EventEnvelope, EventLog, SqliteStore,
+};
+use serde_json::json;
+
+fn envelope(block_height: u64, event: &str) -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: "nft.near".to_string(),
+            block_height,
+            block_hash: "4reLvkAWfqk5fsqio1KLudk46cqRz9erQdaHkWZKMJDZ".to_string(),
+            block_timestamp: 1_700_000_000_000_000_000,
+            tx_hash: Some("9fDRQbTe3Xt4Brb2GXg5SiwN3eMCKE5yUA2XDUsC2a4H".to_string()),
+            signer_id: "alice.near".to_string(),
+            receiver_id: "nft.near".to_string(),
+            method_name: None,
+            deposit: None,
+            gas: None,
+            execution_status: None,
+            receipt_id: Some("3NH2pBDfWKvBzKGDZzsaaL2RFvA2WC8FXWbZpvAfSrAy".to_string()),
+            log_index: 0,
+            sequence: None,
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: event.to_string(),
+            data: json!([{ "token_ids": ["1"] }]),
+        },
+    }
+}
+
+#[test]
+fn test_sqlite_store_checkpoint_roundtrip() {
+    let store = SqliteStore::open_in_memory().unwrap();
+
+    assert_eq!(store.load().unwrap(), None);
+    store.save(100).unwrap();
+    store.save(101).unwrap();
+    assert_eq!(store.load().unwrap(), Some(101));
+}
+
+#[test]
+fn test_sqlite_store_archives_events_with_block() {
+    let store = SqliteStore::open_in_memory().unwrap();
+    let events = vec![envelope(200, "nft_mint"), envelope(200, "nft_transfer")];
+
+    store.save_block(200, &events).unwrap();
+
+    assert_eq!(store.load().unwrap(), Some(200));
+    assert_eq!(store.events().unwrap(), events);
+}
+
+#[test]
+fn test_sqlite_store_archives_receipt_events_without_tx_hash() {
+    let store = SqliteStore::open_in_memory().unwrap();
+    let mut event = envelope(300, "nft_transfer");
+    event.context.tx_hash = None;
+    event.context.signer_id = "market.near".to_string();
+
+    store.save_block(300, std::slice::from_ref(&event)).unwrap();
+
+    assert_eq!(store.events().unwrap(), vec![event]);
+}
+
+#[test]
+fn test_sqlite_store_revert_drops_orphaned_events() {
+    let store = SqliteStore::open_in_memory().unwrap();
+    let kept = envelope(400, "nft_mint");
+
+    store.save_block(400, std::slice::from_ref(&kept)).unwrap();
+    store
+        .save_block(401, &[envelope(401, "nft_transfer")])
+        .unwrap();
+    store.revert_to(400).unwrap();
+
+    assert_eq!(store.load().unwrap(), Some(400));
+    assert_eq!(store.events().unwrap(), vec![kept]);
+}
+
+#[test]
+fn test_sqlite_store_saves_sequence_with_block() {
+    let store = SqliteStore::open_in_memory().unwrap();
+    assert_eq!(store.load_sequence().unwrap(), None);
+
+    store
+        .save_progress(500, 12, &[envelope(500, "nft_mint")])
+        .unwrap();
+    store.save(501).unwrap();
+
+    assert_eq!(store.load().unwrap(), Some(501));
+    assert_eq!(store.load_sequence().unwrap(), Some(12));
+}
+
+#[test]
+fn test_sqlite_store_keeps_delivered_events_of_block_in_progress() {
+    let store = SqliteStore::open_in_memory().unwrap();
+    let delivered = DeliveredEvent::from(&envelope(500, "nft_mint").context);
+
+    store.begin_block(500).unwrap();
+    store.save_delivered(500, &delivered).unwrap();
+    // A restart begins the interrupted block again.
+    store.begin_block(500).unwrap();
+    assert_eq!(store.load_delivered().unwrap(), vec![delivered]);
+
+    store.save_progress(500, 1, &[]).unwrap();
+    assert!(store.load_delivered().unwrap().is_empty());
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "templates")]
+
+use near_event_listener::{EventContext, EventEnvelope, EventLog, EventTemplate, ListenerError};
+use serde_json::json;
+
+fn envelope() -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: "nft.near".to_string(),
+            block_height: 10,
+            block_hash: "hash".to_string(),
+            block_timestamp: 0,
+            tx_hash: Some("tx".to_string()),
+            signer_id: "alice.near".to_string(),
+            receiver_id: "nft.near".to_string(),
+            method_name: Some("nft_mint".to_string()),
+            deposit: Some(10u128.pow(24)),
+            gas: None,
+            execution_status: None,
+            receipt_id: None,
+            log_index: 0,
+            sequence: None,
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: "nft_mint".to_string(),
+            data: json!([{ "owner_id": "alice.near", "token_ids": ["1", "2"] }]),
+        },
+    }
+}
+
+#[test]
+fn test_template_renders_event_fields() {
+    let template = EventTemplate::new(
+        "{{ data[0].owner_id }} minted {{ data[0].token_ids | join(', ') }} on {{ account_id }} \
+         in {{ tx_hash }} for {{ deposit }}{% if receipt_id %} ({{ receipt_id }}){% endif %}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        template.render(&envelope()).unwrap(),
+        "alice.near minted 1, 2 on nft.near in tx for 1000000000000000000000000"
+    );
+}
+
+#[test]
+fn test_template_renders_json() {
+    let template = EventTemplate::new(
+        r#"{"text": {{ (event ~ " " ~ standard) | tojson }}, "data": {{ data | tojson }}}"#,
+    )
+    .unwrap();
+
+    let rendered: serde_json::Value =
+        serde_json::from_str(&template.render(&envelope()).unwrap()).unwrap();
+    assert_eq!(
+        rendered,
+        json!({
+            "text": "nft_mint nep171",
+            "data": [{ "owner_id": "alice.near", "token_ids": ["1", "2"] }]
+        })
+    );
+    assert_eq!(
+        EventTemplate::new("{{ envelope.event.version }}")
+            .unwrap()
+            .render(&envelope())
+            .unwrap(),
+        "1.0.0"
+    );
+}
+
+#[test]
+fn test_template_errors() {
+    assert!(matches!(
+        EventTemplate::new("{{ event"),
+        Err(ListenerError::InvalidConfig(_))
+    ));
+    // Unknown filters are only found when rendering.
+    assert!(matches!(
+        EventTemplate::new("{{ event | no_such_filter }}")
+            .unwrap()
+            .render(&envelope()),
+        Err(ListenerError::TemplateError(_))
+    ));
+    // Undefined variables render as empty.
+    assert_eq!(
+        EventTemplate::new("[{{ no_such_field }}]")
+            .unwrap()
+            .render(&envelope())
+            .unwrap(),
+        "[]"
+    );
+}
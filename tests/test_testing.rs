@@ -0,0 +1,2739 @@
+#![cfg(feature = "testing")]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use futures::future::{self, BoxFuture};
+use futures::StreamExt;
+use near_event_listener::testing::{
+    MockBlockSource, MockFunctionCall, PlaybackBlockSource, RecordingBlockSource,
+};
+use near_event_listener::{
+    contains, equals, AccountChangeKind, BorshLogDecoder, CheckpointStore, DeadLetter,
+    DeliveryMode, ErrorAction, EventEnvelope, EventFilter, EventLog, EventMiddleware,
+    EventNotification, EventRegistry, EventSink, ExecutionStatus, ExecutionStatusFilter,
+    ExecutorFilter, FileCheckpointStore, HealthCheck, ListenerError, ListenerEvent,
+    ListenerSnapshot, ListenerState, LogSource, MultiNetworkListener, NearEventListener, Next,
+    OverflowPolicy, RetryPolicy, Runtime, StartPosition, Subscription, TokioRuntime,
+    TxExecutionStatus, WindowSize,
+};
+use near_jsonrpc_client::methods::tx::RpcTransactionResponse;
+use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_primitives::types::{BlockId, BlockReference};
+use near_primitives::views::{
+    ExecutionStatusView, FinalExecutionOutcomeViewEnum, FinalExecutionOutcomeWithReceiptView,
+};
+use near_sdk::base64::engine::general_purpose::STANDARD;
+use near_sdk::base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+fn nft_mint(token_id: &str) -> EventLog {
+    EventLog {
+        standard: "nep171".to_string(),
+        version: "1.0.0".to_string(),
+        event: "nft_mint".to_string(),
+        data: json!([{ "owner_id": "alice.near", "token_ids": [token_id] }]),
+    }
+}
+
+#[tokio::test]
+async fn test_mock_block_source_delivers_events() {
+    let mock = MockBlockSource::start().await.unwrap();
+    let tx_hash = mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "other.near", "nft_mint").event(&nft_mint("2")),
+    );
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("bob.near", "nft.near", "nft_mint")
+            .log("not an event")
+            .event(&nft_mint("3")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+
+    let envelopes: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.stream().take(2).collect::<Vec<_>>(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .unwrap();
+
+    assert_eq!(envelopes[0].event, nft_mint("1"));
+    assert_eq!(envelopes[0].context.block_height, 10);
+    assert_eq!(envelopes[0].context.tx_hash, Some(tx_hash.to_string()));
+    assert_eq!(envelopes[0].context.signer_id, "alice.near");
+    assert_eq!(envelopes[0].context.account_id, "nft.near");
+
+    assert_eq!(envelopes[1].event, nft_mint("3"));
+    assert_eq!(envelopes[1].context.block_height, 12);
+    assert_eq!(envelopes[1].context.signer_id, "bob.near");
+    assert_eq!(envelopes[1].context.log_index, 1);
+    assert_eq!(listener.last_processed_block, 12);
+}
+
+async fn backfill_nft_events(rpc_url: &str) -> Vec<EventEnvelope> {
+    let mut listener = NearEventListener::builder(rpc_url)
+        .account_id("nft.near")
+        .build()
+        .unwrap();
+    let mut envelopes = Vec::new();
+    listener
+        .backfill(10, 12, |envelope| envelopes.push(envelope))
+        .await
+        .unwrap();
+    envelopes
+}
+
+#[tokio::test]
+async fn test_recorded_fixtures_play_back_offline() {
+    let dir = std::env::temp_dir().join(format!(
+        "near_event_listener_fixtures_{}",
+        std::process::id()
+    ));
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("bob.near", "nft.near", "nft_mint").event(&nft_mint("3")),
+    );
+
+    let recorder = RecordingBlockSource::start(mock.url(), &dir).await.unwrap();
+    let recorded = backfill_nft_events(recorder.url()).await;
+    assert_eq!(recorded.len(), 3);
+    drop(recorder);
+    drop(mock);
+
+    let playback = PlaybackBlockSource::start(&dir).await.unwrap();
+    for _ in 0..2 {
+        assert_eq!(backfill_nft_events(playback.url()).await, recorded);
+    }
+
+    let unrecorded = JsonRpcClient::connect(playback.url())
+        .call(methods::block::RpcBlockRequest {
+            block_reference: BlockReference::BlockId(BlockId::Height(99)),
+        })
+        .await;
+    assert!(unrecorded.is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+async fn backfill_twice(mock: &MockBlockSource, delivery: DeliveryMode) -> usize {
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .delivery(delivery)
+        .build()
+        .unwrap();
+
+    let mut delivered = 0;
+    for _ in 0..2 {
+        listener
+            .backfill(10, 11, |_envelope| delivered += 1)
+            .await
+            .unwrap();
+    }
+    delivered
+}
+
+#[tokio::test]
+async fn test_exactly_once_skips_refetched_events() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    mock.add_empty_block(11);
+
+    assert_eq!(backfill_twice(&mock, DeliveryMode::AtLeastOnce).await, 4);
+    assert_eq!(backfill_twice(&mock, DeliveryMode::ExactlyOnce).await, 2);
+}
+
+#[tokio::test]
+async fn test_malformed_events_go_to_dead_letter_sink() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .log(r#"EVENT_JSON:{"standard":"nep171""#)
+            .event(&nft_mint("1")),
+    );
+
+    let letters = Arc::new(Mutex::new(Vec::new()));
+    let sink_letters = letters.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .dead_letter_sink(Box::new(move |letter: DeadLetter| {
+            sink_letters.lock().unwrap().push(letter)
+        }))
+        .build()
+        .unwrap();
+
+    let mut delivered = Vec::new();
+    listener
+        .backfill(10, 10, |envelope| delivered.push(envelope))
+        .await
+        .unwrap();
+
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(delivered[0].context.log_index, 1);
+
+    let letters = letters.lock().unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].log, r#"EVENT_JSON:{"standard":"nep171""#);
+    assert_eq!(letters[0].context.log_index, 0);
+    assert_eq!(letters[0].context.block_height, 10);
+    assert!(!letters[0].error.is_empty());
+}
+
+#[tokio::test]
+async fn test_strict_validation_rejects_non_compliant_events() {
+    let loose = EventLog {
+        version: "1".to_string(),
+        ..nft_mint("2")
+    };
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&loose),
+    );
+
+    let letters = Arc::new(Mutex::new(Vec::new()));
+    let sink_letters = letters.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .strict_validation(true)
+        .dead_letter_sink(Box::new(move |letter: DeadLetter| {
+            sink_letters.lock().unwrap().push(letter)
+        }))
+        .build()
+        .unwrap();
+
+    let mut delivered = Vec::new();
+    listener
+        .backfill(10, 10, |envelope| delivered.push(envelope))
+        .await
+        .unwrap();
+
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(delivered[0].event, nft_mint("1"));
+    let letters = letters.lock().unwrap();
+    assert_eq!(letters.len(), 1);
+    assert!(letters[0].error.contains("NEP-297"));
+}
+
+#[tokio::test]
+async fn test_raw_logs_are_delivered_in_order() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .log("minting token 1")
+            .event(&nft_mint("1")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .include_raw_logs(true)
+        .build()
+        .unwrap();
+
+    let notifications: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.notifications().take(2).collect::<Vec<_>>(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .unwrap();
+
+    match &notifications[0] {
+        EventNotification::RawLog(raw_log) => {
+            assert_eq!(raw_log.log, "minting token 1");
+            assert_eq!(raw_log.context.log_index, 0);
+            assert_eq!(raw_log.context.signer_id, "alice.near");
+        }
+        other => panic!("expected a raw log, got {:?}", other),
+    }
+    match &notifications[1] {
+        EventNotification::Applied(envelope) => assert_eq!(envelope.event, nft_mint("1")),
+        other => panic!("expected an event, got {:?}", other),
+    }
+
+    let raw_logs = Arc::new(Mutex::new(Vec::new()));
+    let handler_logs = raw_logs.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .on_raw_log(move |raw_log| handler_logs.lock().unwrap().push(raw_log.log))
+        .build()
+        .unwrap();
+
+    let mut delivered = 0;
+    listener
+        .backfill(10, 10, |_envelope| delivered += 1)
+        .await
+        .unwrap();
+
+    assert_eq!(delivered, 1);
+    assert_eq!(
+        *raw_logs.lock().unwrap(),
+        vec!["minting token 1".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn test_start_from_timestamp_finds_first_block() {
+    let mock = MockBlockSource::start().await.unwrap();
+    for height in (10..=40).filter(|height| height % 7 != 0) {
+        mock.add_empty_block(height);
+    }
+    mock.add_function_call(
+        20,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        22,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("2")),
+    );
+
+    // Mock blocks are one second apart and heights 14, 21, 28 and 35 are
+    // skipped, so the first block since height 21's timestamp is at 22.
+    let block_at = |height: u64| UNIX_EPOCH + Duration::from_secs(1_600_000_000 + height);
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .start_from(StartPosition::Timestamp(block_at(21)))
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    assert_eq!(listener.last_processed_block, 0);
+
+    let envelope = tokio::time::timeout(Duration::from_secs(10), listener.stream().next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(envelope.event, nft_mint("2"));
+    assert_eq!(envelope.context.block_height, 22);
+}
+
+#[tokio::test]
+async fn test_start_after_tx_begins_at_its_block() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    let tx_hash = mock.add_function_call(12, MockFunctionCall::new("nft.near", "nft.near", "new"));
+    mock.add_function_call(
+        13,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("2")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .start_after_tx(&tx_hash.to_string(), "nft.near")
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+
+    let envelope = tokio::time::timeout(Duration::from_secs(10), listener.stream().next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(envelope.event, nft_mint("2"));
+    assert_eq!(envelope.context.block_height, 13);
+}
+
+#[tokio::test]
+async fn test_start_stops_at_until_height_and_max_events() {
+    let mock = MockBlockSource::start().await.unwrap();
+    for height in 10..=12 {
+        mock.add_function_call(
+            height,
+            MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+                .event(&nft_mint(&format!("{}-a", height)))
+                .event(&nft_mint(&format!("{}-b", height))),
+        );
+    }
+
+    let run = |listener: NearEventListener| async move {
+        let mut listener = listener;
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let callback_delivered = delivered.clone();
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            listener.start(move |envelope| callback_delivered.lock().unwrap().push(envelope)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let delivered = delivered.lock().unwrap().len();
+        (delivered, listener.last_processed_block)
+    };
+    let builder = || {
+        NearEventListener::builder(mock.url())
+            .account_id("nft.near")
+            .last_processed_block(9)
+            .catch_up_interval(Duration::from_millis(10))
+    };
+
+    assert_eq!(
+        run(builder().until_height(11).build().unwrap()).await,
+        (4, 11)
+    );
+    assert_eq!(run(builder().max_events(3).build().unwrap()).await, (3, 11));
+}
+
+#[tokio::test]
+async fn test_start_batched_delivers_one_batch_per_block() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    mock.add_empty_block(11);
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("bob.near", "nft.near", "nft_mint").event(&nft_mint("3")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(12)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let callback_batches = batches.clone();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start_batched(move |batch| callback_batches.lock().unwrap().push(batch)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    let batches = batches.lock().unwrap();
+    let summary: Vec<_> = batches
+        .iter()
+        .map(|batch| (batch.header.height, batch.events.len(), batch.reverted))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![(10, 2, false), (11, 0, false), (12, 1, false)]
+    );
+    assert_eq!(batches[0].events[1].event, nft_mint("2"));
+    assert_eq!(
+        batches[2].header.hash,
+        batches[2].events[0].context.block_hash
+    );
+}
+
+struct CollectingSink(Arc<Mutex<Vec<EventEnvelope>>>);
+
+impl EventSink for CollectingSink {
+    fn send<'a>(&'a self, envelope: &'a EventEnvelope) -> BoxFuture<'a, Result<(), ListenerError>> {
+        self.0.lock().unwrap().push(envelope.clone());
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+#[tokio::test]
+async fn test_start_sink_forwards_events() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(10)
+        .build()
+        .unwrap();
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start_sink(CollectingSink(sent.clone())),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[1].event, nft_mint("2"));
+}
+
+#[tokio::test]
+async fn test_channel_and_broadcast_deliver_events() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    let builder = || {
+        NearEventListener::builder(mock.url())
+            .account_id("nft.near")
+            .last_processed_block(9)
+            .until_height(10)
+            .build()
+            .unwrap()
+    };
+
+    let (mut receiver, task) = builder().channel(1);
+    assert_eq!(receiver.recv().await.unwrap().event, nft_mint("1"));
+    assert_eq!(receiver.recv().await.unwrap().event, nft_mint("2"));
+    assert!(receiver.recv().await.is_none());
+    task.await.unwrap().unwrap();
+
+    let (mut first, task) = builder().broadcast(16);
+    let mut second = first.resubscribe();
+    task.await.unwrap().unwrap();
+    for receiver in [&mut first, &mut second] {
+        assert_eq!(receiver.recv().await.unwrap().event, nft_mint("1"));
+        assert_eq!(receiver.recv().await.unwrap().event, nft_mint("2"));
+    }
+}
+
+#[tokio::test]
+async fn test_aborted_channel_task_closes_the_receiver() {
+    let mock = MockBlockSource::start().await.unwrap();
+    let listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .build()
+        .unwrap();
+
+    let (mut receiver, task) = listener.channel(1);
+    task.abort();
+    assert!(task.await.is_err());
+    assert!(receiver.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_subscriptions_share_one_listener() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_burn").event(&nft_mint("2")),
+    );
+    mock.add_function_call(
+        11,
+        MockFunctionCall::new("bob.near", "market.near", "buy").event(&nft_mint("3")),
+    );
+
+    let mints = Arc::new(Mutex::new(Vec::new()));
+    let market = Arc::new(Mutex::new(Vec::new()));
+    let (mint_events, market_events) = (mints.clone(), market.clone());
+    let mut listener = NearEventListener::builder(mock.url())
+        .subscription(
+            Subscription::new(move |envelope| mint_events.lock().unwrap().push(envelope))
+                .account_id("nft.near")
+                .method_name("nft_mint"),
+        )
+        .subscription(
+            Subscription::new(move |envelope| market_events.lock().unwrap().push(envelope))
+                .account_pattern("*.near")
+                .event_filter(EventFilter::new().event("nft_mint")),
+        )
+        .last_processed_block(9)
+        .until_height(11)
+        .build()
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(10), listener.run())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let mints = mints.lock().unwrap();
+    assert_eq!(mints.len(), 1);
+    assert_eq!(mints[0].event, nft_mint("1"));
+    assert_eq!(mints[0].context.method_name.as_deref(), Some("nft_mint"));
+
+    let market = market.lock().unwrap();
+    let tokens: Vec<_> = market
+        .iter()
+        .map(|envelope| envelope.event.data[0]["token_ids"][0].clone())
+        .collect();
+    assert_eq!(tokens, vec![json!("1"), json!("2"), json!("3")]);
+}
+
+#[tokio::test]
+async fn test_borsh_logs_are_decoded_per_subscription() {
+    #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq)]
+    struct Score {
+        player: String,
+        points: u64,
+    }
+
+    let score = Score {
+        player: "alice.near".to_string(),
+        points: 42,
+    };
+    let log = format!(
+        "EVENT_BORSH:{}",
+        STANDARD.encode(borsh::to_vec(&score).unwrap())
+    );
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "game.near", "play").log(&log),
+    );
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        11,
+        MockFunctionCall::new("alice.near", "game.near", "play").log("EVENT_BORSH:AAAA"),
+    );
+
+    let scores = Arc::new(Mutex::new(Vec::new()));
+    let dead_letters = Arc::new(Mutex::new(Vec::new()));
+    let (score_events, sink) = (scores.clone(), dead_letters.clone());
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .subscription(
+            Subscription::new(move |envelope| score_events.lock().unwrap().push(envelope))
+                .account_id("game.near")
+                .log_decoder(
+                    BorshLogDecoder::new::<Score>("game", "1.0.0", "score").prefix("EVENT_BORSH:"),
+                ),
+        )
+        .dead_letter_sink(Box::new(move |dead_letter: DeadLetter| {
+            sink.lock().unwrap().push(dead_letter);
+        }))
+        .last_processed_block(9)
+        .until_height(11)
+        .build()
+        .unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let delivered = events.clone();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start(move |envelope| delivered.lock().unwrap().push(envelope.event)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(*events.lock().unwrap(), vec![nft_mint("1")]);
+    let scores = scores.lock().unwrap();
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].event.event, "score");
+    assert_eq!(scores[0].event.parse_data::<Score>().unwrap(), score);
+
+    let dead_letters = dead_letters.lock().unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert!(dead_letters[0].error.contains("invalid Borsh"));
+}
+
+#[tokio::test]
+async fn test_log_prefixes_per_subscription() {
+    let event = serde_json::to_string(&nft_mint("2")).unwrap();
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "legacy.near", "nft_mint")
+            .log(&format!("EVENT:{}", event))
+            .event(&nft_mint("3")),
+    );
+
+    let legacy = Arc::new(Mutex::new(Vec::new()));
+    let legacy_events = legacy.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .subscription(
+            Subscription::new(move |envelope| legacy_events.lock().unwrap().push(envelope.event))
+                .account_id("legacy.near")
+                .log_prefixes(&["EVENT:", "EVENT_JSON:"]),
+        )
+        .last_processed_block(9)
+        .until_height(10)
+        .build()
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(10), listener.run())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(*legacy.lock().unwrap(), vec![nft_mint("2"), nft_mint("3")]);
+}
+
+#[cfg(feature = "schema")]
+#[tokio::test]
+async fn test_schema_drift_goes_to_dead_letter_sink() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    let mut drifted = nft_mint("2");
+    drifted.data = json!([{ "owner": "alice.near", "token_ids": ["2"] }]);
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&drifted),
+    );
+
+    let mut registry = near_event_listener::SchemaRegistry::new();
+    registry
+        .register(
+            "nep171",
+            "nft_mint",
+            "^1.0",
+            &json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["owner_id", "token_ids"],
+                },
+            }),
+        )
+        .unwrap();
+    let dead_letters = Arc::new(Mutex::new(Vec::new()));
+    let sink = dead_letters.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .schema_registry(registry)
+        .dead_letter_sink(Box::new(move |dead_letter: DeadLetter| {
+            sink.lock().unwrap().push(dead_letter);
+        }))
+        .last_processed_block(9)
+        .until_height(10)
+        .build()
+        .unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let delivered = events.clone();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start(move |envelope| delivered.lock().unwrap().push(envelope.event)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(*events.lock().unwrap(), vec![nft_mint("1")]);
+    let dead_letters = dead_letters.lock().unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert!(dead_letters[0].error.contains("owner_id"));
+}
+
+#[tokio::test]
+async fn test_paused_listener_keeps_its_place() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .build()
+        .unwrap();
+    let handle = listener.handle();
+
+    handle.pause();
+    let paused = tokio::time::timeout(Duration::from_millis(300), listener.stream().next()).await;
+    assert!(paused.is_err());
+    assert!(handle.is_paused());
+    assert_eq!(listener.last_processed_block, 9);
+
+    handle.resume();
+    let envelope = tokio::time::timeout(Duration::from_secs(10), listener.stream().next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(envelope.event, nft_mint("1"));
+    assert_eq!(listener.last_processed_block, 10);
+}
+
+#[tokio::test]
+async fn test_status_reports_progress() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+
+    let listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(10)
+        .build()
+        .unwrap();
+    let handle = listener.handle();
+
+    let status = listener.status();
+    assert_eq!(status.state, ListenerState::Stopped);
+    assert_eq!(status.last_processed_block, 9);
+    assert_eq!(status.uptime, Duration::ZERO);
+
+    let (mut receiver, task) = listener.channel(1);
+    receiver.recv().await.unwrap();
+    assert_ne!(handle.status().state, ListenerState::Stopped);
+
+    handle.pause();
+    assert_eq!(handle.status().state, ListenerState::Paused);
+    handle.resume();
+
+    receiver.recv().await.unwrap();
+    task.await.unwrap().unwrap();
+
+    let status = handle.status();
+    assert_eq!(status.state, ListenerState::Stopped);
+    assert_eq!(status.last_processed_block, 10);
+    assert_eq!(status.head_block, 10);
+    assert_eq!(status.events_emitted, 2);
+    assert_eq!(status.rpc_errors, 0);
+}
+
+#[tokio::test]
+async fn test_logs_are_attributed_to_their_receipt() {
+    let mock = MockBlockSource::start().await.unwrap();
+    let tx_hash = mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .cross_contract_event("market.near", "on_mint", &nft_mint("2")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .executor_filter(ExecutorFilter::Any)
+        .last_processed_block(9)
+        .build()
+        .unwrap();
+
+    let response = JsonRpcClient::connect(mock.url())
+        .call(methods::tx::RpcTransactionStatusRequest {
+            transaction_info: methods::tx::TransactionInfo::TransactionId {
+                tx_hash,
+                sender_account_id: "alice.near".parse().unwrap(),
+            },
+            wait_until: TxExecutionStatus::ExecutedOptimistic,
+        })
+        .await
+        .unwrap();
+    let logs = listener.extract_attributed_logs(&response);
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[0].executor_id, "nft.near");
+    assert_eq!(logs[1].executor_id, "market.near");
+    assert!(matches!(logs[1].source, LogSource::Receipt { .. }));
+    assert_ne!(logs[0].source, logs[1].source);
+
+    let envelopes: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.stream().take(2).collect::<Vec<_>>(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .unwrap();
+    assert_eq!(envelopes[1].event, nft_mint("2"));
+    assert_eq!(envelopes[1].context.account_id, "market.near");
+    assert_eq!(envelopes[1].context.source(), logs[1].source);
+}
+
+#[tokio::test]
+async fn test_executor_filter_drops_logs_of_other_contracts() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .cross_contract_event("market.near", "on_mint", &nft_mint("2")),
+    );
+    let tokens = |executor_filter| {
+        let mut listener = NearEventListener::builder(mock.url())
+            .account_id("nft.near")
+            .executor_filter(executor_filter)
+            .build()
+            .unwrap();
+        async move {
+            let mut tokens = Vec::new();
+            listener
+                .backfill(10, 10, |envelope| {
+                    tokens.push(envelope.event.data[0]["token_ids"][0].clone())
+                })
+                .await
+                .unwrap();
+            tokens
+        }
+    };
+
+    assert_eq!(
+        tokens(ExecutorFilter::WatchedAccounts).await,
+        vec![json!("1")]
+    );
+    assert_eq!(
+        tokens(ExecutorFilter::Accounts(vec!["market.near".to_string()])).await,
+        vec![json!("2")]
+    );
+}
+
+#[tokio::test]
+async fn test_relayed_meta_transactions_are_matched() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .relayed_by("relayer.near")
+            .event(&nft_mint("1")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .method_name("nft_mint")
+        .build()
+        .unwrap();
+
+    let mut envelopes = Vec::new();
+    listener
+        .backfill(10, 10, |envelope| envelopes.push(envelope))
+        .await
+        .unwrap();
+
+    assert_eq!(envelopes.len(), 1);
+    assert_eq!(envelopes[0].event, nft_mint("1"));
+    assert_eq!(envelopes[0].context.signer_id, "alice.near");
+    assert_eq!(envelopes[0].context.receiver_id, "nft.near");
+    assert_eq!(envelopes[0].context.account_id, "nft.near");
+}
+
+#[derive(Clone, Default)]
+struct RecordingStore(Arc<Mutex<Vec<String>>>);
+
+impl CheckpointStore for RecordingStore {
+    fn load(&self) -> Result<Option<u64>, ListenerError> {
+        Ok(None)
+    }
+
+    fn save(&self, height: u64) -> Result<(), ListenerError> {
+        self.0.lock().unwrap().push(format!("done {}", height));
+        Ok(())
+    }
+
+    fn begin_block(&self, height: u64) -> Result<(), ListenerError> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("processing {}", height));
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_blocks_are_checkpointed_after_delivery() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_empty_block(11);
+
+    let store = RecordingStore::default();
+    let records = store.0.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(11)
+        .checkpoint_store(Box::new(store))
+        .build()
+        .unwrap();
+    let delivered = records.clone();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start(move |_envelope| delivered.lock().unwrap().push("event".to_string())),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(
+        *records.lock().unwrap(),
+        vec![
+            "processing 10",
+            "event",
+            "done 10",
+            "processing 11",
+            "done 11"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_sequence_numbers_continue_after_restart() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    mock.add_function_call(
+        11,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("3")),
+    );
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("4")),
+    );
+
+    let path = std::env::temp_dir().join(format!(
+        "near_event_listener_sequence_{}.checkpoint",
+        std::process::id()
+    ));
+    let url = mock.url();
+    let run = |until_height: u64| {
+        let path = path.clone();
+        async move {
+            let mut listener = NearEventListener::builder(url)
+                .account_id("nft.near")
+                .last_processed_block(9)
+                .until_height(until_height)
+                .catch_up_interval(Duration::from_millis(10))
+                .checkpoint_store(Box::new(FileCheckpointStore::new(path)))
+                .build()
+                .unwrap();
+            let sequences = Arc::new(Mutex::new(Vec::new()));
+            let delivered = sequences.clone();
+            tokio::time::timeout(
+                Duration::from_secs(10),
+                listener.start(move |envelope| {
+                    delivered.lock().unwrap().push(envelope.context.sequence)
+                }),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            let sequences = sequences.lock().unwrap().clone();
+            sequences
+        }
+    };
+
+    assert_eq!(run(11).await, vec![Some(1), Some(2), Some(3)]);
+    assert_eq!(run(12).await, vec![Some(4)]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_exactly_once_survives_a_restart_mid_block() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2"))
+            .event(&nft_mint("3")),
+    );
+
+    let path = std::env::temp_dir().join(format!(
+        "near_event_listener_exactly_once_{}.checkpoint",
+        std::process::id()
+    ));
+    let url = mock.url();
+    let run = |max_events: usize| {
+        let path = path.clone();
+        async move {
+            let mut listener = NearEventListener::builder(url)
+                .account_id("nft.near")
+                .last_processed_block(9)
+                .until_height(10)
+                .max_events(max_events)
+                .delivery(DeliveryMode::ExactlyOnce)
+                .checkpoint_store(Box::new(FileCheckpointStore::new(path)))
+                .build()
+                .unwrap();
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let delivered = events.clone();
+            tokio::time::timeout(
+                Duration::from_secs(10),
+                listener.start(move |envelope| delivered.lock().unwrap().push(envelope.event)),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            let events = events.lock().unwrap().clone();
+            events
+        }
+    };
+
+    // Stopping after two events leaves the block in progress, as a crash
+    // would.
+    assert_eq!(run(2).await, vec![nft_mint("1"), nft_mint("2")]);
+    assert_eq!(run(10).await, vec![nft_mint("3")]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+// Fails to start the block at `height` once.
+struct FailingStore {
+    height: u64,
+    failed: AtomicBool,
+}
+
+impl CheckpointStore for FailingStore {
+    fn load(&self) -> Result<Option<u64>, ListenerError> {
+        Ok(None)
+    }
+
+    fn save(&self, _height: u64) -> Result<(), ListenerError> {
+        Ok(())
+    }
+
+    fn begin_block(&self, height: u64) -> Result<(), ListenerError> {
+        if height == self.height && !self.failed.swap(true, Ordering::SeqCst) {
+            return Err(ListenerError::CheckpointError("disk full".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_failed_backfill_keeps_delivered_progress() {
+    let mock = MockBlockSource::start().await.unwrap();
+    for (height, token_id) in [(10, "1"), (11, "2"), (12, "3")] {
+        mock.add_function_call(
+            height,
+            MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint(token_id)),
+        );
+    }
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .checkpoint_store(Box::new(FailingStore {
+            height: 12,
+            failed: AtomicBool::new(false),
+        }))
+        .build()
+        .unwrap();
+    let mut sequences = Vec::new();
+    let result = listener
+        .backfill(10, 12, |envelope| sequences.push(envelope.context.sequence))
+        .await;
+    assert!(matches!(result, Err(ListenerError::CheckpointError(_))));
+    assert_eq!(listener.last_processed_block, 11);
+
+    listener
+        .backfill(12, 12, |envelope| sequences.push(envelope.context.sequence))
+        .await
+        .unwrap();
+    assert_eq!(sequences, vec![Some(1), Some(2), Some(3)]);
+    assert_eq!(listener.last_processed_block, 12);
+}
+
+#[tokio::test]
+async fn test_empty_chunks_are_not_fetched() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_empty_block(11);
+    mock.add_empty_block(12);
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .build()
+        .unwrap();
+    let mut delivered = 0;
+    listener
+        .backfill(10, 12, |_envelope| delivered += 1)
+        .await
+        .unwrap();
+
+    assert_eq!(delivered, 1);
+    assert_eq!(mock.request_count("chunk"), 1);
+}
+
+#[tokio::test]
+async fn test_cached_blocks_and_chunks_are_not_refetched() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .cache_capacity(16)
+        .build()
+        .unwrap();
+    let mut delivered = 0;
+    for _ in 0..2 {
+        listener
+            .backfill(10, 10, |_envelope| delivered += 1)
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(delivered, 2);
+    assert_eq!(mock.request_count("block"), 1);
+    assert_eq!(mock.request_count("chunk"), 1);
+    let status = listener.status();
+    assert_eq!((status.cache_hits, status.cache_misses), (2, 2));
+}
+
+#[tokio::test]
+async fn test_concurrent_status_fetches_keep_block_order() {
+    let mock = MockBlockSource::start().await.unwrap();
+    let mut tx_hashes = Vec::new();
+    for token_id in ["1", "2", "3", "4", "5"] {
+        tx_hashes.push(
+            mock.add_function_call(
+                10,
+                MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+                    .event(&nft_mint(token_id)),
+            )
+            .to_string(),
+        );
+    }
+
+    for concurrency in [1, 4] {
+        let mut listener = NearEventListener::builder(mock.url())
+            .account_id("nft.near")
+            .transaction_concurrency(concurrency)
+            .build()
+            .unwrap();
+
+        let mut envelopes = Vec::new();
+        listener
+            .backfill(10, 10, |envelope| envelopes.push(envelope))
+            .await
+            .unwrap();
+
+        let delivered: Vec<_> = envelopes
+            .iter()
+            .map(|envelope| envelope.context.tx_hash.clone().unwrap())
+            .collect();
+        assert_eq!(delivered, tx_hashes);
+    }
+}
+
+#[tokio::test]
+async fn test_context_carries_deposit_and_gas() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .deposit(10_000_000_000_000_000_000_000)
+            .event(&nft_mint("1")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .method_name("nft_mint")
+        .build()
+        .unwrap();
+
+    let mut envelopes = Vec::new();
+    listener
+        .backfill(10, 10, |envelope| envelopes.push(envelope))
+        .await
+        .unwrap();
+
+    assert_eq!(envelopes.len(), 1);
+    let context = &envelopes[0].context;
+    assert_eq!(context.deposit, Some(10_000_000_000_000_000_000_000));
+    assert_eq!(context.gas, Some(30_000_000_000_000));
+}
+
+#[tokio::test]
+async fn test_failed_transactions_are_skipped_unless_opted_in() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        11,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("2"))
+            .failed(),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .build()
+        .unwrap();
+    let mut envelopes = Vec::new();
+    listener
+        .backfill(10, 11, |envelope| envelopes.push(envelope))
+        .await
+        .unwrap();
+
+    assert_eq!(envelopes.len(), 1);
+    assert_eq!(envelopes[0].event, nft_mint("1"));
+    assert_eq!(
+        envelopes[0].context.execution_status,
+        Some(ExecutionStatus::Success)
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .delivery_on(ExecutionStatusFilter::All)
+        .build()
+        .unwrap();
+    let mut envelopes = Vec::new();
+    listener
+        .backfill(10, 11, |envelope| envelopes.push(envelope))
+        .await
+        .unwrap();
+
+    assert_eq!(envelopes.len(), 2);
+    assert_eq!(envelopes[1].event, nft_mint("2"));
+    assert_eq!(
+        envelopes[1].context.execution_status,
+        Some(ExecutionStatus::Failure)
+    );
+}
+
+#[tokio::test]
+async fn test_event_handlers_dispatch_on_event_name() {
+    let transfer = EventLog {
+        event: "nft_transfer".to_string(),
+        ..nft_mint("2")
+    };
+    let burn = EventLog {
+        event: "nft_burn".to_string(),
+        ..nft_mint("3")
+    };
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&transfer)
+            .event(&burn),
+    );
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let (mints, transfers, others) = (received.clone(), received.clone(), received.clone());
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(10)
+        .build()
+        .unwrap()
+        .on("nft_mint", move |envelope| {
+            mints.lock().unwrap().push(("mint", envelope.event))
+        })
+        .on("nft_transfer", move |envelope| {
+            transfers.lock().unwrap().push(("transfer", envelope.event))
+        })
+        .on_any(move |envelope| others.lock().unwrap().push(("any", envelope.event)));
+
+    tokio::time::timeout(Duration::from_secs(10), listener.run())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![
+            ("mint", nft_mint("1")),
+            ("transfer", transfer),
+            ("any", burn)
+        ]
+    );
+}
+
+#[derive(Debug, PartialEq)]
+enum Mint {
+    V1 { token_id: String },
+    V2 { token_id: String, memo: String },
+}
+
+#[derive(serde::Deserialize)]
+struct MintV1 {
+    token_ids: Vec<String>,
+}
+
+impl From<Vec<MintV1>> for Mint {
+    fn from(mints: Vec<MintV1>) -> Self {
+        Mint::V1 {
+            token_id: mints[0].token_ids[0].clone(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct MintV2 {
+    token_id: String,
+    memo: String,
+}
+
+impl From<Vec<MintV2>> for Mint {
+    fn from(mints: Vec<MintV2>) -> Self {
+        Mint::V2 {
+            token_id: mints[0].token_id.clone(),
+            memo: mints[0].memo.clone(),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_registry_decodes_each_event_version() {
+    let mint_v2 = EventLog {
+        version: "2.1.0".to_string(),
+        data: json!([{ "token_id": "2", "memo": "upgraded" }]),
+        ..nft_mint("2")
+    };
+    let mint_v3 = EventLog {
+        version: "3.0.0".to_string(),
+        ..nft_mint("3")
+    };
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&mint_v2)
+            .event(&mint_v3),
+    );
+
+    let mut registry = EventRegistry::<Mint>::new();
+    registry
+        .register::<Vec<MintV1>>("nep171", "nft_mint", "^1.0")
+        .unwrap()
+        .register::<Vec<MintV2>>("nep171", "nft_mint", "^2.0")
+        .unwrap();
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(10)
+        .build()
+        .unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let decoded = received.clone();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start_with_registry(registry, move |_envelope, mint| {
+            decoded.lock().unwrap().push(mint)
+        }),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![
+            Mint::V1 {
+                token_id: "1".to_string()
+            },
+            Mint::V2 {
+                token_id: "2".to_string(),
+                memo: "upgraded".to_string()
+            },
+        ]
+    );
+}
+
+// The callback task only runs once polling yields, so on the single-threaded
+// test runtime all three events of the block meet a queue holding one.
+async fn deliver_through_queue(
+    overflow: OverflowPolicy,
+) -> (Vec<EventLog>, Result<(), ListenerError>) {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2"))
+            .event(&nft_mint("3")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(10)
+        .delivery_queue(1, overflow)
+        .build()
+        .unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let delivered = received.clone();
+    let result = tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start_async(move |envelope| {
+            let delivered = delivered.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                delivered.lock().unwrap().push(envelope.event);
+            }
+        }),
+    )
+    .await
+    .unwrap();
+
+    let received = received.lock().unwrap().clone();
+    (received, result)
+}
+
+#[tokio::test]
+async fn test_delivery_queue_overflow_policies() {
+    let (delivered, result) = deliver_through_queue(OverflowPolicy::Block).await;
+    result.unwrap();
+    assert_eq!(delivered, vec![nft_mint("1"), nft_mint("2"), nft_mint("3")]);
+
+    let (delivered, result) = deliver_through_queue(OverflowPolicy::DropNewest).await;
+    result.unwrap();
+    assert_eq!(delivered, vec![nft_mint("1")]);
+
+    let (delivered, result) = deliver_through_queue(OverflowPolicy::DropOldest).await;
+    result.unwrap();
+    assert_eq!(delivered, vec![nft_mint("3")]);
+
+    let (delivered, result) = deliver_through_queue(OverflowPolicy::Error).await;
+    assert!(matches!(result, Err(ListenerError::QueueFull(1))));
+    assert_eq!(delivered, vec![nft_mint("1")]);
+}
+
+struct DropToken(&'static str);
+
+impl EventMiddleware for DropToken {
+    fn handle<'a>(
+        &'a self,
+        envelope: EventEnvelope,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<(), ListenerError>> {
+        if envelope.event.data[0]["token_ids"][0] == self.0 {
+            return Box::pin(future::ready(Ok(())));
+        }
+        next.run(envelope)
+    }
+}
+
+struct TagOwner;
+
+impl EventMiddleware for TagOwner {
+    fn handle<'a>(
+        &'a self,
+        mut envelope: EventEnvelope,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<(), ListenerError>> {
+        Box::pin(async move {
+            envelope.event.data[0]["tagged"] = json!(true);
+            next.run(envelope).await
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_filters_and_enriches_events() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2"))
+            .event(&nft_mint("3")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .middleware(DropToken("2"))
+        .middleware(TagOwner)
+        .build()
+        .unwrap();
+
+    let mut envelopes = Vec::new();
+    listener
+        .backfill(10, 10, |envelope| envelopes.push(envelope))
+        .await
+        .unwrap();
+
+    let tokens: Vec<_> = envelopes
+        .iter()
+        .map(|envelope| envelope.event.data[0]["token_ids"][0].clone())
+        .collect();
+    assert_eq!(tokens, vec![json!("1"), json!("3")]);
+    assert!(envelopes
+        .iter()
+        .all(|envelope| envelope.event.data[0]["tagged"] == json!(true)));
+}
+
+#[tokio::test]
+async fn test_repair_gaps_delivers_late_events() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("3")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(12)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let late = delivered.clone();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start(move |envelope| late.lock().unwrap().push(envelope.context.late)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![false, false]);
+    assert_eq!(listener.gaps(), vec![11..=11]);
+
+    mock.add_function_call(
+        11,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("2")),
+    );
+    let mut repaired = Vec::new();
+    listener
+        .repair_gaps(|envelope| repaired.push(envelope))
+        .await
+        .unwrap();
+
+    assert_eq!(repaired.len(), 1);
+    assert!(repaired[0].context.late);
+    assert_eq!(repaired[0].context.block_height, 11);
+    assert!(listener.gaps().is_empty());
+}
+
+#[tokio::test]
+async fn test_replay_from_file_runs_the_pipeline() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    mock.add_function_call(
+        11,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_burn").event(&EventLog {
+            event: "nft_burn".to_string(),
+            ..nft_mint("3")
+        }),
+    );
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("4")),
+    );
+
+    let mut recorder = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .build()
+        .unwrap();
+    let mut archive = Vec::new();
+    recorder
+        .backfill(10, 12, |envelope| {
+            archive.push(serde_json::to_string(&envelope).unwrap())
+        })
+        .await
+        .unwrap();
+    archive.insert(1, "not json".to_string());
+    archive.insert(2, String::new());
+    let path = std::env::temp_dir().join(format!(
+        "near_event_listener_replay_{}.ndjson",
+        std::process::id()
+    ));
+    std::fs::write(&path, archive.join("\n")).unwrap();
+
+    // Replaying needs no RPC, so the endpoint is never called.
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let reported = errors.clone();
+    let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+        .account_id("nft.near")
+        .event_filter(EventFilter::new().event("nft_mint"))
+        .middleware(DropToken("2"))
+        .on_error(move |err| {
+            reported.lock().unwrap().push(err.to_string());
+            ErrorAction::Continue
+        })
+        .build()
+        .unwrap();
+    let mut replayed = Vec::new();
+    listener
+        .replay_from_file(&path, |envelope| replayed.push(envelope))
+        .await
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let tokens: Vec<_> = replayed
+        .iter()
+        .map(|envelope| envelope.event.data[0]["token_ids"][0].clone())
+        .collect();
+    assert_eq!(tokens, vec![json!("1"), json!("4")]);
+    assert_eq!(replayed[1].context.block_height, 12);
+    assert_eq!(
+        replayed
+            .iter()
+            .map(|envelope| envelope.context.sequence)
+            .collect::<Vec<_>>(),
+        vec![Some(1), Some(2)]
+    );
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains(":2:"));
+}
+
+#[tokio::test]
+async fn test_replay_from_missing_file_fails() {
+    let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+        .account_id("nft.near")
+        .build()
+        .unwrap();
+
+    let result = listener
+        .replay_from_file("/nonexistent/events.ndjson", |_| {})
+        .await;
+    assert!(matches!(result, Err(ListenerError::SourceError(_))));
+}
+
+#[tokio::test]
+async fn test_exported_state_resumes_elsewhere() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("3")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .method_name("nft_*")
+        .event_filter(EventFilter::new().standard("nep171"))
+        .delivery(DeliveryMode::ExactlyOnce)
+        .last_processed_block(9)
+        .until_height(10)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    listener.start(|_envelope| {}).await.unwrap();
+
+    let json = listener.export_state().to_json().unwrap();
+    let snapshot = ListenerSnapshot::from_json(&json).unwrap();
+    assert_eq!(snapshot, listener.export_state());
+    assert_eq!(snapshot.last_processed_block, 10);
+    assert_eq!(snapshot.sequence, 2);
+
+    let mut restored = NearEventListener::from_state(snapshot)
+        .until_height(12)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    assert_eq!(restored.account_ids, vec!["nft.near"]);
+    assert_eq!(restored.method_names, vec!["nft_*"]);
+    assert_eq!(restored.last_processed_block, 10);
+
+    // Events the original listener delivered are not delivered again.
+    let mut sequences = Vec::new();
+    restored
+        .backfill(10, 10, |envelope| sequences.push(envelope.context.sequence))
+        .await
+        .unwrap();
+    assert!(sequences.is_empty());
+
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let sink = delivered.clone();
+    restored
+        .start(move |envelope| sink.lock().unwrap().push(envelope.context.sequence))
+        .await
+        .unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![Some(3)]);
+}
+
+#[tokio::test]
+async fn test_handle_changes_watched_accounts_while_running() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("alice.near", "new.near", "nft_mint").event(&nft_mint("2")),
+    );
+    mock.add_function_call(
+        14,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("3")),
+    );
+    mock.add_function_call(
+        14,
+        MockFunctionCall::new("alice.near", "sub.near", "nft_mint").event(&nft_mint("4")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(14)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let handle = listener.handle();
+    assert!(handle.add_account_id("not a valid account").is_err());
+
+    let subscribed = Arc::new(Mutex::new(Vec::new()));
+    let subscription_sink = subscribed.clone();
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let sink = delivered.clone();
+    listener
+        .start(move |envelope| {
+            if envelope.context.block_height == 10 {
+                handle.add_account_id("new.near").unwrap();
+                handle.remove_account_id("nft.near");
+                let sink = subscription_sink.clone();
+                handle
+                    .add_subscription(
+                        Subscription::new(move |envelope| {
+                            sink.lock().unwrap().push(envelope.context.receiver_id)
+                        })
+                        .account_id("sub.near"),
+                    )
+                    .unwrap();
+            }
+            sink.lock().unwrap().push(envelope.context.receiver_id);
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(*delivered.lock().unwrap(), vec!["nft.near", "new.near"]);
+    assert_eq!(*subscribed.lock().unwrap(), vec!["sub.near"]);
+    assert_eq!(listener.account_ids, vec!["new.near"]);
+}
+
+#[tokio::test]
+async fn test_factory_created_accounts_are_watched() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_account_creation(10, "factory.near", "store.factory.near");
+    mock.add_account_creation(10, "other.near", "store.other.near");
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("alice.near", "store.factory.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("alice.near", "store.other.near", "nft_mint").event(&nft_mint("2")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .factory("factory.near")
+        .last_processed_block(9)
+        .until_height(12)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let sink = delivered.clone();
+    listener
+        .start(move |envelope| sink.lock().unwrap().push(envelope.context.receiver_id))
+        .await
+        .unwrap();
+
+    assert_eq!(*delivered.lock().unwrap(), vec!["store.factory.near"]);
+    assert_eq!(listener.account_ids, vec!["store.factory.near"]);
+}
+
+#[tokio::test]
+async fn test_storage_changes_are_reported() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_storage_change(10, "counter.near", b"c:alice", Some(b"1"));
+    mock.add_storage_change(10, "counter.near", b"owner", Some(b"bob.near"));
+    mock.add_storage_change(10, "other.near", b"c:alice", Some(b"7"));
+    mock.add_storage_change(11, "counter.near", b"c:alice", None);
+
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let handler_changes = changes.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .watch_storage("counter.near", b"c:")
+        .watch_storage("counter.near", b"c:al")
+        .on_state_change(move |change| handler_changes.lock().unwrap().push(change))
+        .last_processed_block(9)
+        .until_height(11)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    listener.start(|_| {}).await.unwrap();
+
+    let changes = changes.lock().unwrap().clone();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].account_id, "counter.near");
+    assert_eq!(changes[0].block_height, 10);
+    assert_eq!(changes[0].key, b"c:alice");
+    assert_eq!(changes[0].value.as_deref(), Some(&b"1"[..]));
+    assert!(changes[0].receipt_id.is_some());
+    assert_eq!(changes[1].block_height, 11);
+    assert_eq!(changes[1].value, None);
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .watch_storage("counter.near", b"")
+        .last_processed_block(9)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let notifications: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.notifications().take(3).collect::<Vec<_>>(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|notification| match notification.unwrap() {
+        EventNotification::StateChange(change) => change.key,
+        other => panic!("expected a state change, got {:?}", other),
+    })
+    .collect();
+    assert_eq!(
+        notifications,
+        vec![b"c:alice".to_vec(), b"owner".to_vec(), b"c:alice".to_vec()]
+    );
+}
+
+#[tokio::test]
+async fn test_account_changes_are_reported() {
+    const KEY: &str = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp";
+    const SESSION_KEY: &str = "ed25519:DcA2MzgpJbrUATQLLceocVckhhAqrkingax4oJ9kZ847";
+
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_balance_change(8, "treasury.near", 100);
+    mock.add_access_key(8, "treasury.near", SESSION_KEY, false);
+    mock.add_balance_change(10, "treasury.near", 40);
+    mock.add_balance_change(10, "treasury.near", 40);
+    mock.add_access_key(10, "treasury.near", KEY, true);
+    mock.add_access_key(10, "treasury.near", SESSION_KEY, false);
+    mock.add_balance_change(10, "other.near", 5);
+    mock.add_code_deployment(11, "treasury.near", b"\0asm");
+    mock.remove_access_key(11, "treasury.near", KEY);
+
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let handler_changes = changes.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .watch_account_changes("treasury.near")
+        .on_account_change(move |change| handler_changes.lock().unwrap().push(change))
+        .last_processed_block(9)
+        .until_height(11)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    listener.start(|_| {}).await.unwrap();
+
+    let changes = changes.lock().unwrap().clone();
+    assert!(changes
+        .iter()
+        .all(|change| change.account_id == "treasury.near"));
+    let kinds: Vec<_> = changes.into_iter().map(|change| change.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            AccountChangeKind::Balance {
+                previous: 100,
+                balance: 40,
+            },
+            AccountChangeKind::AccessKeyAdded {
+                public_key: KEY.to_string(),
+                full_access: true,
+            },
+            AccountChangeKind::AccessKeyRemoved {
+                public_key: KEY.to_string(),
+            },
+            AccountChangeKind::CodeDeployed {
+                code_hash: near_primitives::hash::CryptoHash::hash_bytes(b"\0asm").to_string(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_payments_are_detected() {
+    let mock = MockBlockSource::start().await.unwrap();
+    let transfer = mock.add_function_call(
+        10,
+        MockFunctionCall::transfer("alice.near", "shop.near", 5_000),
+    );
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("bob.near", "shop.near", "buy").deposit(700),
+    );
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("carol.near", "shop.near", "buy")
+            .deposit(900)
+            .failed(),
+    );
+    mock.add_function_call(10, MockFunctionCall::new("dave.near", "shop.near", "view"));
+    mock.add_function_call(
+        10,
+        MockFunctionCall::transfer("alice.near", "other.near", 1_000),
+    );
+
+    let payments = Arc::new(Mutex::new(Vec::new()));
+    let handler_payments = payments.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("shop.near")
+        .on_payment(move |payment| handler_payments.lock().unwrap().push(payment))
+        .last_processed_block(9)
+        .until_height(10)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    listener.start(|_| {}).await.unwrap();
+
+    let payments = payments.lock().unwrap().clone();
+    assert_eq!(payments.len(), 2);
+    assert_eq!(payments[0].sender_id, "alice.near");
+    assert_eq!(payments[0].receiver_id, "shop.near");
+    assert_eq!(payments[0].amount, 5_000);
+    assert_eq!(payments[0].method_name, None);
+    assert_eq!(payments[0].tx_hash, Some(transfer.to_string()));
+    assert!(payments[0].receipt_id.is_some());
+    assert_eq!(payments[1].sender_id, "bob.near");
+    assert_eq!(payments[1].amount, 700);
+    assert_eq!(payments[1].method_name.as_deref(), Some("buy"));
+}
+
+#[tokio::test]
+async fn test_events_stream_carries_every_kind() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .deposit(10)
+            .event(&nft_mint("1")),
+    );
+    mock.add_storage_change(10, "nft.near", b"owner", Some(b"alice.near"));
+    mock.add_empty_block(11);
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .watch_storage("nft.near", b"")
+        .detect_payments(true)
+        .last_processed_block(9)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let events: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.events().take(5).collect::<Vec<_>>(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .unwrap();
+
+    assert!(
+        matches!(&events[0], ListenerEvent::Nep297(envelope) if envelope.event == nft_mint("1"))
+    );
+    assert!(matches!(&events[1], ListenerEvent::Payment(payment) if payment.amount == 10));
+    assert!(matches!(&events[2], ListenerEvent::StateChange(change) if change.key == b"owner"));
+    assert!(matches!(&events[3], ListenerEvent::BlockBoundary(header) if header.height == 10));
+    assert!(matches!(&events[4], ListenerEvent::BlockBoundary(header) if header.height == 11));
+}
+
+#[tokio::test]
+async fn test_block_processed_is_reported_for_every_block() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    mock.add_empty_block(11);
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("bob.near", "other.near", "nft_mint").event(&nft_mint("3")),
+    );
+
+    let processed = Arc::new(Mutex::new(Vec::new()));
+    let handler_processed = processed.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(12)
+        .catch_up_interval(Duration::from_millis(10))
+        .on_block_processed(move |block| handler_processed.lock().unwrap().push(block))
+        .build()
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(10), listener.start(|_| {}))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let processed = processed.lock().unwrap();
+    let summary: Vec<_> = processed
+        .iter()
+        .map(|block| (block.height, block.events_count))
+        .collect();
+    assert_eq!(summary, vec![(10, 2), (11, 0), (12, 0)]);
+    assert!(processed.iter().all(|block| !block.hash.is_empty()));
+}
+
+#[tokio::test]
+async fn test_windows_group_events_by_block_range() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("2")),
+    );
+    mock.add_empty_block(11);
+    mock.add_function_call(
+        12,
+        MockFunctionCall::new("bob.near", "nft.near", "nft_mint").event(&nft_mint("3")),
+    );
+    mock.add_empty_block(13);
+    mock.add_function_call(
+        14,
+        MockFunctionCall::new("bob.near", "nft.near", "nft_mint").event(&nft_mint("4")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(14)
+        .catch_up_interval(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    assert!(matches!(
+        listener.start_windowed(WindowSize::Blocks(0), |_| {}).await,
+        Err(ListenerError::InvalidConfig(_))
+    ));
+
+    let mut windows = Vec::new();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start_windowed(WindowSize::Blocks(2), |window| windows.push(window)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    let summary: Vec<_> = windows
+        .iter()
+        .map(|window| {
+            (
+                window.start..window.end,
+                window.first_block..=window.last_block,
+                window.events.len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (10..12, 10..=11, 2),
+            (12..14, 12..=13, 1),
+            (14..16, 14..=14, 1)
+        ]
+    );
+    assert_eq!(
+        windows[0].count_by(|envelope| envelope.context.signer_id.clone()),
+        [("alice.near".to_string(), 2)].into()
+    );
+}
+
+#[tokio::test]
+async fn test_data_filters_select_events_by_payload() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .event(&nft_mint("123"))
+            .event(&nft_mint("7")),
+    );
+    let builder = || {
+        NearEventListener::builder(mock.url())
+            .account_id("nft.near")
+            .last_processed_block(9)
+            .until_height(10)
+            .catch_up_interval(Duration::from_millis(10))
+    };
+    let token_ids = |listener: NearEventListener| async move {
+        let mut listener = listener;
+        let events: Vec<_> = listener.stream().collect().await;
+        events
+            .into_iter()
+            .map(|envelope| envelope.unwrap().event.data[0]["token_ids"][0].clone())
+            .collect::<Vec<_>>()
+    };
+
+    let listener = builder()
+        .data_filter("$[0].token_ids[*]", equals("123"))
+        .build()
+        .unwrap();
+    assert_eq!(token_ids(listener).await, vec![json!("123")]);
+
+    let listener = builder()
+        .data_filter(".[0].owner_id", contains("alice"))
+        .data_filter_fn(|data| data[0]["token_ids"][0] != "1")
+        .build()
+        .unwrap();
+    assert_eq!(token_ids(listener).await, vec![json!("123"), json!("7")]);
+
+    assert!(matches!(
+        builder()
+            .data_filter("$[0].token_ids[", equals("1"))
+            .build(),
+        Err(ListenerError::InvalidConfig(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_receipt_views_are_attached_to_events() {
+    let mock = MockBlockSource::start().await.unwrap();
+    let tx_hash = mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .include_receipt_views(true)
+        .build()
+        .unwrap();
+    let envelope = tokio::time::timeout(Duration::from_secs(10), listener.stream().next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let details = envelope.context.receipt.unwrap();
+    let receipt = details.receipt.unwrap();
+    assert_eq!(
+        Some(receipt.receipt_id.to_string()),
+        envelope.context.receipt_id
+    );
+    assert_eq!(receipt.predecessor_id, "alice.near");
+    assert_eq!(details.outcome.executor_id, "nft.near");
+    assert!(matches!(
+        details.outcome.status,
+        ExecutionStatusView::SuccessValue(_)
+    ));
+
+    // Nodes that return the receipts along with the outcomes are read the same way.
+    let response = JsonRpcClient::connect(mock.url())
+        .call(methods::tx::RpcTransactionStatusRequest {
+            transaction_info: methods::tx::TransactionInfo::TransactionId {
+                tx_hash,
+                sender_account_id: "alice.near".parse().unwrap(),
+            },
+            wait_until: TxExecutionStatus::ExecutedOptimistic,
+        })
+        .await
+        .unwrap();
+    let Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(final_outcome)) =
+        response.final_execution_outcome
+    else {
+        panic!("expected a plain outcome from the mock");
+    };
+    let response = RpcTransactionResponse {
+        final_execution_outcome: Some(
+            FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(
+                FinalExecutionOutcomeWithReceiptView {
+                    final_outcome,
+                    receipts: vec![receipt],
+                },
+            ),
+        ),
+        final_execution_status: response.final_execution_status,
+    };
+    assert_eq!(listener.extract_logs(&response).len(), 1);
+}
+
+#[tokio::test]
+async fn test_execution_metrics_sum_the_transaction() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint")
+            .event(&nft_mint("1"))
+            .cross_contract_event("market.near", "on_mint", &nft_mint("2"))
+            .gas_burnt(3_000_000_000_000),
+    );
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .include_execution_metrics(true)
+        .build()
+        .unwrap();
+    let envelope = tokio::time::timeout(Duration::from_secs(10), listener.stream().next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let metrics = envelope.context.metrics.unwrap();
+    assert_eq!(metrics.gas_burnt, 3_000_000_000_000);
+    assert_eq!(metrics.tokens_burnt, 300_000_000_000_000_000_000);
+    assert_eq!(metrics.receipts, 2);
+
+    let serialized = serde_json::to_value(&envelope.context).unwrap();
+    assert_eq!(
+        serialized["metrics"]["tokens_burnt"],
+        "300000000000000000000"
+    );
+    assert!(serialized.get("receipt").is_none());
+}
+
+#[tokio::test]
+async fn test_failed_calls_are_reported() {
+    let mock = MockBlockSource::start().await.unwrap();
+    let tx_hash = mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").failed(),
+    );
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("bob.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+
+    let failures = Arc::new(Mutex::new(Vec::new()));
+    let handler_failures = failures.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(10)
+        .catch_up_interval(Duration::from_millis(10))
+        .on_failure(move |failure| handler_failures.lock().unwrap().push(failure))
+        .build()
+        .unwrap();
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let callback_delivered = delivered.clone();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start(move |envelope| callback_delivered.lock().unwrap().push(envelope)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(delivered.lock().unwrap().len(), 1);
+    let failures = failures.lock().unwrap();
+    assert_eq!(failures.len(), 1);
+    let failure = &failures[0];
+    assert_eq!(failure.context.tx_hash, Some(tx_hash.to_string()));
+    assert_eq!(failure.context.signer_id, "alice.near");
+    assert_eq!(failure.context.method_name.as_deref(), Some("nft_mint"));
+    assert_eq!(
+        failure.context.execution_status,
+        Some(ExecutionStatus::Failure)
+    );
+    assert!(
+        failure.error.contains("Smart contract panicked"),
+        "{}",
+        failure.error
+    );
+}
+
+#[tokio::test]
+async fn test_multi_network_listener_tags_events() {
+    let mainnet = MockBlockSource::start().await.unwrap();
+    mainnet.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    let testnet = MockBlockSource::start().await.unwrap();
+    testnet.add_function_call(
+        20,
+        MockFunctionCall::new("bob.near", "nft.near", "nft_mint").event(&nft_mint("2")),
+    );
+    let listener = |mock: &MockBlockSource, height: u64| {
+        NearEventListener::builder(mock.url())
+            .account_id("nft.near")
+            .last_processed_block(height - 1)
+            .until_height(height)
+            .catch_up_interval(Duration::from_millis(10))
+            .build()
+            .unwrap()
+    };
+
+    let mut events = Vec::new();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        MultiNetworkListener::new()
+            .add("mainnet", listener(&mainnet, 10))
+            .add("testnet", listener(&testnet, 20))
+            .start(|event| events.push(event)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    events.sort_by(|a, b| a.network.cmp(&b.network));
+    let tagged: Vec<_> = events
+        .iter()
+        .map(|event| (event.network.as_str(), event.envelope.event.clone()))
+        .collect();
+    assert_eq!(
+        tagged,
+        vec![("mainnet", nft_mint("1")), ("testnet", nft_mint("2"))]
+    );
+}
+
+#[tokio::test]
+async fn test_expect_chain_rejects_other_networks() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    let listener = |chain_id: &str| {
+        NearEventListener::builder(mock.url())
+            .account_id("nft.near")
+            .last_processed_block(9)
+            .until_height(10)
+            .catch_up_interval(Duration::from_millis(10))
+            .expect_chain(chain_id)
+            .build()
+            .unwrap()
+    };
+
+    let mut mainnet = listener("mainnet");
+    let result = tokio::time::timeout(Duration::from_secs(10), mainnet.start(|_| {}))
+        .await
+        .unwrap();
+    assert!(
+        matches!(result, Err(ListenerError::InvalidConfig(message)) if message.contains("`mock`"))
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let delivered = events.clone();
+    let mut mock_chain = listener("mock");
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        mock_chain.start(move |envelope| delivered.lock().unwrap().push(envelope.event)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(*events.lock().unwrap(), vec![nft_mint("1")]);
+}
+
+#[tokio::test]
+async fn test_unhealthy_node_fails_over_to_fallback() {
+    let primary = MockBlockSource::start().await.unwrap();
+    primary.add_empty_block(10);
+    primary.set_syncing(true);
+    let fallback = MockBlockSource::start().await.unwrap();
+    fallback.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let reported = errors.clone();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let delivered = events.clone();
+    let mut listener = NearEventListener::builder(primary.url())
+        .fallback_rpc_url(fallback.url())
+        .health_check(HealthCheck::new(Duration::from_secs(60)))
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(10)
+        .catch_up_interval(Duration::from_millis(10))
+        .on_error(move |err| {
+            reported.lock().unwrap().push(err.to_string());
+            ErrorAction::Continue
+        })
+        .build()
+        .unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start(move |envelope| delivered.lock().unwrap().push(envelope.event)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(*events.lock().unwrap(), vec![nft_mint("1")]);
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains(primary.url()) && errors[0].contains("is syncing"));
+    assert_eq!(listener.client.server_addr(), fallback.url());
+}
+
+#[tokio::test]
+async fn test_stale_node_is_reported() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_empty_block(10);
+    mock.set_head_age(Duration::from_secs(600));
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .health_check(HealthCheck::new(Duration::from_secs(60)))
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(10)
+        .on_error(|_| ErrorAction::Stop)
+        .build()
+        .unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(10), listener.start(|_| {}))
+        .await
+        .unwrap();
+    assert!(
+        matches!(result, Err(ListenerError::UnhealthyNode(message)) if message.contains("stale head"))
+    );
+}
+
+#[tokio::test]
+async fn test_hung_provider_times_out() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_empty_block(10);
+    mock.set_response_delay(Duration::from_secs(30));
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .request_timeout(Duration::from_millis(50))
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .on_error(|_| ErrorAction::Stop)
+        .build()
+        .unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(10), listener.start(|_| {}))
+        .await
+        .unwrap();
+    assert!(matches!(result, Err(ListenerError::Timeout)));
+
+    // Retries would go on for far longer than the deadline allows.
+    let started = std::time::Instant::now();
+    let mut listener = NearEventListener::builder(mock.url())
+        .request_timeout(Duration::from_millis(50))
+        .call_deadline(Duration::from_millis(300))
+        .retry_policy(RetryPolicy::new(100).initial_backoff(Duration::from_millis(10)))
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .on_error(|_| ErrorAction::Stop)
+        .build()
+        .unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(10), listener.start(|_| {}))
+        .await
+        .unwrap();
+    assert!(matches!(result, Err(ListenerError::Timeout)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_lifecycle_hooks_follow_the_run() {
+    let mock = Arc::new(MockBlockSource::start().await.unwrap());
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_empty_block(11);
+
+    let hooks = Arc::new(Mutex::new(Vec::new()));
+    let (on_start, on_block_start, on_block_end, on_catch_up, on_stop) = (
+        hooks.clone(),
+        hooks.clone(),
+        hooks.clone(),
+        hooks.clone(),
+        hooks.clone(),
+    );
+    let chain = mock.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(12)
+        .catch_up_interval(Duration::from_millis(10))
+        .poll_interval(Duration::from_millis(10))
+        .on_start(move |height| on_start.lock().unwrap().push(format!("start {}", height)))
+        .on_block_start(move |block| {
+            let hook = format!("block start {}", block.height);
+            on_block_start.lock().unwrap().push(hook);
+        })
+        .on_block_end(move |block| {
+            let hook = format!("block end {} ({})", block.height, block.events_count);
+            on_block_end.lock().unwrap().push(hook);
+        })
+        .on_catch_up_complete(move |height| {
+            on_catch_up
+                .lock()
+                .unwrap()
+                .push(format!("caught up {}", height));
+            // The chain moves on once the listener is at its head.
+            chain.add_empty_block(12);
+        })
+        .on_stop(move |err| {
+            on_stop
+                .lock()
+                .unwrap()
+                .push(format!("stop {:?}", err.is_some()))
+        })
+        .build()
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(10), listener.start(|_| {}))
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        *hooks.lock().unwrap(),
+        vec![
+            "start 10",
+            "block start 10",
+            "block end 10 (1)",
+            "block start 11",
+            "block end 11 (0)",
+            "caught up 11",
+            "block start 12",
+            "block end 12 (0)",
+            "stop false",
+        ]
+    );
+}
+
+#[derive(Default)]
+struct CountingRuntime {
+    sleeps: Arc<Mutex<usize>>,
+    spawns: Arc<Mutex<usize>>,
+}
+
+impl Runtime for CountingRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        *self.sleeps.lock().unwrap() += 1;
+        TokioRuntime.sleep(duration)
+    }
+
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        *self.spawns.lock().unwrap() += 1;
+        TokioRuntime.spawn(task)
+    }
+}
+
+#[tokio::test]
+async fn test_custom_runtime_runs_timers_and_tasks() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_empty_block(11);
+
+    let runtime = CountingRuntime::default();
+    let (sleeps, spawns) = (runtime.sleeps.clone(), runtime.spawns.clone());
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let delivered = events.clone();
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .until_height(11)
+        .catch_up_interval(Duration::from_millis(10))
+        .delivery_queue(10, OverflowPolicy::Block)
+        .runtime(runtime)
+        .build()
+        .unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        listener.start(move |envelope| delivered.lock().unwrap().push(envelope.event)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(*events.lock().unwrap(), vec![nft_mint("1")]);
+    assert_eq!(*spawns.lock().unwrap(), 1);
+    assert!(*sleeps.lock().unwrap() >= 1);
+}
+
+#[tokio::test]
+async fn test_handle_stops_a_running_listener() {
+    let mock = MockBlockSource::start().await.unwrap();
+    mock.add_function_call(
+        10,
+        MockFunctionCall::new("alice.near", "nft.near", "nft_mint").event(&nft_mint("1")),
+    );
+    mock.add_empty_block(11);
+
+    let mut listener = NearEventListener::builder(mock.url())
+        .account_id("nft.near")
+        .last_processed_block(9)
+        .catch_up_interval(Duration::from_millis(10))
+        .poll_interval(Duration::from_secs(600))
+        .build()
+        .unwrap();
+    let handle = listener.handle();
+    let running = tokio::spawn(async move { listener.start(|_| {}).await.map(|()| listener) });
+
+    // The listener keeps polling at the head; the handle reports its progress
+    // while it runs.
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while handle.status().last_processed_block < 11 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(handle.status().events_emitted, 1);
+
+    handle.stop();
+    let listener = tokio::time::timeout(Duration::from_secs(10), running)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(handle.is_stopped());
+    assert_eq!(listener.status().state, ListenerState::Stopped);
+    assert_eq!(listener.status().last_processed_block, 11);
+}
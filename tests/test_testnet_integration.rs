@@ -126,50 +126,24 @@ async fn test_integration_using_testnet() -> anyhow::Result<()> {
         .set_greeting("Hello from testnet!".to_string())
         .await?;
 
-    // Obtenemos el block height de la transacción
-    let block_height = if let Some(final_execution) = &tx_result.final_execution_outcome {
-        match final_execution {
-            near_primitives::views::FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(
-                outcome,
-            ) => {
-                let block = contract_wrapper
-                    .rpc_client
-                    .call(methods::block::RpcBlockRequest {
-                        block_reference: BlockReference::BlockId(
-                            near_primitives::types::BlockId::Hash(
-                                outcome.transaction_outcome.block_hash,
-                            ),
-                        ),
-                    })
-                    .await?;
-                block.header.height
-            }
-            _ => 0,
-        }
-    } else {
-        0
+    let Some(near_primitives::views::FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome)) =
+        &tx_result.final_execution_outcome
+    else {
+        anyhow::bail!("Missing transaction outcome");
     };
 
-    // Configuramos el canal para recibir eventos
-    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
-    let tx_clone = tx.clone();
-
     // Inicializamos el listener
-    let mut listener = NearEventListener::builder("https://rpc.testnet.near.org")
+    let listener = NearEventListener::builder("https://rpc.testnet.near.org")
         .account_id(&contract_wrapper.contract_id)
         .method_name("set_greeting")
-        .last_processed_block(block_height - 1)
+        .start_after_tx(
+            &outcome.transaction.hash.to_string(),
+            outcome.transaction.signer_id.as_str(),
+        )
         .build()?;
 
     // Iniciamos el listener en un task separado
-    let listener_handle = tokio::spawn(async move {
-        listener
-            .start(move |event_log| {
-                println!("Captured event: {:?}", event_log);
-                let _ = tx_clone.try_send(event_log.clone());
-            })
-            .await
-    });
+    let (mut rx, listener_handle) = listener.channel(1);
 
     // Definimos el evento esperado
     let expected_event = EventLog {
@@ -188,8 +162,9 @@ async fn test_integration_using_testnet() -> anyhow::Result<()> {
         .ok_or_else(|| anyhow::anyhow!("Channel closed"))?;
 
     // Verificamos que el evento recibido coincida con el esperado
+    assert_eq!(received_event.context.account_id, contract_wrapper.contract_id);
     assert_eq!(
-        received_event, expected_event,
+        received_event.event, expected_event,
         "Received event does not match expected event"
     );
 
@@ -0,0 +1,186 @@
+#![cfg(feature = "webhook")]
+
+use near_event_listener::{
+    EventContext, EventEnvelope, EventLog, ListenerError, RetryPolicy, WebhookMetrics, WebhookSink,
+};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+struct Request {
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// Answers one request per connection with the given statuses, in order.
+async fn serve(statuses: Vec<u16>) -> (String, JoinHandle<Vec<Request>>) {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/events", server.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for status in statuses {
+            let (stream, _) = server.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut headers = Vec::new();
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).await.unwrap();
+                match line.trim_end().split_once(": ") {
+                    Some((name, value)) => headers.push((name.to_string(), value.to_string())),
+                    None => break,
+                }
+            }
+
+            let length = headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .map_or(0, |(_, value)| value.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+
+            let response = format!(
+                "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            reader
+                .into_inner()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+            requests.push(Request { headers, body });
+        }
+        requests
+    });
+
+    (url, handle)
+}
+
+fn envelope() -> EventEnvelope {
+    EventEnvelope {
+        context: EventContext {
+            account_id: "nft.near".to_string(),
+            block_height: 10,
+            block_hash: "hash".to_string(),
+            block_timestamp: 0,
+            tx_hash: None,
+            signer_id: "alice.near".to_string(),
+            receiver_id: "nft.near".to_string(),
+            method_name: None,
+            deposit: None,
+            gas: None,
+            execution_status: None,
+            receipt_id: None,
+            log_index: 0,
+            sequence: None,
+            late: false,
+            receipt: None,
+            metrics: None,
+        },
+        event: EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: "nft_mint".to_string(),
+            data: json!([{ "owner_id": "alice.near", "token_ids": ["1"] }]),
+        },
+    }
+}
+
+fn fast_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::new(max_attempts)
+        .initial_backoff(Duration::from_millis(1))
+        .jitter(false)
+}
+
+#[tokio::test]
+async fn test_webhook_retries_and_signs_payload() {
+    let (url, server) = serve(vec![503, 200]).await;
+    let retried = Arc::new(Mutex::new(Vec::new()));
+    let sink = WebhookSink::new(&url)
+        .unwrap()
+        .secret("s3cret")
+        .retry_policy(fast_retries(3))
+        .on_retry({
+            let retried = retried.clone();
+            move |attempt, err| retried.lock().unwrap().push((attempt, err.to_string()))
+        });
+
+    sink.send(&envelope()).await.unwrap();
+
+    let retried = retried.lock().unwrap().clone();
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].0, 1);
+    assert!(retried[0].1.contains("503"));
+
+    let requests = server.await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let delivered: EventEnvelope = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(delivered, envelope());
+    assert_eq!(
+        requests[1].header("X-Signature-256"),
+        Some(WebhookSink::signature(b"s3cret", &requests[1].body).as_str())
+    );
+    assert_eq!(
+        sink.metrics(),
+        WebhookMetrics {
+            delivered: 1,
+            failed: 0,
+            retries: 1,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_webhook_does_not_retry_client_errors() {
+    let (url, server) = serve(vec![400]).await;
+    let sink = WebhookSink::new(&url)
+        .unwrap()
+        .retry_policy(fast_retries(3));
+
+    let result = sink.send(&envelope()).await;
+
+    match result {
+        Err(ListenerError::WebhookError(message)) => {
+            assert_eq!(message, "webhook responded with 400 Bad Request");
+            assert!(!message.contains(&url));
+        }
+        other => panic!("expected a webhook error, got {:?}", other),
+    }
+    let requests = server.await.unwrap();
+    assert_eq!(requests[0].header("X-Signature-256"), None);
+    assert_eq!(sink.metrics().failed, 1);
+    assert_eq!(sink.metrics().retries, 0);
+}
+
+#[cfg(feature = "templates")]
+#[tokio::test]
+async fn test_webhook_sends_rendered_template() {
+    let (url, server) = serve(vec![200]).await;
+    let sink = WebhookSink::new(&url).unwrap().template(
+        near_event_listener::EventTemplate::new(
+            r#"{"text": {{ (event ~ " on " ~ account_id) | tojson }}}"#,
+        )
+        .unwrap(),
+    );
+
+    sink.send(&envelope()).await.unwrap();
+
+    let requests = server.await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body, json!({"text": "nft_mint on nft.near"}));
+}
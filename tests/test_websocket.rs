@@ -0,0 +1,64 @@
+#![cfg(feature = "websocket")]
+
+use futures::{SinkExt, StreamExt};
+use near_event_listener::{DataSource, ErrorAction, NearEventListener};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+async fn test_websocket_announcement_triggers_poll() {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", server.local_addr().unwrap());
+
+    let announcer = tokio::spawn(async move {
+        let (stream, _) = server.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+        let subscription = socket.next().await.unwrap().unwrap();
+        socket
+            .send(Message::Text(r#"{"height":1}"#.to_string()))
+            .await
+            .unwrap();
+        // Keep the connection open until the listener is done with it.
+        let _ = socket.next().await;
+        subscription
+    });
+
+    let polls = Arc::new(AtomicUsize::new(0));
+    let handler_polls = polls.clone();
+
+    // The poll interval is far longer than the test timeout, so the second
+    // poll can only come from the announcement.
+    let mut listener = NearEventListener::builder("http://127.0.0.1:1")
+        .source(DataSource::WebSocket {
+            url,
+            subscribe_message: Some(r#"{"method":"subscribe","params":["block"]}"#.to_string()),
+        })
+        .account_id("test.near")
+        .poll_interval(Duration::from_secs(600))
+        .on_error(move |_err| {
+            if handler_polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                ErrorAction::Continue
+            } else {
+                ErrorAction::Stop
+            }
+        })
+        .build()
+        .unwrap();
+
+    let result = tokio::time::timeout(Duration::from_secs(10), listener.start(|_envelope| {}))
+        .await
+        .expect("announcement should wake the listener");
+    assert!(result.is_err());
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+
+    drop(listener);
+    let subscription = announcer.await.unwrap();
+    assert_eq!(
+        subscription,
+        Message::Text(r#"{"method":"subscribe","params":["block"]}"#.to_string())
+    );
+}